@@ -0,0 +1,299 @@
+use rusqlite::{Connection, Result, Row};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::character::Character;
+use crate::storage;
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Ability {
+    Strength,
+    Dexterity,
+    Constitution,
+    Intelligence,
+    Wisdom,
+    Charisma,
+}
+
+impl Ability {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Ability::Strength => "strength",
+            Ability::Dexterity => "dexterity",
+            Ability::Constitution => "constitution",
+            Ability::Intelligence => "intelligence",
+            Ability::Wisdom => "wisdom",
+            Ability::Charisma => "charisma",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "strength" => Ok(Ability::Strength),
+            "dexterity" => Ok(Ability::Dexterity),
+            "constitution" => Ok(Ability::Constitution),
+            "intelligence" => Ok(Ability::Intelligence),
+            "wisdom" => Ok(Ability::Wisdom),
+            "charisma" => Ok(Ability::Charisma),
+            _ => Err(format!("Unknown ability: {}", value)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct AbilityScores {
+    pub strength: i32,
+    pub dexterity: i32,
+    pub constitution: i32,
+    pub intelligence: i32,
+    pub wisdom: i32,
+    pub charisma: i32,
+}
+
+// Characters created before ability scores were tracked are all-round average.
+impl Default for AbilityScores {
+    fn default() -> Self {
+        AbilityScores {
+            strength: 10,
+            dexterity: 10,
+            constitution: 10,
+            intelligence: 10,
+            wisdom: 10,
+            charisma: 10,
+        }
+    }
+}
+
+impl AbilityScores {
+    pub fn score(&self, ability: Ability) -> i32 {
+        match ability {
+            Ability::Strength => self.strength,
+            Ability::Dexterity => self.dexterity,
+            Ability::Constitution => self.constitution,
+            Ability::Intelligence => self.intelligence,
+            Ability::Wisdom => self.wisdom,
+            Ability::Charisma => self.charisma,
+        }
+    }
+
+    // Rounds down, so a score of 9 gives -1.
+    pub fn modifier(&self, ability: Ability) -> i32 {
+        (self.score(ability) - 10).div_euclid(2)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for ability in [
+            Ability::Strength,
+            Ability::Dexterity,
+            Ability::Constitution,
+            Ability::Intelligence,
+            Ability::Wisdom,
+            Ability::Charisma,
+        ] {
+            let score = self.score(ability);
+            if !(1..=30).contains(&score) {
+                return Err(format!(
+                    "{} must be between 1 and 30, got {}",
+                    ability.as_str(),
+                    score
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn from_row(row: &Row) -> Result<Self> {
+        Ok(AbilityScores {
+            strength: row.get("strength")?,
+            dexterity: row.get("dexterity")?,
+            constitution: row.get("constitution")?,
+            intelligence: row.get("intelligence")?,
+            wisdom: row.get("wisdom")?,
+            charisma: row.get("charisma")?,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Skill {
+    Acrobatics,
+    AnimalHandling,
+    Arcana,
+    Athletics,
+    Deception,
+    History,
+    Insight,
+    Intimidation,
+    Investigation,
+    Medicine,
+    Nature,
+    Perception,
+    Performance,
+    Persuasion,
+    Religion,
+    SleightOfHand,
+    Stealth,
+    Survival,
+}
+
+impl Skill {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Skill::Acrobatics => "acrobatics",
+            Skill::AnimalHandling => "animal_handling",
+            Skill::Arcana => "arcana",
+            Skill::Athletics => "athletics",
+            Skill::Deception => "deception",
+            Skill::History => "history",
+            Skill::Insight => "insight",
+            Skill::Intimidation => "intimidation",
+            Skill::Investigation => "investigation",
+            Skill::Medicine => "medicine",
+            Skill::Nature => "nature",
+            Skill::Perception => "perception",
+            Skill::Performance => "performance",
+            Skill::Persuasion => "persuasion",
+            Skill::Religion => "religion",
+            Skill::SleightOfHand => "sleight_of_hand",
+            Skill::Stealth => "stealth",
+            Skill::Survival => "survival",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "acrobatics" => Ok(Skill::Acrobatics),
+            "animal_handling" => Ok(Skill::AnimalHandling),
+            "arcana" => Ok(Skill::Arcana),
+            "athletics" => Ok(Skill::Athletics),
+            "deception" => Ok(Skill::Deception),
+            "history" => Ok(Skill::History),
+            "insight" => Ok(Skill::Insight),
+            "intimidation" => Ok(Skill::Intimidation),
+            "investigation" => Ok(Skill::Investigation),
+            "medicine" => Ok(Skill::Medicine),
+            "nature" => Ok(Skill::Nature),
+            "perception" => Ok(Skill::Perception),
+            "performance" => Ok(Skill::Performance),
+            "persuasion" => Ok(Skill::Persuasion),
+            "religion" => Ok(Skill::Religion),
+            "sleight_of_hand" => Ok(Skill::SleightOfHand),
+            "stealth" => Ok(Skill::Stealth),
+            "survival" => Ok(Skill::Survival),
+            _ => Err(format!("Unknown skill: {}", value)),
+        }
+    }
+
+    pub fn ability(&self) -> Ability {
+        match self {
+            Skill::Athletics => Ability::Strength,
+            Skill::Acrobatics | Skill::SleightOfHand | Skill::Stealth => Ability::Dexterity,
+            Skill::Arcana
+            | Skill::History
+            | Skill::Investigation
+            | Skill::Nature
+            | Skill::Religion => Ability::Intelligence,
+            Skill::AnimalHandling
+            | Skill::Insight
+            | Skill::Medicine
+            | Skill::Perception
+            | Skill::Survival => Ability::Wisdom,
+            Skill::Deception | Skill::Intimidation | Skill::Performance | Skill::Persuasion => {
+                Ability::Charisma
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct SkillProficiency {
+    pub skill: Skill,
+    // Doubles the proficiency bonus.
+    #[serde(default)]
+    pub expertise: bool,
+}
+
+// Saving throw and skill proficiencies live in their own tables and are read together.
+pub struct Proficiencies;
+
+impl Proficiencies {
+    pub(crate) fn save_all(
+        character: &Character,
+        connection: &Connection,
+    ) -> Result<(), rusqlite::Error> {
+        let character_id = character.id.to_string();
+        connection.execute(
+            "DELETE FROM character_saving_throws WHERE character_id = ?1",
+            rusqlite::params![character_id],
+        )?;
+        connection.execute(
+            "DELETE FROM character_skills WHERE character_id = ?1",
+            rusqlite::params![character_id],
+        )?;
+
+        for ability in &character.saving_throw_proficiencies {
+            connection.execute(
+                "INSERT OR IGNORE INTO character_saving_throws (character_id, ability) VALUES (?1, ?2)",
+                rusqlite::params![character_id, ability.as_str()],
+            )?;
+        }
+        for skill_proficiency in &character.skill_proficiencies {
+            connection.execute(
+                "INSERT OR REPLACE INTO character_skills (character_id, skill, expertise) VALUES (?1, ?2, ?3)",
+                rusqlite::params![
+                    character_id,
+                    skill_proficiency.skill.as_str(),
+                    skill_proficiency.expertise
+                ],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // Loads the proficiencies of many characters at once, like their class levels.
+    pub fn load_for<'a, I: IntoIterator<Item = &'a mut Character>>(
+        characters: I,
+        connection: &Connection,
+    ) -> Result<(), rusqlite::Error> {
+        let characters: Vec<&mut Character> = characters.into_iter().collect();
+        let character_ids: Vec<Uuid> = characters.iter().map(|character| character.id).collect();
+
+        let mut saving_throws = storage::load_grouped_by_id(
+            connection,
+            "character_saving_throws",
+            "character_id",
+            "rowid",
+            &character_ids,
+            |row| {
+                let ability: String = row.get("ability")?;
+                Ok(Ability::parse(&ability).unwrap())
+            },
+        )?;
+        let mut skills = storage::load_grouped_by_id(
+            connection,
+            "character_skills",
+            "character_id",
+            "rowid",
+            &character_ids,
+            |row| {
+                let skill: String = row.get("skill")?;
+                Ok(SkillProficiency {
+                    skill: Skill::parse(&skill).unwrap(),
+                    expertise: row.get("expertise")?,
+                })
+            },
+        )?;
+
+        for character in characters {
+            character.saving_throw_proficiencies =
+                saving_throws.remove(&character.id).unwrap_or_default();
+            character.skill_proficiencies = skills.remove(&character.id).unwrap_or_default();
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,358 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use uuid::Uuid;
+
+use dm_companion_lib::character::{
+    Character, CharacterQuery, CharacterSort, ClassLevel, SortDirection,
+};
+use dm_companion_lib::configuration::{Configuration, ConfigurationOverrides};
+use dm_companion_lib::dice::DiceExpression;
+use dm_companion_lib::encounter::{self, EncounterDetail};
+use dm_companion_lib::import::ImportReport;
+use dm_companion_lib::repository::{CharacterRepository, EncounterRepository, SqliteRepository};
+use dm_companion_lib::storage;
+
+#[derive(Parser)]
+#[command(
+    name = "dm-companion-cli",
+    version,
+    about = "Manage the DM Companion database from the terminal"
+)]
+struct Cli {
+    /// Directory holding the configuration file and databases
+    #[arg(long, global = true)]
+    data_dir: Option<PathBuf>,
+    /// Database profile to use instead of the active one
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List, search, create and import characters
+    Characters {
+        #[command(subcommand)]
+        command: CharacterCommand,
+    },
+    /// List, create and run encounters
+    Encounters {
+        #[command(subcommand)]
+        command: EncounterCommand,
+    },
+    /// Roll a dice expression such as 2d6+3
+    Roll { expression: String },
+    /// Export all characters and encounters as JSON
+    Export {
+        /// Write the export to a file instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CharacterCommand {
+    List,
+    Create {
+        #[arg(long)]
+        name: String,
+        /// Class as CLASS[/SUBCLASS][:LEVELS], repeat for multiclass characters
+        #[arg(long = "class", required = true, value_parser = parse_class_level)]
+        classes: Vec<ClassLevel>,
+        #[arg(long)]
+        race: String,
+        #[arg(long)]
+        background: Option<String>,
+        #[arg(long, default_value_t = 0)]
+        experience: i32,
+        #[arg(long)]
+        hit_points: i32,
+        #[arg(long)]
+        armor_class: i32,
+        #[arg(long, default_value = "")]
+        notes: String,
+        #[arg(long)]
+        campaign: Option<String>,
+    },
+    /// Search characters by name and notes, with filters, sorting and pages
+    Search {
+        /// Words to look for in names and notes
+        text: Option<String>,
+        #[arg(long)]
+        class: Option<String>,
+        #[arg(long)]
+        race: Option<String>,
+        #[arg(long)]
+        min_level: Option<i32>,
+        #[arg(long)]
+        max_level: Option<i32>,
+        #[arg(long)]
+        alive: Option<bool>,
+        #[arg(long)]
+        campaign: Option<String>,
+        #[arg(long, value_enum, default_value = "name")]
+        sort: SortArg,
+        #[arg(long)]
+        descending: bool,
+        #[arg(long, default_value_t = 1)]
+        page: u32,
+        #[arg(long, default_value_t = 50)]
+        page_size: u32,
+    },
+    /// Import characters from a D&D Beyond, Foundry VTT or dm-companion JSON export
+    Import {
+        path: PathBuf,
+        /// Report what would be imported without saving anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Clone, ValueEnum)]
+enum SortArg {
+    Name,
+    Class,
+    Race,
+    Level,
+    Created,
+    Updated,
+}
+
+#[derive(Subcommand)]
+enum EncounterCommand {
+    List,
+    Create {
+        title: String,
+    },
+    Show {
+        encounter_id: Uuid,
+    },
+    /// Add a character to an encounter
+    Add {
+        encounter_id: Uuid,
+        character_id: Uuid,
+        #[arg(long)]
+        initiative: Option<i32>,
+    },
+    /// Advance to the next turn, starting round 1 on the first call
+    NextTurn {
+        encounter_id: Uuid,
+    },
+}
+
+#[derive(Serialize)]
+struct Export {
+    characters: Vec<Character>,
+    encounters: Vec<EncounterDetail>,
+}
+
+// Accepts "Fighter", "Fighter:3" or "Fighter/Champion:3".
+fn parse_class_level(value: &str) -> Result<ClassLevel, String> {
+    let (class, levels) = match value.rsplit_once(':') {
+        Some((class, levels)) => (
+            class,
+            levels
+                .trim()
+                .parse::<i32>()
+                .map_err(|_| format!("Invalid class levels: {}", levels))?,
+        ),
+        None => (value, 1),
+    };
+    let (class, subclass) = match class.split_once('/') {
+        Some((class, subclass)) => (class, Some(String::from(subclass.trim()))),
+        None => (class, None),
+    };
+
+    Ok(ClassLevel::new(
+        String::from(class.trim()),
+        subclass,
+        levels,
+    ))
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).map_err(|e| e.to_string())?
+    );
+
+    Ok(())
+}
+
+fn run_character_command(
+    repository: &SqliteRepository,
+    command: CharacterCommand,
+) -> Result<(), String> {
+    match command {
+        CharacterCommand::List => print_json(&repository.load_characters()?),
+        CharacterCommand::Create {
+            name,
+            classes,
+            race,
+            background,
+            experience,
+            hit_points,
+            armor_class,
+            notes,
+            campaign,
+        } => {
+            let mut character = Character::new(
+                name,
+                classes[0].class.clone(),
+                race,
+                background,
+                classes[0].levels,
+                experience,
+                hit_points,
+                armor_class,
+                notes,
+            );
+            character.set_class_levels(classes);
+            character.campaign = campaign;
+            repository.save_character(&character)?;
+
+            print_json(&character)
+        }
+        CharacterCommand::Search {
+            text,
+            class,
+            race,
+            min_level,
+            max_level,
+            alive,
+            campaign,
+            sort,
+            descending,
+            page,
+            page_size,
+        } => {
+            let query = CharacterQuery {
+                search: text,
+                class,
+                race,
+                min_level,
+                max_level,
+                alive,
+                campaign,
+                sort_by: match sort {
+                    SortArg::Name => CharacterSort::Name,
+                    SortArg::Class => CharacterSort::Class,
+                    SortArg::Race => CharacterSort::Race,
+                    SortArg::Level => CharacterSort::Level,
+                    SortArg::Created => CharacterSort::CreatedAt,
+                    SortArg::Updated => CharacterSort::UpdatedAt,
+                },
+                sort_direction: if descending {
+                    SortDirection::Descending
+                } else {
+                    SortDirection::Ascending
+                },
+                page,
+                page_size,
+            };
+
+            print_json(&repository.query_characters(&query)?)
+        }
+        CharacterCommand::Import { path, dry_run } => {
+            let json = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+            let mut report = ImportReport::parse(&json)?;
+            if !dry_run {
+                let mut conn = repository.connection()?;
+                report.save(&mut conn).map_err(|e| e.to_string())?;
+            }
+
+            print_json(&report)
+        }
+    }
+}
+
+fn run_encounter_command(
+    repository: &SqliteRepository,
+    command: EncounterCommand,
+) -> Result<(), String> {
+    match command {
+        EncounterCommand::List => print_json(&repository.load_encounters()?),
+        EncounterCommand::Create { title } => {
+            print_json(&encounter::create_encounter(repository, title, None)?)
+        }
+        EncounterCommand::Show { encounter_id } => print_json(
+            &repository
+                .load_encounter_detail(encounter_id)?
+                .ok_or(format!("Encounter {} not found", encounter_id))?,
+        ),
+        EncounterCommand::Add {
+            encounter_id,
+            character_id,
+            initiative,
+        } => print_json(&encounter::add_character_to_encounter(
+            repository,
+            encounter_id,
+            character_id,
+            initiative,
+        )?),
+        EncounterCommand::NextTurn { encounter_id } => {
+            let encounter_detail = encounter::advance_turn(repository, encounter_id)?;
+
+            print_json(&encounter_detail.active_participant())
+        }
+    }
+}
+
+fn export(repository: &SqliteRepository, output: Option<PathBuf>) -> Result<(), String> {
+    let mut encounters = Vec::new();
+    for encounter in repository.load_encounters()? {
+        encounters.extend(repository.load_encounter_detail(encounter.id)?);
+    }
+
+    let export = Export {
+        characters: repository.load_characters()?,
+        encounters,
+    };
+
+    match output {
+        Some(path) => std::fs::write(
+            &path,
+            serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?,
+        )
+        .map_err(|e| e.to_string()),
+        None => print_json(&export),
+    }
+}
+
+fn open_repository(overrides: ConfigurationOverrides) -> Result<SqliteRepository, String> {
+    let configuration = Configuration::init_with(overrides)?;
+    let db_pool = storage::setup_database(&configuration)?;
+
+    Ok(SqliteRepository::new(&db_pool))
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    let env_overrides = ConfigurationOverrides::from_env();
+    let overrides = ConfigurationOverrides {
+        data_directory: cli.data_dir.or(env_overrides.data_directory),
+        profile: cli.profile.or(env_overrides.profile),
+    };
+
+    match cli.command {
+        Command::Characters { command } => {
+            run_character_command(&open_repository(overrides)?, command)
+        }
+        Command::Encounters { command } => {
+            run_encounter_command(&open_repository(overrides)?, command)
+        }
+        Command::Roll { expression } => {
+            print_json(&DiceExpression::parse(&expression)?.roll(&mut rand::thread_rng()))
+        }
+        Command::Export { output } => export(&open_repository(overrides)?, output),
+    }
+}
+
+fn main() {
+    if let Err(e) = run(Cli::parse()) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,589 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime, State};
+use uuid::Uuid;
+
+use crate::events::{self, DomainEvent};
+use crate::storage::Database;
+
+const MINUTES_PER_HOUR: i64 = 60;
+const MINUTES_PER_DAY: i64 = 24 * MINUTES_PER_HOUR;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Month {
+    pub name: String,
+    pub days: u32,
+}
+
+// In-game times are stored as minutes since midnight on the first day of year 1 and only turned
+// into dates through a calendar, so switching calendars never rewrites stored times.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Calendar {
+    pub id: Uuid,
+    pub name: String,
+    pub months: Vec<Month>,
+    pub weekdays: Vec<String>,
+    pub created_at_utc: DateTime<Utc>,
+    pub updated_at_utc: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct DateInput {
+    pub year: i64,
+    // Months and days start at 1.
+    pub month: u32,
+    pub day: u32,
+    #[serde(default)]
+    pub hour: u32,
+    #[serde(default)]
+    pub minute: u32,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct InGameDate {
+    pub year: i64,
+    pub month: u32,
+    pub month_name: String,
+    pub day: u32,
+    pub weekday: String,
+    pub hour: u32,
+    pub minute: u32,
+}
+
+impl Calendar {
+    pub fn new(name: String, months: Vec<Month>, weekdays: Vec<String>) -> Result<Self, String> {
+        if months.is_empty() {
+            return Err(String::from("A calendar needs at least one month"));
+        }
+        if let Some(month) = months.iter().find(|month| month.days == 0) {
+            return Err(format!("{} needs at least one day", month.name));
+        }
+        if weekdays.is_empty() {
+            return Err(String::from("A calendar needs at least one weekday"));
+        }
+
+        Ok(Calendar {
+            id: Uuid::new_v4(),
+            name,
+            months,
+            weekdays,
+            created_at_utc: Utc::now(),
+            updated_at_utc: Utc::now(),
+        })
+    }
+
+    pub fn days_per_year(&self) -> i64 {
+        self.months.iter().map(|month| month.days as i64).sum()
+    }
+
+    pub fn date(&self, time: i64) -> InGameDate {
+        let days = time.div_euclid(MINUTES_PER_DAY);
+        let minute_of_day = time.rem_euclid(MINUTES_PER_DAY);
+
+        let year = days.div_euclid(self.days_per_year()) + 1;
+        let mut day_of_year = days.rem_euclid(self.days_per_year());
+        let mut month = 0;
+        while day_of_year >= self.months[month].days as i64 {
+            day_of_year -= self.months[month].days as i64;
+            month += 1;
+        }
+
+        InGameDate {
+            year,
+            month: month as u32 + 1,
+            month_name: self.months[month].name.clone(),
+            day: day_of_year as u32 + 1,
+            weekday: self.weekdays[days.rem_euclid(self.weekdays.len() as i64) as usize].clone(),
+            hour: (minute_of_day / MINUTES_PER_HOUR) as u32,
+            minute: (minute_of_day % MINUTES_PER_HOUR) as u32,
+        }
+    }
+
+    pub fn time(&self, date: &DateInput) -> Result<i64, String> {
+        let month = date
+            .month
+            .checked_sub(1)
+            .and_then(|month| self.months.get(month as usize))
+            .ok_or(format!("{} has no month {}", self.name, date.month))?;
+        if date.day == 0 || date.day > month.days {
+            return Err(format!("{} has no day {}", month.name, date.day));
+        }
+        if date.hour >= 24 || date.minute >= 60 {
+            return Err(format!(
+                "{:02}:{:02} is not a time of day",
+                date.hour, date.minute
+            ));
+        }
+
+        let days_before_month: i64 = self.months[..date.month as usize - 1]
+            .iter()
+            .map(|month| month.days as i64)
+            .sum();
+        // Years come from the frontend, ones too far out to count in minutes are refused.
+        date.year
+            .checked_sub(1)
+            .and_then(|years| years.checked_mul(self.days_per_year()))
+            .and_then(|days| days.checked_add(days_before_month + date.day as i64 - 1))
+            .and_then(|days| days.checked_mul(MINUTES_PER_DAY))
+            .and_then(|minutes| {
+                minutes.checked_add(date.hour as i64 * MINUTES_PER_HOUR + date.minute as i64)
+            })
+            .ok_or(format!("Year {} is out of range", date.year))
+    }
+
+    pub fn save(&self, connection: &Connection) -> Result<(), rusqlite::Error> {
+        connection.execute(
+            "INSERT INTO calendars (id, name, created_at_utc, updated_at_utc) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                self.id.to_string(),
+                self.name,
+                self.created_at_utc.to_rfc3339(),
+                self.updated_at_utc.to_rfc3339()
+            ],
+        )?;
+
+        for (position, month) in self.months.iter().enumerate() {
+            connection.execute(
+                "INSERT INTO calendar_months (calendar_id, position, name, days) VALUES (?1, ?2, ?3, ?4)",
+                params![self.id.to_string(), position, month.name, month.days],
+            )?;
+        }
+        for (position, weekday) in self.weekdays.iter().enumerate() {
+            connection.execute(
+                "INSERT INTO calendar_weekdays (calendar_id, position, name) VALUES (?1, ?2, ?3)",
+                params![self.id.to_string(), position, weekday],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        let id: String = row.get("id")?;
+        let created_at: String = row.get("created_at_utc")?;
+        let updated_at: String = row.get("updated_at_utc")?;
+
+        Ok(Calendar {
+            id: Uuid::parse_str(&id).unwrap(),
+            name: row.get("name")?,
+            months: Vec::new(),
+            weekdays: Vec::new(),
+            created_at_utc: DateTime::parse_from_rfc3339(&created_at).unwrap().into(),
+            updated_at_utc: DateTime::parse_from_rfc3339(&updated_at).unwrap().into(),
+        })
+    }
+
+    fn load_details(&mut self, connection: &Connection) -> Result<(), rusqlite::Error> {
+        let mut statement = connection.prepare(
+            "SELECT name, days FROM calendar_months WHERE calendar_id = ?1 ORDER BY position",
+        )?;
+        self.months = statement
+            .query_map(params![self.id.to_string()], |row| {
+                Ok(Month {
+                    name: row.get("name")?,
+                    days: row.get("days")?,
+                })
+            })?
+            .collect::<Result<Vec<Month>>>()?;
+
+        let mut statement = connection.prepare(
+            "SELECT name FROM calendar_weekdays WHERE calendar_id = ?1 ORDER BY position",
+        )?;
+        self.weekdays = statement
+            .query_map(params![self.id.to_string()], |row| row.get("name"))?
+            .collect::<Result<Vec<String>>>()?;
+
+        Ok(())
+    }
+
+    pub fn load_by_id(id: Uuid, connection: &Connection) -> Result<Option<Self>> {
+        let calendar = connection
+            .query_row(
+                "SELECT * FROM calendars WHERE id = ?1",
+                params![id.to_string()],
+                Calendar::from_row,
+            )
+            .optional()?;
+
+        match calendar {
+            Some(mut calendar) => {
+                calendar.load_details(connection)?;
+                Ok(Some(calendar))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn load_all(connection: &Connection) -> Result<Vec<Self>> {
+        let mut statement =
+            connection.prepare("SELECT * FROM calendars ORDER BY name COLLATE NOCASE")?;
+        let mut calendars = statement
+            .query_map([], Calendar::from_row)?
+            .collect::<Result<Vec<Self>>>()?;
+        for calendar in calendars.iter_mut() {
+            calendar.load_details(connection)?;
+        }
+
+        Ok(calendars)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeAdvance {
+    ShortRest,
+    LongRest,
+    Travel,
+    Session,
+    Other,
+}
+
+impl TimeAdvance {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimeAdvance::ShortRest => "short_rest",
+            TimeAdvance::LongRest => "long_rest",
+            TimeAdvance::Travel => "travel",
+            TimeAdvance::Session => "session",
+            TimeAdvance::Other => "other",
+        }
+    }
+
+    // Rests take a fixed time in the Player's Handbook, anything else needs a duration.
+    pub fn minutes(&self, minutes: Option<i64>) -> Result<i64, String> {
+        let minutes = match (self, minutes) {
+            (_, Some(minutes)) => minutes,
+            (TimeAdvance::ShortRest, None) => MINUTES_PER_HOUR,
+            (TimeAdvance::LongRest, None) => 8 * MINUTES_PER_HOUR,
+            (_, None) => return Err(format!("{} needs a duration", self.as_str())),
+        };
+        if minutes <= 0 {
+            return Err(String::from("Time can only move forward"));
+        }
+
+        Ok(minutes)
+    }
+}
+
+// The campaign's current in-game time, kept in a single row.
+#[derive(Debug, Serialize, Clone)]
+pub struct Clock {
+    pub calendar: Calendar,
+    pub current_time: i64,
+}
+
+impl Clock {
+    pub fn load(connection: &Connection) -> Result<Option<Self>> {
+        let clock = connection
+            .query_row(
+                "SELECT calendar_id, current_time_minutes FROM campaign_clock WHERE id = 1",
+                [],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+            )
+            .optional()?;
+
+        let Some((calendar_id, current_time)) = clock else {
+            return Ok(None);
+        };
+        let calendar = Calendar::load_by_id(Uuid::parse_str(&calendar_id).unwrap(), connection)?;
+
+        Ok(calendar.map(|calendar| Clock {
+            calendar,
+            current_time,
+        }))
+    }
+
+    // The time to stamp new records with, None until a clock has been started.
+    pub fn current_time(connection: &Connection) -> Result<Option<i64>> {
+        connection
+            .query_row(
+                "SELECT current_time_minutes FROM campaign_clock WHERE id = 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()
+    }
+
+    pub fn save(&self, connection: &Connection) -> Result<(), rusqlite::Error> {
+        connection.execute(
+            "INSERT INTO campaign_clock (id, calendar_id, current_time_minutes) VALUES (1, ?1, ?2)
+            ON CONFLICT (id) DO UPDATE SET calendar_id = excluded.calendar_id, current_time_minutes = excluded.current_time_minutes",
+            params![self.calendar.id.to_string(), self.current_time],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn advance(&mut self, minutes: i64) -> Result<(), String> {
+        self.current_time = self.current_time.checked_add(minutes).ok_or(format!(
+            "Advancing {} minutes runs past the calendar",
+            minutes
+        ))?;
+
+        Ok(())
+    }
+
+    pub fn date(&self) -> InGameDate {
+        self.calendar.date(self.current_time)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CalendarEvent {
+    pub id: Uuid,
+    pub title: String,
+    pub description: String,
+    pub occurs_at: i64,
+    pub created_at_utc: DateTime<Utc>,
+}
+
+impl CalendarEvent {
+    pub fn new(title: String, description: String, occurs_at: i64) -> Self {
+        CalendarEvent {
+            id: Uuid::new_v4(),
+            title,
+            description,
+            occurs_at,
+            created_at_utc: Utc::now(),
+        }
+    }
+
+    pub fn save(&self, connection: &Connection) -> Result<(), rusqlite::Error> {
+        connection.execute(
+            "INSERT INTO calendar_events (id, title, description, occurs_at, created_at_utc) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                self.id.to_string(),
+                self.title,
+                self.description,
+                self.occurs_at,
+                self.created_at_utc.to_rfc3339()
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        let id: String = row.get("id")?;
+        let created_at: String = row.get("created_at_utc")?;
+
+        Ok(CalendarEvent {
+            id: Uuid::parse_str(&id).unwrap(),
+            title: row.get("title")?,
+            description: row.get("description")?,
+            occurs_at: row.get("occurs_at")?,
+            created_at_utc: DateTime::parse_from_rfc3339(&created_at).unwrap().into(),
+        })
+    }
+
+    // Events after `from` up to and including `to`, either bound may be left open.
+    pub fn load_between(
+        from: Option<i64>,
+        to: Option<i64>,
+        connection: &Connection,
+    ) -> Result<Vec<Self>> {
+        let mut statement = connection.prepare(
+            "SELECT * FROM calendar_events
+            WHERE (?1 IS NULL OR occurs_at > ?1) AND (?2 IS NULL OR occurs_at <= ?2)
+            ORDER BY occurs_at, title COLLATE NOCASE",
+        )?;
+
+        let events = statement
+            .query_map(params![from, to], CalendarEvent::from_row)?
+            .collect();
+
+        events
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClockStatus {
+    pub clock: Clock,
+    pub date: InGameDate,
+    // Events passed by the last advance.
+    pub due_events: Vec<CalendarEvent>,
+}
+
+fn load_clock(connection: &Connection) -> Result<Clock, String> {
+    Clock::load(connection)
+        .map_err(|e| e.to_string())?
+        .ok_or(String::from("The campaign clock has not been started"))
+}
+
+#[tauri::command]
+pub fn create_calendar_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    name: String,
+    months: Vec<Month>,
+    weekdays: Vec<String>,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Creating calendar {}", name);
+    let calendar = Calendar::new(name, months, weekdays)?;
+
+    let mut conn = db_pool.get().map_err(|e| e.to_string())?;
+    let transaction = conn.transaction().map_err(|e| e.to_string())?;
+    calendar.save(&transaction).map_err(|e| e.to_string())?;
+    transaction.commit().map_err(|e| e.to_string())?;
+
+    events::emit(
+        &app,
+        [DomainEvent::CalendarCreated {
+            calendar_id: calendar.id,
+        }],
+    );
+
+    Ok(serde_json::to_string(&calendar).unwrap())
+}
+
+#[tauri::command]
+pub fn load_calendars_command(database: State<Database>) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Running load_calendars_command");
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+    let calendars = Calendar::load_all(&conn).map_err(|e| e.to_string())?;
+
+    Ok(serde_json::to_string(&calendars).unwrap())
+}
+
+// Starts the clock, or moves it to another calendar and date.
+#[tauri::command]
+pub fn set_clock_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    calendar_id: String,
+    date: DateInput,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!(
+        "Setting the clock to {:?} on calendar {}",
+        date,
+        calendar_id
+    );
+    let calendar_id = Uuid::parse_str(&calendar_id).map_err(|e| e.to_string())?;
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+
+    let calendar = Calendar::load_by_id(calendar_id, &conn)
+        .map_err(|e| e.to_string())?
+        .ok_or(format!("Calendar {} not found", calendar_id))?;
+    let current_time = calendar.time(&date)?;
+    let clock = Clock {
+        calendar,
+        current_time,
+    };
+    clock.save(&conn).map_err(|e| e.to_string())?;
+
+    events::emit(
+        &app,
+        [DomainEvent::ClockChanged {
+            calendar_id: clock.calendar.id,
+            current_time: clock.current_time,
+        }],
+    );
+
+    let date = clock.date();
+    Ok(serde_json::to_string(&ClockStatus {
+        clock,
+        date,
+        due_events: Vec::new(),
+    })
+    .unwrap())
+}
+
+#[tauri::command]
+pub fn load_clock_command(database: State<Database>) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Running load_clock_command");
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+    let clock = load_clock(&conn)?;
+
+    let date = clock.date();
+    Ok(serde_json::to_string(&ClockStatus {
+        clock,
+        date,
+        due_events: Vec::new(),
+    })
+    .unwrap())
+}
+
+#[tauri::command]
+pub fn advance_time_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    reason: TimeAdvance,
+    minutes: Option<i64>,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    let minutes = reason.minutes(minutes)?;
+    log::debug!(
+        "Advancing the clock by {} minutes ({})",
+        minutes,
+        reason.as_str()
+    );
+
+    let mut conn = db_pool.get().map_err(|e| e.to_string())?;
+    let transaction = conn.transaction().map_err(|e| e.to_string())?;
+    let mut clock = load_clock(&transaction)?;
+    let previous_time = clock.current_time;
+    clock.advance(minutes)?;
+    clock.save(&transaction).map_err(|e| e.to_string())?;
+    let due_events =
+        CalendarEvent::load_between(Some(previous_time), Some(clock.current_time), &transaction)
+            .map_err(|e| e.to_string())?;
+    transaction.commit().map_err(|e| e.to_string())?;
+
+    events::emit(
+        &app,
+        [DomainEvent::ClockChanged {
+            calendar_id: clock.calendar.id,
+            current_time: clock.current_time,
+        }],
+    );
+
+    let date = clock.date();
+    Ok(serde_json::to_string(&ClockStatus {
+        clock,
+        date,
+        due_events,
+    })
+    .unwrap())
+}
+
+#[tauri::command]
+pub fn schedule_event_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    title: String,
+    description: String,
+    date: DateInput,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Scheduling {} on {:?}", title, date);
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+
+    let clock = load_clock(&conn)?;
+    let event = CalendarEvent::new(title, description, clock.calendar.time(&date)?);
+    event.save(&conn).map_err(|e| e.to_string())?;
+
+    events::emit(
+        &app,
+        [DomainEvent::CalendarEventScheduled { event_id: event.id }],
+    );
+
+    Ok(serde_json::to_string(&event).unwrap())
+}
+
+#[tauri::command]
+pub fn load_upcoming_events_command(database: State<Database>) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Running load_upcoming_events_command");
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+
+    let clock = load_clock(&conn)?;
+    let events = CalendarEvent::load_between(Some(clock.current_time), None, &conn)
+        .map_err(|e| e.to_string())?;
+
+    Ok(serde_json::to_string(&events).unwrap())
+}
@@ -0,0 +1,74 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, Result, Row};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::calendar::Clock;
+
+// Something that happened to a character, stamped with both real and in-game time.
+#[derive(Debug, Serialize, Clone)]
+pub struct HistoryEntry {
+    pub id: Uuid,
+    pub character_id: Uuid,
+    pub description: String,
+    // Minutes on the campaign clock, None when no clock was running.
+    pub in_game_time: Option<i64>,
+    pub created_at_utc: DateTime<Utc>,
+}
+
+impl HistoryEntry {
+    pub fn record(
+        character_id: Uuid,
+        description: String,
+        connection: &Connection,
+    ) -> Result<Self, rusqlite::Error> {
+        let entry = HistoryEntry {
+            id: Uuid::new_v4(),
+            character_id,
+            description,
+            in_game_time: Clock::current_time(connection)?,
+            created_at_utc: Utc::now(),
+        };
+
+        connection.execute(
+            "INSERT INTO character_history (id, character_id, description, in_game_time, created_at_utc) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                entry.id.to_string(),
+                entry.character_id.to_string(),
+                entry.description,
+                entry.in_game_time,
+                entry.created_at_utc.to_rfc3339()
+            ],
+        )?;
+
+        Ok(entry)
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        let id: String = row.get("id")?;
+        let character_id: String = row.get("character_id")?;
+        let created_at: String = row.get("created_at_utc")?;
+
+        Ok(HistoryEntry {
+            id: Uuid::parse_str(&id).unwrap(),
+            character_id: Uuid::parse_str(&character_id).unwrap(),
+            description: row.get("description")?,
+            in_game_time: row.get("in_game_time")?,
+            created_at_utc: DateTime::parse_from_rfc3339(&created_at).unwrap().into(),
+        })
+    }
+
+    pub fn load_for_character(character_id: Uuid, connection: &Connection) -> Result<Vec<Self>> {
+        let mut statement = connection.prepare(
+            "SELECT * FROM character_history WHERE character_id = ?1 ORDER BY created_at_utc",
+        )?;
+
+        let entries = statement
+            .query_map(params![character_id.to_string()], |row| {
+                HistoryEntry::from_row(row)
+            })?
+            .collect();
+
+        entries
+    }
+}
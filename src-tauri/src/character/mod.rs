@@ -1,33 +1,202 @@
 use chrono::{DateTime, Utc};
-use r2d2::Pool;
-use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::{Connection, Result, Row, Statement};
+use rusqlite::{Connection, Result, Row, Transaction};
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use std::sync::Mutex;
+use tauri::{AppHandle, Runtime, State};
 use uuid::Uuid;
 
+use crate::abilities::{Ability, AbilityScores, Proficiencies, Skill, SkillProficiency};
 use crate::configuration::Configuration;
+use crate::damage::{self, AppliedDamage, Damage, DamageModifier};
+use crate::dice::DiceExpression;
+use crate::events::{self, DomainEvent};
+use crate::repository::{CharacterRepository, SqliteRepository};
+use crate::storage::{self, Database};
 
-#[derive(Debug, Serialize, Deserialize)]
+mod history;
+mod query;
+
+pub use history::HistoryEntry;
+pub use query::{CharacterPage, CharacterQuery, CharacterSort, SortDirection};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ClassLevel {
+    pub class: String,
+    pub subclass: Option<String>,
+    pub levels: i32,
+    // Looked up from the class when left out.
+    #[serde(default)]
+    pub hit_die: u32,
+}
+
+impl ClassLevel {
+    pub fn new(class: String, subclass: Option<String>, levels: i32) -> Self {
+        let hit_die = ClassLevel::default_hit_die(&class);
+
+        ClassLevel {
+            class,
+            subclass,
+            levels,
+            hit_die,
+        }
+    }
+
+    // Classes outside the core rules get a d8, the most common hit die.
+    pub fn default_hit_die(class: &str) -> u32 {
+        match class.trim().to_lowercase().as_str() {
+            "barbarian" => 12,
+            "fighter" | "paladin" | "ranger" => 10,
+            "sorcerer" | "wizard" => 6,
+            _ => 8,
+        }
+    }
+
+    pub fn hit_dice(&self) -> DiceExpression {
+        DiceExpression::new(self.levels.max(0) as u32, self.hit_die, 0)
+    }
+
+    // Mirrors the checks on character_classes, so a bad class is reported before it is saved.
+    pub fn validate_all(class_levels: &[ClassLevel]) -> Result<(), String> {
+        if class_levels.is_empty() {
+            return Err(String::from("A character needs at least one class"));
+        }
+
+        for class_level in class_levels {
+            if class_level.levels < 1 {
+                return Err(format!(
+                    "{} needs at least one level, got {}",
+                    class_level.class, class_level.levels
+                ));
+            }
+            // Zero means it was left out and is looked up from the class.
+            if ![0, 6, 8, 10, 12].contains(&class_level.hit_die) {
+                return Err(format!(
+                    "Hit die d{} of {} is not a d6, d8, d10 or d12",
+                    class_level.hit_die, class_level.class
+                ));
+            }
+        }
+
+        let level: i64 = class_levels
+            .iter()
+            .map(|class_level| i64::from(class_level.levels))
+            .sum();
+        if level > 20 {
+            return Err(format!("Class levels add up to {}, the most is 20", level));
+        }
+
+        Ok(())
+    }
+
+    fn save_all(
+        character_id: Uuid,
+        class_levels: &[ClassLevel],
+        connection: &Connection,
+    ) -> Result<(), rusqlite::Error> {
+        connection.execute(
+            "DELETE FROM character_classes WHERE character_id = ?1",
+            rusqlite::params![character_id.to_string()],
+        )?;
+
+        for (position, class_level) in class_levels.iter().enumerate() {
+            connection.execute(
+                "INSERT INTO character_classes (character_id, position, class, subclass, levels, hit_die) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                rusqlite::params![
+                    character_id.to_string(),
+                    position,
+                    class_level.class,
+                    class_level.subclass,
+                    class_level.levels,
+                    class_level.hit_die
+                ],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(ClassLevel {
+            class: row.get("class")?,
+            subclass: row.get("subclass")?,
+            levels: row.get("levels")?,
+            hit_die: row.get("hit_die")?,
+        })
+    }
+
+    // Loads the classes of many characters at once instead of one query per character.
+    pub fn load_for<'a, I: IntoIterator<Item = &'a mut Character>>(
+        characters: I,
+        connection: &Connection,
+    ) -> Result<(), rusqlite::Error> {
+        let characters: Vec<&mut Character> = characters.into_iter().collect();
+        let character_ids: Vec<Uuid> = characters.iter().map(|character| character.id).collect();
+        let mut class_levels = storage::load_grouped_by_id(
+            connection,
+            "character_classes",
+            "character_id",
+            "position",
+            &character_ids,
+            ClassLevel::from_row,
+        )?;
+
+        for character in characters {
+            if let Some(class_levels) = class_levels.remove(&character.id) {
+                character.class_levels = class_levels;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn proficiency_bonus(level: i32) -> i32 {
+    2 + (level.max(1) - 1) / 4
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Character {
     pub id: Uuid,
     pub name: String,
+    // Summary of class_levels, e.g. "Fighter / Wizard".
     pub class: String,
+    #[serde(default)]
+    pub class_levels: Vec<ClassLevel>,
     pub race: String,
     pub background: Option<String>,
+    // Total of all class levels.
     pub level: i32,
+    #[serde(default)]
+    pub proficiency_bonus: i32,
     pub experience: i32,
     pub hit_points: i32,
     pub current_hit_points: i32,
+    pub temporary_hit_points: i32,
     pub armor_class: i32,
+    #[serde(default)]
+    pub abilities: AbilityScores,
+    #[serde(default)]
+    pub saving_throw_proficiencies: Vec<Ability>,
+    #[serde(default)]
+    pub skill_proficiencies: Vec<SkillProficiency>,
+    // Evasion and similar traits: no damage on a successful Dexterity save against an area
+    // effect, half on a failed one.
+    #[serde(default)]
+    pub evasion: bool,
+    // Resistances, immunities and vulnerabilities, honoured whenever the character takes damage.
+    #[serde(default)]
+    pub damage_modifiers: Vec<DamageModifier>,
     pub initiative: Option<i32>,
     pub alive: bool,
     pub notes: String,
+    #[serde(default)]
+    pub campaign: Option<String>,
     pub created_at_utc: DateTime<Utc>,
     pub updated_at_utc: DateTime<Utc>,
 }
 
 impl Character {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         name: String,
         class: String,
@@ -39,36 +208,154 @@ impl Character {
         armor_class: i32,
         notes: String,
     ) -> Self {
+        let class_levels = vec![ClassLevel::new(class.clone(), None, level)];
+
         Character {
             id: Uuid::new_v4(),
-            name: name,
-            class: class,
-            race: race,
-            background: background,
-            level: level,
-            experience: experience,
-            hit_points: hit_points,
+            name,
+            class,
+            class_levels,
+            race,
+            background,
+            level,
+            proficiency_bonus: proficiency_bonus(level),
+            experience,
+            hit_points,
             current_hit_points: hit_points,
-            armor_class: armor_class,
+            temporary_hit_points: 0,
+            armor_class,
+            abilities: AbilityScores::default(),
+            saving_throw_proficiencies: Vec::new(),
+            skill_proficiencies: Vec::new(),
+            evasion: false,
+            damage_modifiers: Vec::new(),
             initiative: None,
             alive: true,
-            notes: notes,
+            notes,
+            campaign: None,
             created_at_utc: Utc::now(),
             updated_at_utc: Utc::now(),
         }
     }
 
+    // Like new, but for a character with one or more classes, which are checked first.
+    #[allow(clippy::too_many_arguments)]
+    pub fn with_class_levels(
+        name: String,
+        class_levels: Vec<ClassLevel>,
+        race: String,
+        background: Option<String>,
+        experience: i32,
+        hit_points: i32,
+        armor_class: i32,
+        notes: String,
+    ) -> Result<Self, String> {
+        ClassLevel::validate_all(&class_levels)?;
+
+        let mut character = Character::new(
+            name,
+            class_levels[0].class.clone(),
+            race,
+            background,
+            class_levels[0].levels,
+            experience,
+            hit_points,
+            armor_class,
+            notes,
+        );
+        character.set_class_levels(class_levels);
+
+        Ok(character)
+    }
+
+    pub fn saving_throw_modifier(&self, ability: Ability) -> i32 {
+        let modifier = self.abilities.modifier(ability);
+        match self.saving_throw_proficiencies.contains(&ability) {
+            true => modifier + self.proficiency_bonus,
+            false => modifier,
+        }
+    }
+
+    pub fn skill_modifier(&self, skill: Skill) -> i32 {
+        let modifier = self.abilities.modifier(skill.ability());
+        match self
+            .skill_proficiencies
+            .iter()
+            .find(|skill_proficiency| skill_proficiency.skill == skill)
+        {
+            Some(skill_proficiency) if skill_proficiency.expertise => {
+                modifier + self.proficiency_bonus * 2
+            }
+            Some(_) => modifier + self.proficiency_bonus,
+            None => modifier,
+        }
+    }
+
+    // Each damage instance is adjusted for the character's resistances, immunities and
+    // vulnerabilities before hit points are lost.
+    pub fn take_damage(&mut self, damage: &[Damage]) -> Vec<AppliedDamage> {
+        let applied: Vec<AppliedDamage> = damage
+            .iter()
+            .map(|damage| damage::adjust(damage, &self.damage_modifiers))
+            .collect();
+        self.lose_hit_points(applied.iter().map(|applied| applied.applied).sum());
+
+        applied
+    }
+
+    // Temporary hit points are lost first, hit points never drop below 0.
+    fn lose_hit_points(&mut self, amount: i32) {
+        let amount = amount.max(0);
+        let absorbed = amount.min(self.temporary_hit_points);
+        self.temporary_hit_points -= absorbed;
+        self.current_hit_points = (self.current_hit_points - (amount - absorbed)).max(0);
+        self.updated_at_utc = Utc::now();
+    }
+
+    pub fn set_class_levels(&mut self, class_levels: Vec<ClassLevel>) {
+        self.class_levels = class_levels
+            .into_iter()
+            .map(|mut class_level| {
+                if class_level.hit_die == 0 {
+                    class_level.hit_die = ClassLevel::default_hit_die(&class_level.class);
+                }
+                class_level
+            })
+            .collect();
+        self.class = self
+            .class_levels
+            .iter()
+            .map(|class_level| class_level.class.clone())
+            .collect::<Vec<String>>()
+            .join(" / ");
+        self.level = self
+            .class_levels
+            .iter()
+            .map(|class_level| class_level.levels)
+            .sum();
+        self.proficiency_bonus = proficiency_bonus(self.level);
+    }
+
+    pub fn hit_dice(&self) -> Vec<DiceExpression> {
+        self.class_levels
+            .iter()
+            .map(|class_level| class_level.hit_dice())
+            .collect()
+    }
+
     pub fn is_stored(&self) -> bool {
         false
     }
 
-    pub fn save(&self, connection: &Connection) -> Result<&Self, ()> {
+    // Classes, proficiencies and damage modifiers are written after the row, the transaction keeps
+    // a failure in any of them from leaving a half saved character.
+    pub fn save(&self, transaction: &Transaction) -> Result<&Self, rusqlite::Error> {
         if self.is_stored() {
             return Ok(self);
         }
 
-        connection.execute(
-            "INSERT INTO characters (id, name, class, race, background, level, experience, hit_points, current_hit_points, armor_class, initiative, alive, notes, created_at_utc, updated_at_utc) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+        transaction.execute(
+            "INSERT INTO characters (id, name, class, race, background, level, experience, hit_points, current_hit_points, temporary_hit_points, armor_class, initiative, alive, notes, campaign, created_at_utc, updated_at_utc, strength, dexterity, constitution, intelligence, wisdom, charisma, evasion) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24)",
             rusqlite::params![
                 &self.id.to_string(),
                 &self.name,
@@ -79,37 +366,59 @@ impl Character {
                 &self.experience,
                 &self.hit_points,
                 &self.current_hit_points,
+                &self.temporary_hit_points,
                 &self.armor_class,
                 &self.initiative,
                 &self.alive,
                 &self.notes,
+                &self.campaign,
                 &self.created_at_utc.to_rfc3339(),
-                &self.updated_at_utc.to_rfc3339()],
-        ).unwrap();
+                &self.updated_at_utc.to_rfc3339(),
+                &self.abilities.strength,
+                &self.abilities.dexterity,
+                &self.abilities.constitution,
+                &self.abilities.intelligence,
+                &self.abilities.wisdom,
+                &self.abilities.charisma,
+                &self.evasion],
+        )?;
+        ClassLevel::save_all(self.id, &self.class_levels, transaction)?;
+        Proficiencies::save_all(self, transaction)?;
+        DamageModifier::save_all(self.id, &self.damage_modifiers, transaction)?;
 
         Ok(self)
     }
 
-    fn from_row(row: &Row) -> Result<Self> {
+    pub fn from_row(row: &Row) -> Result<Self> {
         let uuid_string: String = row.get("id").unwrap();
         let created_at_string: String = row.get("created_at_utc").unwrap();
         let updated_at_string: String = row.get("updated_at_utc").unwrap();
         let initiative: Option<i32> = row.get("initiative").ok();
+        let level: i32 = row.get("level").unwrap();
 
         Ok(Character {
             id: Uuid::parse_str(&uuid_string).unwrap(),
             name: row.get("name").unwrap(),
             class: row.get("class").unwrap(),
+            class_levels: Vec::new(),
             race: row.get("race").unwrap(),
             background: row.get("background").ok(),
-            level: row.get("level").unwrap(),
+            level,
+            proficiency_bonus: proficiency_bonus(level),
             experience: row.get("experience").unwrap(),
             hit_points: row.get("hit_points").unwrap(),
             current_hit_points: row.get("current_hit_points").unwrap(),
+            temporary_hit_points: row.get("temporary_hit_points").unwrap(),
             armor_class: row.get("armor_class").unwrap(),
-            initiative: initiative,
+            abilities: AbilityScores::from_row(row)?,
+            saving_throw_proficiencies: Vec::new(),
+            skill_proficiencies: Vec::new(),
+            evasion: row.get("evasion")?,
+            damage_modifiers: Vec::new(),
+            initiative,
             alive: row.get("alive").unwrap(),
             notes: row.get("notes").unwrap(),
+            campaign: row.get("campaign").unwrap(),
             created_at_utc: DateTime::<Utc>::from(
                 DateTime::parse_from_rfc3339(&created_at_string).unwrap(),
             ),
@@ -119,69 +428,231 @@ impl Character {
         })
     }
 
+    pub fn update(&self, transaction: &Transaction) -> Result<&Self, rusqlite::Error> {
+        transaction.execute(
+            "UPDATE characters SET name = ?2, class = ?3, race = ?4, background = ?5, level = ?6, experience = ?7, hit_points = ?8, current_hit_points = ?9, temporary_hit_points = ?10, armor_class = ?11, initiative = ?12, alive = ?13, notes = ?14, campaign = ?15, updated_at_utc = ?16, strength = ?17, dexterity = ?18, constitution = ?19, intelligence = ?20, wisdom = ?21, charisma = ?22, evasion = ?23 WHERE id = ?1",
+            rusqlite::params![
+                &self.id.to_string(),
+                &self.name,
+                &self.class,
+                &self.race,
+                &self.background,
+                &self.level,
+                &self.experience,
+                &self.hit_points,
+                &self.current_hit_points,
+                &self.temporary_hit_points,
+                &self.armor_class,
+                &self.initiative,
+                &self.alive,
+                &self.notes,
+                &self.campaign,
+                &self.updated_at_utc.to_rfc3339(),
+                &self.abilities.strength,
+                &self.abilities.dexterity,
+                &self.abilities.constitution,
+                &self.abilities.intelligence,
+                &self.abilities.wisdom,
+                &self.abilities.charisma,
+                &self.evasion],
+        )?;
+        ClassLevel::save_all(self.id, &self.class_levels, transaction)?;
+        Proficiencies::save_all(self, transaction)?;
+        DamageModifier::save_all(self.id, &self.damage_modifiers, transaction)?;
+
+        Ok(self)
+    }
+
     pub fn load_by_id(id: Uuid, connection: &Connection) -> Result<Self> {
-        let mut stmt = connection
-            .prepare("SELECT * FROM characters WHERE id = ?1")
-            .unwrap();
-        let mut rows = stmt.query(rusqlite::params![id.to_string()]).unwrap();
+        let mut character = connection.query_row(
+            "SELECT * FROM characters WHERE id = ?1",
+            rusqlite::params![id.to_string()],
+            Character::from_row,
+        )?;
+        ClassLevel::load_for([&mut character], connection)?;
+        Proficiencies::load_for([&mut character], connection)?;
+        DamageModifier::load_for([&mut character], connection)?;
+
+        Ok(character)
+    }
+
+    pub fn load_all(connection: &Connection) -> Result<Vec<Self>> {
+        let mut stmt = connection.prepare("SELECT * FROM characters")?;
 
-        let row = rows.next().unwrap().unwrap();
+        let mut characters = stmt
+            .query_map([], Character::from_row)?
+            .collect::<Result<Vec<Self>>>()?;
+        ClassLevel::load_for(characters.iter_mut(), connection)?;
+        Proficiencies::load_for(characters.iter_mut(), connection)?;
+        DamageModifier::load_for(characters.iter_mut(), connection)?;
 
-        Ok(Character::from_row(&row).unwrap())
+        Ok(characters)
     }
 }
 
 #[tauri::command]
-pub fn create_character_command(
+#[allow(clippy::too_many_arguments)]
+pub fn create_character_command<R: Runtime>(
+    app: AppHandle<R>,
     name: String,
-    class: String,
+    class_levels: Vec<ClassLevel>,
     race: String,
     background: Option<String>,
-    level: i32,
     experience: i32,
     hit_points: i32,
     armor_class: i32,
     notes: String,
-    db: State<Pool<SqliteConnectionManager>>,
-    configuration: State<Configuration>,
+    campaign: Option<String>,
+    database: State<Database>,
 ) -> Result<String, String> {
+    let db = database.pool();
     log::debug!("Running create character command for: {:?}", name);
-    let character = Character::new(
+    let mut character = Character::with_class_levels(
         name,
-        class,
+        class_levels,
         race,
         background,
-        level,
         experience,
         hit_points,
         armor_class,
         notes,
-    );
+    )?;
+    character.campaign = campaign;
 
-    character.save(&db.get().unwrap()).unwrap();
+    SqliteRepository::new(&db).save_character(&character)?;
+
+    events::emit(
+        &app,
+        [DomainEvent::CharacterCreated {
+            character_id: character.id,
+        }],
+    );
 
     Ok(serde_json::to_string(&character).unwrap())
 }
 
 #[tauri::command]
 pub fn load_characters_command(
-    db: State<Pool<SqliteConnectionManager>>,
-    configuration: State<Configuration>,
+    database: State<Database>,
+    _configuration: State<Mutex<Configuration>>,
 ) -> Result<String, String> {
+    let db = database.pool();
     log::debug!("Running load characters command");
-    let conn = db.get().unwrap(); // Get a connection from the pool
+    let characters = SqliteRepository::new(&db).load_characters()?;
+
+    Ok(serde_json::to_string(&characters).unwrap())
+}
 
-    let mut stmt = conn.prepare("SELECT * FROM characters").unwrap(); // Prepare the SQL statement
-    let character_iter = stmt
-        .query_map([], |row| Character::from_row(row))
-        .map_err(|e| e.to_string())?;
+#[tauri::command]
+pub fn query_characters_command(
+    database: State<Database>,
+    query: CharacterQuery,
+) -> Result<String, String> {
+    let db = database.pool();
+    log::debug!("Running query characters command with {:?}", query);
+    let page = SqliteRepository::new(&db).query_characters(&query)?;
 
-    let mut characters = Vec::new();
-    for character in character_iter {
-        characters.push(character.unwrap()); // Collect all characters into a vector
-    }
+    Ok(serde_json::to_string(&page).unwrap())
+}
 
-    Ok(serde_json::to_string(&characters).unwrap())
+#[tauri::command]
+pub fn set_damage_modifiers_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    character_id: String,
+    damage_modifiers: Vec<DamageModifier>,
+) -> Result<String, String> {
+    let db = database.pool();
+    log::debug!("Setting damage modifiers of character {}", character_id);
+    let character_id = Uuid::parse_str(&character_id).map_err(|e| e.to_string())?;
+    let repository = SqliteRepository::new(&db);
+
+    let mut character = repository
+        .load_character(character_id)?
+        .ok_or(format!("Character {} not found", character_id))?;
+    character.damage_modifiers = damage_modifiers;
+    character.updated_at_utc = Utc::now();
+    repository.update_character(&character)?;
+
+    events::emit(&app, [DomainEvent::CharacterUpdated { character_id }]);
+
+    Ok(serde_json::to_string(&character).unwrap())
+}
+
+#[tauri::command]
+pub fn set_abilities_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    character_id: String,
+    abilities: AbilityScores,
+    saving_throw_proficiencies: Vec<Ability>,
+    skill_proficiencies: Vec<SkillProficiency>,
+    evasion: bool,
+) -> Result<String, String> {
+    let db = database.pool();
+    log::debug!("Setting abilities of character {}", character_id);
+    let character_id = Uuid::parse_str(&character_id).map_err(|e| e.to_string())?;
+    abilities.validate()?;
+    let repository = SqliteRepository::new(&db);
+
+    let mut character = repository
+        .load_character(character_id)?
+        .ok_or(format!("Character {} not found", character_id))?;
+    character.abilities = abilities;
+    character.saving_throw_proficiencies = saving_throw_proficiencies;
+    character.skill_proficiencies = skill_proficiencies;
+    character.evasion = evasion;
+    character.updated_at_utc = Utc::now();
+    repository.update_character(&character)?;
+
+    events::emit(&app, [DomainEvent::CharacterUpdated { character_id }]);
+
+    Ok(serde_json::to_string(&character).unwrap())
+}
+
+#[tauri::command]
+pub fn add_character_history_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    character_id: String,
+    description: String,
+) -> Result<String, String> {
+    let db = database.pool();
+    log::debug!("Adding history to character {}", character_id);
+    let character_id = Uuid::parse_str(&character_id).map_err(|e| e.to_string())?;
+    SqliteRepository::new(&db)
+        .load_character(character_id)?
+        .ok_or(format!("Character {} not found", character_id))?;
+
+    let conn = db.get().map_err(|e| e.to_string())?;
+    let entry =
+        HistoryEntry::record(character_id, description, &conn).map_err(|e| e.to_string())?;
+
+    events::emit(
+        &app,
+        [DomainEvent::CharacterHistoryAdded {
+            character_id,
+            entry_id: entry.id,
+        }],
+    );
+
+    Ok(serde_json::to_string(&entry).unwrap())
+}
+
+#[tauri::command]
+pub fn load_character_history_command(
+    database: State<Database>,
+    character_id: String,
+) -> Result<String, String> {
+    let db = database.pool();
+    log::debug!("Loading history for character {}", character_id);
+    let character_id = Uuid::parse_str(&character_id).map_err(|e| e.to_string())?;
+
+    let conn = db.get().map_err(|e| e.to_string())?;
+    let entries =
+        HistoryEntry::load_for_character(character_id, &conn).map_err(|e| e.to_string())?;
+
+    Ok(serde_json::to_string(&entries).unwrap())
 }
 
 // #[derive(Debug, Serialize, Deserialize)]
@@ -214,7 +685,7 @@ pub fn load_characters_command(
 //         false
 //     }
 
-//     pub fn save(&self, connection: &Connection) -> Result<&Self, ()> {
+//     pub fn save(&self, connection: &Connection) -> Result<&Self, rusqlite::Error> {
 //         if self.is_stored() {
 //             return Ok(self);
 //         }
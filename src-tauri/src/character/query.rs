@@ -0,0 +1,245 @@
+use std::cmp::Ordering;
+
+use rusqlite::types::ToSql;
+use rusqlite::{params_from_iter, Connection, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{Character, ClassLevel};
+use crate::abilities::Proficiencies;
+use crate::damage::DamageModifier;
+use crate::storage::match_expression;
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 500;
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CharacterSort {
+    #[default]
+    Name,
+    Class,
+    Race,
+    Level,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+// Every filter is optional, an empty query returns the first page of all characters by name.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct CharacterQuery {
+    pub search: Option<String>,
+    pub class: Option<String>,
+    pub race: Option<String>,
+    pub min_level: Option<i32>,
+    pub max_level: Option<i32>,
+    pub alive: Option<bool>,
+    pub campaign: Option<String>,
+    pub sort_by: CharacterSort,
+    pub sort_direction: SortDirection,
+    // Pages start at 1.
+    pub page: u32,
+    pub page_size: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CharacterPage {
+    pub characters: Vec<Character>,
+    pub total: u32,
+    pub page: u32,
+    pub page_size: u32,
+}
+
+impl CharacterQuery {
+    pub fn page(&self) -> u32 {
+        self.page.max(1)
+    }
+
+    pub fn page_size(&self) -> u32 {
+        match self.page_size {
+            0 => DEFAULT_PAGE_SIZE,
+            page_size => page_size.min(MAX_PAGE_SIZE),
+        }
+    }
+
+    // Widened so a very large page number cannot overflow.
+    fn offset(&self) -> u64 {
+        u64::from(self.page() - 1) * u64::from(self.page_size())
+    }
+
+    fn where_clause(&self) -> (String, Vec<Box<dyn ToSql>>) {
+        let mut conditions: Vec<&str> = Vec::new();
+        let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+
+        if let Some(search) = self.search.as_deref().and_then(match_expression) {
+            conditions.push(
+                "characters.id IN (SELECT id FROM characters_fts WHERE characters_fts MATCH ?)",
+            );
+            params.push(Box::new(search));
+        }
+        if let Some(class) = &self.class {
+            conditions.push(
+                "EXISTS (SELECT 1 FROM character_classes WHERE character_classes.character_id = characters.id AND character_classes.class = ? COLLATE NOCASE)",
+            );
+            params.push(Box::new(class.clone()));
+        }
+        if let Some(race) = &self.race {
+            conditions.push("characters.race = ? COLLATE NOCASE");
+            params.push(Box::new(race.clone()));
+        }
+        if let Some(min_level) = self.min_level {
+            conditions.push("characters.level >= ?");
+            params.push(Box::new(min_level));
+        }
+        if let Some(max_level) = self.max_level {
+            conditions.push("characters.level <= ?");
+            params.push(Box::new(max_level));
+        }
+        if let Some(alive) = self.alive {
+            conditions.push("characters.alive = ?");
+            params.push(Box::new(alive));
+        }
+        if let Some(campaign) = &self.campaign {
+            conditions.push("characters.campaign = ?");
+            params.push(Box::new(campaign.clone()));
+        }
+
+        if conditions.is_empty() {
+            return (String::new(), params);
+        }
+
+        (format!("WHERE {}", conditions.join(" AND ")), params)
+    }
+
+    fn order_clause(&self) -> String {
+        let column = match self.sort_by {
+            CharacterSort::Name => "characters.name COLLATE NOCASE",
+            CharacterSort::Class => "characters.class COLLATE NOCASE",
+            CharacterSort::Race => "characters.race COLLATE NOCASE",
+            CharacterSort::Level => "characters.level",
+            CharacterSort::CreatedAt => "characters.created_at_utc",
+            CharacterSort::UpdatedAt => "characters.updated_at_utc",
+        };
+        let direction = match self.sort_direction {
+            SortDirection::Ascending => "ASC",
+            SortDirection::Descending => "DESC",
+        };
+
+        // The id keeps pages stable when the sort column has ties.
+        format!("ORDER BY {} {}, characters.id", column, direction)
+    }
+
+    pub fn run(&self, connection: &Connection) -> Result<CharacterPage> {
+        let (where_clause, mut params) = self.where_clause();
+
+        let total: u32 = connection.query_row(
+            &format!("SELECT COUNT(*) FROM characters {}", where_clause),
+            params_from_iter(params.iter()),
+            |row| row.get(0),
+        )?;
+
+        params.push(Box::new(self.page_size()));
+        params.push(Box::new(self.offset()));
+        let mut statement = connection.prepare(&format!(
+            "SELECT * FROM characters {} {} LIMIT ? OFFSET ?",
+            where_clause,
+            self.order_clause()
+        ))?;
+        let mut characters = statement
+            .query_map(params_from_iter(params.iter()), |row| {
+                Character::from_row(row)
+            })?
+            .collect::<Result<Vec<Character>>>()?;
+        ClassLevel::load_for(characters.iter_mut(), connection)?;
+        Proficiencies::load_for(characters.iter_mut(), connection)?;
+        DamageModifier::load_for(characters.iter_mut(), connection)?;
+
+        Ok(CharacterPage {
+            characters,
+            total,
+            page: self.page(),
+            page_size: self.page_size(),
+        })
+    }
+
+    // Mirrors the SQL filters for repositories without a database.
+    pub fn matches(&self, character: &Character) -> bool {
+        let contains = |text: &str, term: &str| text.to_lowercase().contains(&term.to_lowercase());
+
+        if let Some(search) = &self.search {
+            if !search
+                .split_whitespace()
+                .all(|term| contains(&character.name, term) || contains(&character.notes, term))
+            {
+                return false;
+            }
+        }
+        if let Some(class) = &self.class {
+            if !character
+                .class_levels
+                .iter()
+                .any(|class_level| class_level.class.eq_ignore_ascii_case(class))
+            {
+                return false;
+            }
+        }
+        if let Some(race) = &self.race {
+            if !character.race.eq_ignore_ascii_case(race) {
+                return false;
+            }
+        }
+
+        self.min_level.is_none_or(|level| character.level >= level)
+            && self.max_level.is_none_or(|level| character.level <= level)
+            && self.alive.is_none_or(|alive| character.alive == alive)
+            && self
+                .campaign
+                .as_ref()
+                .is_none_or(|campaign| character.campaign.as_ref() == Some(campaign))
+    }
+
+    pub fn compare(&self, a: &Character, b: &Character) -> Ordering {
+        let ordering = match self.sort_by {
+            CharacterSort::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            CharacterSort::Class => a.class.to_lowercase().cmp(&b.class.to_lowercase()),
+            CharacterSort::Race => a.race.to_lowercase().cmp(&b.race.to_lowercase()),
+            CharacterSort::Level => a.level.cmp(&b.level),
+            CharacterSort::CreatedAt => a.created_at_utc.cmp(&b.created_at_utc),
+            CharacterSort::UpdatedAt => a.updated_at_utc.cmp(&b.updated_at_utc),
+        };
+        let ordering = match self.sort_direction {
+            SortDirection::Ascending => ordering,
+            SortDirection::Descending => ordering.reverse(),
+        };
+
+        ordering.then_with(|| a.id.to_string().cmp(&b.id.to_string()))
+    }
+
+    pub fn apply(&self, characters: &[Character]) -> CharacterPage {
+        let mut matching: Vec<Character> = characters
+            .iter()
+            .filter(|character| self.matches(character))
+            .cloned()
+            .collect();
+        matching.sort_by(|a, b| self.compare(a, b));
+
+        CharacterPage {
+            total: matching.len() as u32,
+            characters: matching
+                .into_iter()
+                .skip(usize::try_from(self.offset()).unwrap_or(usize::MAX))
+                .take(self.page_size() as usize)
+                .collect(),
+            page: self.page(),
+            page_size: self.page_size(),
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::State;
+use uuid::Uuid;
+
+use crate::abilities::{Ability, Skill};
+use crate::character::{Character, CharacterQuery};
+use crate::dice::{D20Roll, RollMode};
+use crate::repository::{CharacterRepository, EncounterRepository, SqliteRepository};
+use crate::storage::Database;
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Check {
+    SavingThrow { ability: Ability },
+    AbilityCheck { ability: Ability },
+    Skill { skill: Skill },
+}
+
+impl Check {
+    pub fn modifier_for(&self, character: &Character) -> i32 {
+        match self {
+            Check::SavingThrow { ability } => character.saving_throw_modifier(*ability),
+            Check::AbilityCheck { ability } => character.abilities.modifier(*ability),
+            Check::Skill { skill } => character.skill_modifier(*skill),
+        }
+    }
+}
+
+// Who rolls: hand-picked characters, participants of an encounter, or the whole party, that is
+// every living character of a campaign. Characters made from encounter templates have no
+// campaign, so monsters never roll with the party.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GroupRollTargets {
+    Characters {
+        character_ids: Vec<Uuid>,
+    },
+    Participants {
+        encounter_id: Uuid,
+        participant_ids: Vec<Uuid>,
+    },
+    Party {
+        campaign: String,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GroupRoll {
+    pub check: Check,
+    pub dc: i32,
+    pub targets: GroupRollTargets,
+    // Advantage or disadvantage keyed by character or participant id, everyone else rolls normally.
+    #[serde(default)]
+    pub modes: HashMap<Uuid, RollMode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupRollEntry {
+    // The character or participant id, whichever the targets were given as.
+    pub id: Uuid,
+    pub character_id: Uuid,
+    pub name: String,
+    pub roll: D20Roll,
+    pub modifier: i32,
+    pub total: i32,
+    pub passed: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupRollResult {
+    pub check: Check,
+    pub dc: i32,
+    pub entries: Vec<GroupRollEntry>,
+    pub passes: usize,
+    pub failures: usize,
+    // The group succeeds when at least half of its members do.
+    pub group_passed: bool,
+}
+
+fn load_targets<R: CharacterRepository + EncounterRepository>(
+    repository: &R,
+    targets: &GroupRollTargets,
+) -> Result<Vec<(Uuid, Character)>, String> {
+    match targets {
+        GroupRollTargets::Characters { character_ids } => character_ids
+            .iter()
+            .map(|character_id| {
+                repository
+                    .load_character(*character_id)?
+                    .map(|character| (*character_id, character))
+                    .ok_or(format!("Character {} not found", character_id))
+            })
+            .collect(),
+        GroupRollTargets::Participants {
+            encounter_id,
+            participant_ids,
+        } => {
+            let encounter_detail = repository
+                .load_encounter_detail(*encounter_id)?
+                .ok_or(format!("Encounter {} not found", encounter_id))?;
+            participant_ids
+                .iter()
+                .map(|participant_id| {
+                    encounter_detail
+                        .characters
+                        .iter()
+                        .find(|participant| participant.id == *participant_id)
+                        .map(|participant| (*participant_id, participant.character.clone()))
+                        .ok_or(format!(
+                            "Participant {} is not in the encounter",
+                            participant_id
+                        ))
+                })
+                .collect()
+        }
+        GroupRollTargets::Party { campaign } => {
+            // The party comes sorted by name, a page at a time in case it is a large one.
+            let query = CharacterQuery {
+                alive: Some(true),
+                campaign: Some(campaign.clone()),
+                ..CharacterQuery::default()
+            };
+            let mut party: Vec<(Uuid, Character)> = Vec::new();
+            for page in 1.. {
+                let characters = repository.query_characters(&CharacterQuery {
+                    page,
+                    ..query.clone()
+                })?;
+                let last_page = u64::from(characters.page) * u64::from(characters.page_size)
+                    >= u64::from(characters.total);
+                party.extend(
+                    characters
+                        .characters
+                        .into_iter()
+                        .map(|character| (character.id, character)),
+                );
+                if last_page {
+                    break;
+                }
+            }
+
+            Ok(party)
+        }
+    }
+}
+
+pub fn roll_group<R: CharacterRepository + EncounterRepository, G: Rng + ?Sized>(
+    repository: &R,
+    group_roll: &GroupRoll,
+    rng: &mut G,
+) -> Result<GroupRollResult, String> {
+    let targets = load_targets(repository, &group_roll.targets)?;
+    if targets.is_empty() {
+        return Err(String::from("Nobody to roll for"));
+    }
+
+    let entries: Vec<GroupRollEntry> = targets
+        .into_iter()
+        .map(|(id, character)| {
+            let roll = group_roll
+                .modes
+                .get(&id)
+                .copied()
+                .unwrap_or_default()
+                .roll_d20(rng);
+            let modifier = group_roll.check.modifier_for(&character);
+            let total = roll.natural as i32 + modifier;
+
+            GroupRollEntry {
+                id,
+                character_id: character.id,
+                name: character.name,
+                roll,
+                modifier,
+                total,
+                passed: total >= group_roll.dc,
+            }
+        })
+        .collect();
+    let passes = entries.iter().filter(|entry| entry.passed).count();
+    let failures = entries.len() - passes;
+
+    Ok(GroupRollResult {
+        check: group_roll.check,
+        dc: group_roll.dc,
+        passes,
+        failures,
+        group_passed: passes * 2 >= entries.len(),
+        entries,
+    })
+}
+
+#[tauri::command]
+pub fn group_roll_command(
+    database: State<Database>,
+    group_roll: GroupRoll,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Running group roll {:?}", group_roll.check);
+
+    let result = roll_group(
+        &SqliteRepository::new(&db_pool),
+        &group_roll,
+        &mut rand::thread_rng(),
+    )?;
+
+    Ok(serde_json::to_string(&result).unwrap())
+}
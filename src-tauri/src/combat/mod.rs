@@ -0,0 +1,377 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime, State};
+use uuid::Uuid;
+
+use crate::abilities::Ability;
+use crate::damage::{AppliedDamage, Damage, DamageType};
+use crate::dice::{D20Roll, DiceExpression, DiceRoll, RollMode};
+use crate::encounter::EncounterCharacter;
+use crate::events::{self, DomainEvent};
+use crate::repository::{CharacterRepository, EncounterRepository, SqliteRepository, UnitOfWork};
+use crate::storage::Database;
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Attack {
+    pub attack_bonus: i32,
+    pub damage: DiceExpression,
+    pub damage_type: DamageType,
+    // Magical attacks get past resistances to nonmagical damage.
+    #[serde(default)]
+    pub magical: bool,
+    #[serde(default)]
+    pub mode: RollMode,
+}
+
+impl Attack {
+    // A critical hit rolls all of the attack's damage dice twice, the modifier is added once.
+    pub fn damage_on(&self, critical: bool) -> DiceExpression {
+        match critical {
+            true => DiceExpression::new(
+                self.damage.count * 2,
+                self.damage.sides,
+                self.damage.modifier,
+            ),
+            false => self.damage.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttackResult {
+    pub attacker_id: Uuid,
+    pub attacker_name: String,
+    pub target_id: Uuid,
+    pub target_character_id: Uuid,
+    pub target_name: String,
+    pub attack_roll: D20Roll,
+    pub attack_bonus: i32,
+    pub attack_total: i32,
+    pub target_armor_class: i32,
+    pub hit: bool,
+    pub critical: bool,
+    pub fumble: bool,
+    pub damage_roll: Option<DiceRoll>,
+    pub applied_damage: Option<AppliedDamage>,
+    pub damage_dealt: i32,
+    pub hit_points_before: i32,
+    pub hit_points_after: i32,
+    pub temporary_hit_points_after: i32,
+}
+
+fn find_participant(
+    participants: &[EncounterCharacter],
+    participant_id: Uuid,
+) -> Result<&EncounterCharacter, String> {
+    participants
+        .iter()
+        .find(|participant| participant.id == participant_id)
+        .ok_or(format!(
+            "Participant {} is not in the encounter",
+            participant_id
+        ))
+}
+
+// A natural 20 always hits and a natural 1 always misses, whatever the armor class.
+pub fn resolve_attack<R: UnitOfWork, G: Rng + ?Sized>(
+    repository: &R,
+    encounter_id: Uuid,
+    attacker_id: Uuid,
+    target_id: Uuid,
+    attack: &Attack,
+    rng: &mut G,
+) -> Result<AttackResult, String> {
+    repository.in_transaction(|repository| {
+        let encounter_detail = repository
+            .load_encounter_detail(encounter_id)?
+            .ok_or(format!("Encounter {} not found", encounter_id))?;
+        let attacker = find_participant(&encounter_detail.characters, attacker_id)?;
+        let mut target = find_participant(&encounter_detail.characters, target_id)?
+            .character
+            .clone();
+
+        let attack_roll = attack.mode.roll_d20(rng);
+        let attack_total = attack_roll.natural as i32 + attack.attack_bonus;
+        let critical = attack_roll.is_critical();
+        let fumble = attack_roll.is_fumble();
+        let hit = critical || (!fumble && attack_total >= target.armor_class);
+
+        let hit_points_before = target.current_hit_points;
+        let damage_roll = hit.then(|| attack.damage_on(critical).roll(rng));
+        let applied_damage = damage_roll.as_ref().map(|damage_roll| {
+            let damage = Damage {
+                magical: attack.magical,
+                ..Damage::new(damage_roll.total, attack.damage_type)
+            };
+            target.take_damage(&[damage]).remove(0)
+        });
+        let damage_dealt = applied_damage
+            .as_ref()
+            .map(|applied_damage| applied_damage.applied)
+            .unwrap_or(0);
+        if damage_dealt > 0 {
+            repository.update_character(&target)?;
+        }
+
+        Ok(AttackResult {
+            attacker_id,
+            attacker_name: attacker.character.name.clone(),
+            target_id,
+            target_character_id: target.id,
+            target_name: target.name.clone(),
+            attack_roll,
+            attack_bonus: attack.attack_bonus,
+            attack_total,
+            target_armor_class: target.armor_class,
+            hit,
+            critical,
+            fumble,
+            damage_roll,
+            applied_damage,
+            damage_dealt,
+            hit_points_before,
+            hit_points_after: target.current_hit_points,
+            temporary_hit_points_after: target.temporary_hit_points,
+        })
+    })
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn attack_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    encounter_id: String,
+    attacker_id: String,
+    target_id: String,
+    attack_bonus: i32,
+    damage: String,
+    damage_type: DamageType,
+    magical: Option<bool>,
+    mode: Option<RollMode>,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!(
+        "{} attacks {} in encounter {}",
+        attacker_id,
+        target_id,
+        encounter_id
+    );
+    let encounter_id = Uuid::parse_str(&encounter_id).map_err(|e| e.to_string())?;
+    let attacker_id = Uuid::parse_str(&attacker_id).map_err(|e| e.to_string())?;
+    let target_id = Uuid::parse_str(&target_id).map_err(|e| e.to_string())?;
+    let attack = Attack {
+        attack_bonus,
+        damage: DiceExpression::parse(&damage)?,
+        damage_type,
+        magical: magical.unwrap_or_default(),
+        mode: mode.unwrap_or_default(),
+    };
+
+    let result = resolve_attack(
+        &SqliteRepository::new(&db_pool),
+        encounter_id,
+        attacker_id,
+        target_id,
+        &attack,
+        &mut rand::thread_rng(),
+    )?;
+    if result.damage_dealt > 0 {
+        events::emit(
+            &app,
+            [
+                DomainEvent::EncounterParticipantUpdated {
+                    encounter_id,
+                    participant_id: target_id,
+                },
+                DomainEvent::CharacterUpdated {
+                    character_id: result.target_character_id,
+                },
+            ],
+        );
+    }
+
+    Ok(serde_json::to_string(&result).unwrap())
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AreaEffect {
+    pub save_ability: Ability,
+    pub dc: i32,
+    pub damage: DiceExpression,
+    pub damage_type: DamageType,
+    #[serde(default)]
+    pub magical: bool,
+    // Most area spells deal half damage on a successful save, some deal none.
+    pub half_on_success: bool,
+}
+
+impl AreaEffect {
+    // Evasion only helps against Dexterity saves.
+    pub fn damage_for(&self, rolled: i32, saved: bool, evasion: bool) -> i32 {
+        let evasion = evasion && self.save_ability == Ability::Dexterity;
+        match (saved, evasion) {
+            (true, true) => 0,
+            (true, false) if self.half_on_success => rolled / 2,
+            (true, false) => 0,
+            (false, true) => rolled / 2,
+            (false, false) => rolled,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AreaEffectTarget {
+    pub target_id: Uuid,
+    pub target_character_id: Uuid,
+    pub target_name: String,
+    pub save_roll: D20Roll,
+    pub save_modifier: i32,
+    pub save_total: i32,
+    pub saved: bool,
+    pub applied_damage: AppliedDamage,
+    pub damage_dealt: i32,
+    pub hit_points_before: i32,
+    pub hit_points_after: i32,
+    pub temporary_hit_points_after: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AreaEffectResult {
+    pub save_ability: Ability,
+    pub dc: i32,
+    pub damage_roll: DiceRoll,
+    pub targets: Vec<AreaEffectTarget>,
+}
+
+// Damage is rolled once for everyone, each target saves on its own. All targets are updated in
+// a single transaction so a failure leaves every character untouched.
+pub fn resolve_area_effect<R: UnitOfWork, G: Rng + ?Sized>(
+    repository: &R,
+    encounter_id: Uuid,
+    target_ids: &[Uuid],
+    area_effect: &AreaEffect,
+    rng: &mut G,
+) -> Result<AreaEffectResult, String> {
+    repository.in_transaction(|repository| {
+        let participants = repository
+            .load_encounter_detail(encounter_id)?
+            .ok_or(format!("Encounter {} not found", encounter_id))?
+            .characters;
+
+        let damage_roll = area_effect.damage.roll(rng);
+        let mut targets = Vec::new();
+        for (index, target_id) in target_ids.iter().enumerate() {
+            if target_ids[..index].contains(target_id) {
+                return Err(format!(
+                    "Participant {} is targeted more than once",
+                    target_id
+                ));
+            }
+            let mut target = find_participant(&participants, *target_id)?
+                .character
+                .clone();
+
+            let save_roll = RollMode::Normal.roll_d20(rng);
+            let save_modifier = target.saving_throw_modifier(area_effect.save_ability);
+            let save_total = save_roll.natural as i32 + save_modifier;
+            let saved = save_total >= area_effect.dc;
+
+            let hit_points_before = target.current_hit_points;
+            let damage = Damage {
+                magical: area_effect.magical,
+                ..Damage::new(
+                    area_effect.damage_for(damage_roll.total.max(0), saved, target.evasion),
+                    area_effect.damage_type,
+                )
+            };
+            let applied_damage = target.take_damage(&[damage]).remove(0);
+            if applied_damage.applied > 0 {
+                repository.update_character(&target)?;
+            }
+
+            targets.push(AreaEffectTarget {
+                target_id: *target_id,
+                target_character_id: target.id,
+                target_name: target.name.clone(),
+                save_roll,
+                save_modifier,
+                save_total,
+                saved,
+                damage_dealt: applied_damage.applied,
+                applied_damage,
+                hit_points_before,
+                hit_points_after: target.current_hit_points,
+                temporary_hit_points_after: target.temporary_hit_points,
+            });
+        }
+        Ok(AreaEffectResult {
+            save_ability: area_effect.save_ability,
+            dc: area_effect.dc,
+            damage_roll,
+            targets,
+        })
+    })
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn area_effect_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    encounter_id: String,
+    target_ids: Vec<String>,
+    save_ability: Ability,
+    dc: i32,
+    damage: String,
+    damage_type: DamageType,
+    magical: Option<bool>,
+    half_on_success: Option<bool>,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!(
+        "Area effect on {} targets in encounter {}",
+        target_ids.len(),
+        encounter_id
+    );
+    let encounter_id = Uuid::parse_str(&encounter_id).map_err(|e| e.to_string())?;
+    let target_ids = target_ids
+        .iter()
+        .map(|target_id| Uuid::parse_str(target_id))
+        .collect::<Result<Vec<Uuid>, _>>()
+        .map_err(|e| e.to_string())?;
+    let area_effect = AreaEffect {
+        save_ability,
+        dc,
+        damage: DiceExpression::parse(&damage)?,
+        damage_type,
+        magical: magical.unwrap_or_default(),
+        half_on_success: half_on_success.unwrap_or(true),
+    };
+
+    let result = resolve_area_effect(
+        &SqliteRepository::new(&db_pool),
+        encounter_id,
+        &target_ids,
+        &area_effect,
+        &mut rand::thread_rng(),
+    )?;
+    events::emit(
+        &app,
+        result.targets.iter().flat_map(|target| {
+            let participant_updated = DomainEvent::EncounterParticipantUpdated {
+                encounter_id,
+                participant_id: target.target_id,
+            };
+            // Characters without damage are unchanged, their sheets need no reload.
+            let character_updated =
+                (target.damage_dealt > 0).then_some(DomainEvent::CharacterUpdated {
+                    character_id: target.target_character_id,
+                });
+
+            std::iter::once(participant_updated).chain(character_updated)
+        }),
+    );
+
+    Ok(serde_json::to_string(&result).unwrap())
+}
@@ -1,118 +1,762 @@
-use plogger;
+use chrono::Local;
+use env_logger::Builder;
 use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::path::PathBuf;
-use tauri::State;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Runtime, State};
 use toml;
 
+use crate::events::{self, DomainEvent};
+use crate::storage::{self, Database};
+
+pub const DATA_DIRECTORY_ENV: &str = "DM_COMPANION_DATA_DIR";
+pub const PROFILE_ENV: &str = "DM_COMPANION_PROFILE";
+const DEFAULT_PROFILE: &str = "default";
+
+// Bumped whenever the shape of the configuration file changes; files without the key are version 1.
+pub const CONFIGURATION_VERSION: u32 = 2;
+
+// Every key falls back to its default so older or hand-edited files still load.
 #[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
 pub struct Configuration {
+    #[serde(rename = "schemaVersion")]
+    pub schema_version: u32,
     pub version: String,
     #[serde(rename = "developmentMode")]
     pub development_mode: bool,
     #[serde(rename = "configurationPath")]
     pub config_path: PathBuf,
+    #[serde(rename = "dataDirectory")]
+    pub data_directory: PathBuf,
     #[serde(rename = "dbPath")]
     pub db_path: PathBuf,
+    #[serde(rename = "activeProfile")]
+    pub active_profile: String,
+    pub profiles: Vec<Profile>,
+    pub preferences: Preferences,
+    pub database: DatabaseSettings,
+    #[serde(rename = "playerServer")]
+    pub player_server: PlayerServerSettings,
+    // Problems found while reading the file, reported to the frontend but never written back.
+    #[serde(skip)]
+    pub load_warnings: Vec<String>,
 }
 
-impl Configuration {
-    fn config_path(dev_mode: bool) -> PathBuf {
-        if dev_mode {
-            let mut config_path = PathBuf::new();
-            config_path.push(".config.toml");
+impl Default for Configuration {
+    fn default() -> Self {
+        Configuration {
+            schema_version: CONFIGURATION_VERSION,
+            version: String::from(env!("CARGO_PKG_VERSION")),
+            development_mode: cfg!(debug_assertions),
+            config_path: PathBuf::new(),
+            data_directory: PathBuf::new(),
+            db_path: PathBuf::new(),
+            active_profile: String::from(DEFAULT_PROFILE),
+            profiles: Vec::new(),
+            preferences: Preferences::default(),
+            database: DatabaseSettings::default(),
+            player_server: PlayerServerSettings::default(),
+            load_warnings: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Profile {
+    pub name: String,
+    #[serde(rename = "dbPath")]
+    pub db_path: PathBuf,
+}
+
+// Values passed on the command line or through the environment win over the configuration file.
+#[derive(Debug, Default, Clone)]
+pub struct ConfigurationOverrides {
+    pub data_directory: Option<PathBuf>,
+    pub profile: Option<String>,
+}
 
-            return config_path;
+impl ConfigurationOverrides {
+    pub fn from_env() -> Self {
+        ConfigurationOverrides {
+            data_directory: std::env::var_os(DATA_DIRECTORY_ENV).map(PathBuf::from),
+            profile: std::env::var(PROFILE_ENV).ok(),
         }
+    }
 
-        let mut config_path = PathBuf::new();
-        config_path.push(dirs::home_dir().expect("Could not load home dir"));
-        config_path.push(".config/.my-blocks/config.toml");
+    // Picks up --data-dir and --profile, falling back to the environment variables.
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Self {
+        let mut overrides = ConfigurationOverrides::from_env();
+        let mut args = args.into_iter();
 
-        println!("Loading config_path {:?}", config_path);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--data-dir" => overrides.data_directory = args.next().map(PathBuf::from),
+                "--profile" => overrides.profile = args.next(),
+                _ => {
+                    if let Some(value) = arg.strip_prefix("--data-dir=") {
+                        overrides.data_directory = Some(PathBuf::from(value));
+                    } else if let Some(value) = arg.strip_prefix("--profile=") {
+                        overrides.profile = Some(String::from(value));
+                    }
+                }
+            }
+        }
 
-        if let Some(parent) = config_path.parent() {
-            if !parent.exists() {
-                log::info!("Creating configuration directory for {:?}", &config_path);
-                std::fs::create_dir_all(parent).expect("Could not create configuration directory");
-                println!("Directory created: {:?}", parent);
+        overrides
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum RuleSet {
+    #[serde(rename = "5e-2014")]
+    FifthEdition2014,
+    #[serde(rename = "5e-2024")]
+    FifthEdition2024,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiceMode {
+    Digital,
+    Physical,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HitPointMethod {
+    Average,
+    Roll,
+    Maximum,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn level_filter(&self) -> log::LevelFilter {
+        match self {
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+            LogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+const MAX_BACKUP_RETENTION: u32 = 100;
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct Preferences {
+    #[serde(rename = "ruleSet")]
+    pub rule_set: RuleSet,
+    #[serde(rename = "diceMode")]
+    pub dice_mode: DiceMode,
+    #[serde(rename = "hitPointMethod")]
+    pub hit_point_method: HitPointMethod,
+    #[serde(rename = "autoRollMonsterInitiative")]
+    pub auto_roll_monster_initiative: bool,
+    #[serde(rename = "logLevel")]
+    pub log_level: LogLevel,
+    #[serde(rename = "backupRetention")]
+    pub backup_retention: u32,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Preferences {
+            rule_set: RuleSet::FifthEdition2014,
+            dice_mode: DiceMode::Digital,
+            hit_point_method: HitPointMethod::Average,
+            auto_roll_monster_initiative: false,
+            log_level: if cfg!(debug_assertions) {
+                LogLevel::Debug
+            } else {
+                LogLevel::Info
+            },
+            backup_retention: 5,
+        }
+    }
+}
+
+impl Preferences {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_BACKUP_RETENTION).contains(&self.backup_retention) {
+            return Err(format!(
+                "Backup retention must be between 1 and {}",
+                MAX_BACKUP_RETENTION
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn apply(&self) {
+        log::set_max_level(self.log_level.level_filter());
+    }
+
+    // With physical dice the table does the rolling, rolled hit points use the stat block average.
+    pub fn monster_hit_point_method(&self) -> HitPointMethod {
+        match (self.dice_mode, self.hit_point_method) {
+            (DiceMode::Physical, HitPointMethod::Roll) => HitPointMethod::Average,
+            (_, hit_point_method) => hit_point_method,
+        }
+    }
+
+    // Initiative rolled at the table is entered by hand.
+    pub fn rolls_monster_initiative(&self) -> bool {
+        self.auto_roll_monster_initiative && self.dice_mode == DiceMode::Digital
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum JournalMode {
+    Delete,
+    Truncate,
+    Persist,
+    Memory,
+    Wal,
+    Off,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Synchronous {
+    Off,
+    Normal,
+    Full,
+    Extra,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DatabaseSettings {
+    #[serde(
+        rename = "journalMode",
+        default = "DatabaseSettings::default_journal_mode"
+    )]
+    pub journal_mode: JournalMode,
+    #[serde(
+        rename = "busyTimeoutMs",
+        default = "DatabaseSettings::default_busy_timeout_ms"
+    )]
+    pub busy_timeout_ms: u32,
+    #[serde(
+        rename = "foreignKeys",
+        default = "DatabaseSettings::default_foreign_keys"
+    )]
+    pub foreign_keys: bool,
+    #[serde(default = "DatabaseSettings::default_synchronous")]
+    pub synchronous: Synchronous,
+}
+
+impl DatabaseSettings {
+    fn default_journal_mode() -> JournalMode {
+        JournalMode::Wal
+    }
+
+    fn default_busy_timeout_ms() -> u32 {
+        5000
+    }
+
+    fn default_foreign_keys() -> bool {
+        true
+    }
+
+    fn default_synchronous() -> Synchronous {
+        Synchronous::Normal
+    }
+}
+
+impl Default for DatabaseSettings {
+    fn default() -> Self {
+        DatabaseSettings {
+            journal_mode: DatabaseSettings::default_journal_mode(),
+            busy_timeout_ms: DatabaseSettings::default_busy_timeout_ms(),
+            foreign_keys: DatabaseSettings::default_foreign_keys(),
+            synchronous: DatabaseSettings::default_synchronous(),
+        }
+    }
+}
+
+// The read-only player view served to phones on the local network, off unless asked for.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct PlayerServerSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(
+        rename = "bindAddress",
+        default = "PlayerServerSettings::default_bind_address"
+    )]
+    pub bind_address: String,
+    // 0 lets the system pick a free port.
+    #[serde(default = "PlayerServerSettings::default_port")]
+    pub port: u16,
+}
+
+impl PlayerServerSettings {
+    fn default_bind_address() -> String {
+        String::from("0.0.0.0")
+    }
+
+    fn default_port() -> u16 {
+        4780
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.bind_address
+            .parse::<std::net::IpAddr>()
+            .map_err(|_| format!("Invalid bind address: {}", self.bind_address))?;
+
+        Ok(())
+    }
+}
+
+impl Default for PlayerServerSettings {
+    fn default() -> Self {
+        PlayerServerSettings {
+            enabled: false,
+            bind_address: PlayerServerSettings::default_bind_address(),
+            port: PlayerServerSettings::default_port(),
+        }
+    }
+}
+
+// Each migration brings a configuration file up by one version, like the database migrations.
+const CONFIGURATION_MIGRATIONS: [fn(&mut toml::Table); 1] = [migrate_to_version_2];
+
+// Version 1 files kept a single dbPath, which becomes the default profile.
+fn migrate_to_version_2(table: &mut toml::Table) {
+    if table.contains_key("profiles") {
+        return;
+    }
+
+    let Some(db_path) = table.get("dbPath").and_then(|db_path| db_path.as_str()) else {
+        return;
+    };
+    if db_path.is_empty() {
+        return;
+    }
+
+    let mut profile = toml::Table::new();
+    profile.insert(String::from("name"), toml::Value::from(DEFAULT_PROFILE));
+    profile.insert(String::from("dbPath"), toml::Value::from(db_path));
+
+    table.insert(
+        String::from("profiles"),
+        toml::Value::Array(vec![toml::Value::Table(profile)]),
+    );
+    table.insert(
+        String::from("activeProfile"),
+        toml::Value::from(DEFAULT_PROFILE),
+    );
+}
+
+fn deserializes(table: &toml::Table) -> bool {
+    toml::Value::Table(table.clone())
+        .try_into::<Configuration>()
+        .is_ok()
+}
+
+// Removes values that do not deserialize, so one bad setting falls back to its default alone.
+fn remove_invalid_values(table: &mut toml::Table, known: &toml::Table) -> Vec<String> {
+    let mut invalid_keys = Vec::new();
+
+    for (key, known_value) in known {
+        match (table.get_mut(key), known_value) {
+            (Some(toml::Value::Table(section)), toml::Value::Table(_)) => {
+                let fields: Vec<String> = section.keys().cloned().collect();
+                for field in fields {
+                    let mut candidate = known.clone();
+                    if let Some(toml::Value::Table(known_section)) = candidate.get_mut(key) {
+                        known_section.insert(field.clone(), section[&field].clone());
+                    }
+                    if !deserializes(&candidate) {
+                        section.remove(&field);
+                        invalid_keys.push(format!("{}.{}", key, field));
+                    }
+                }
             }
+            (Some(value), _) => {
+                let mut candidate = known.clone();
+                candidate.insert(key.clone(), value.clone());
+                if !deserializes(&candidate) {
+                    table.remove(key);
+                    invalid_keys.push(key.clone());
+                }
+            }
+            (None, _) => {}
+        }
+    }
+
+    invalid_keys
+}
+
+fn find_unknown_keys(table: &toml::Table, known: &toml::Table, prefix: &str) -> Vec<String> {
+    let mut unknown_keys = Vec::new();
+
+    for (key, value) in table {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+
+        match (value, known.get(key)) {
+            (_, None) => unknown_keys.push(path),
+            (toml::Value::Table(table), Some(toml::Value::Table(known))) => {
+                unknown_keys.extend(find_unknown_keys(table, known, &path));
+            }
+            (toml::Value::Array(items), Some(toml::Value::Array(known_items))) => {
+                if let Some(toml::Value::Table(known)) = known_items.first() {
+                    for item in items {
+                        if let toml::Value::Table(table) = item {
+                            unknown_keys.extend(find_unknown_keys(table, known, &path));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    unknown_keys
+}
+
+impl Configuration {
+    fn config_path(dev_mode: bool, overrides: &ConfigurationOverrides) -> PathBuf {
+        if let Some(data_directory) = &overrides.data_directory {
+            return data_directory.join("config.toml");
         }
 
-        if !config_path.exists() {
-            log::info!("Creating configuration file {:?}", &config_path);
-            File::create(&config_path).expect("Could not create config file");
+        if dev_mode {
+            return PathBuf::from(".config.toml");
         }
 
-        config_path
+        dirs::config_dir()
+            .expect("Could not load configuration dir")
+            .join("dm-companion")
+            .join("config.toml")
     }
 
-    fn db_path(dev_mode: bool) -> PathBuf {
+    fn data_directory(dev_mode: bool, overrides: &ConfigurationOverrides) -> PathBuf {
+        if let Some(data_directory) = &overrides.data_directory {
+            return data_directory.clone();
+        }
+
         if dev_mode {
-            let mut config_path = PathBuf::new();
-            config_path.push("file.db");
+            return PathBuf::new();
+        }
+
+        dirs::data_dir()
+            .expect("Could not load data dir")
+            .join("dm-companion")
+    }
 
-            return config_path;
+    fn default_db_path(dev_mode: bool, data_directory: &Path) -> PathBuf {
+        if dev_mode {
+            return data_directory.join("file.db");
         }
 
-        let mut db_path = PathBuf::new();
-        db_path.push(dirs::home_dir().expect("Could not load home dir"));
-        db_path.push(".config/.my-blocks/db.sqlite");
+        data_directory.join("db.sqlite")
+    }
+
+    fn profile_db_path(&self, name: &str) -> PathBuf {
+        let file_name: String = name
+            .trim()
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+
+        self.data_directory
+            .join("profiles")
+            .join(format!("{}.sqlite", file_name))
+    }
+
+    // Databases from before the data directory moved lived next to the old configuration file.
+    fn import_legacy_database(db_path: &Path) {
+        let Some(home_dir) = dirs::home_dir() else {
+            return;
+        };
+        let legacy_db_path = home_dir.join(".config/.my-blocks/db.sqlite");
+
+        if db_path.exists() || !legacy_db_path.exists() {
+            return;
+        }
 
+        log::info!(
+            "Copying database from legacy location {:?} to {:?}",
+            &legacy_db_path,
+            db_path
+        );
         if let Some(parent) = db_path.parent() {
-            if !parent.exists() {
-                std::fs::create_dir_all(parent).expect("Could not create configuration directory");
-                println!("Directory created: {:?}", parent);
+            std::fs::create_dir_all(parent).expect("Could not create data directory");
+        }
+        if let Err(e) = std::fs::copy(&legacy_db_path, db_path) {
+            log::error!("Could not copy legacy database: {:?}", e);
+        }
+    }
+
+    fn backup_path(config_path: &Path, generation: u32) -> PathBuf {
+        if generation == 0 {
+            return config_path.with_extension("toml.bak");
+        }
+
+        config_path.with_extension(format!("toml.bak.{}", generation))
+    }
+
+    // Copies the file aside before it is rewritten, so nothing a user wrote is lost. The newest
+    // backup is always config.toml.bak, older ones move to config.toml.bak.1 and up.
+    fn backup_file(config_path: &Path, retention: u32) -> Option<PathBuf> {
+        let retention = retention.clamp(1, MAX_BACKUP_RETENTION);
+        let _ = std::fs::remove_file(Configuration::backup_path(config_path, retention - 1));
+        for generation in (0..retention - 1).rev() {
+            let backup_path = Configuration::backup_path(config_path, generation);
+            if backup_path.exists() {
+                if let Err(e) = std::fs::rename(
+                    &backup_path,
+                    Configuration::backup_path(config_path, generation + 1),
+                ) {
+                    log::warn!("Could not rotate configuration backup: {:?}", e);
+                }
             }
         }
 
-        if !db_path.exists() {
-            File::create(&db_path).expect("Could not create config file");
+        let backup_path = Configuration::backup_path(config_path, 0);
+
+        match std::fs::copy(config_path, &backup_path) {
+            Ok(_) => {
+                log::info!("Backed up configuration file to {:?}", &backup_path);
+                Some(backup_path)
+            }
+            Err(e) => {
+                log::error!("Could not back up configuration file: {:?}", e);
+                None
+            }
+        }
+    }
+
+    // Upgrades older files to the current shape and reports keys this version does not know.
+    fn parse(config_str: &str) -> Result<(Self, bool), String> {
+        let mut table: toml::Table = config_str.parse().map_err(|e| format!("{}", e))?;
+
+        let file_version = table
+            .get("schemaVersion")
+            .and_then(|version| version.as_integer())
+            .unwrap_or(1)
+            .max(1) as u32;
+        let mut load_warnings = Vec::new();
+
+        if file_version > CONFIGURATION_VERSION {
+            log::warn!(
+                "Configuration file version {} is newer than {}",
+                file_version,
+                CONFIGURATION_VERSION
+            );
+            load_warnings.push(format!(
+                "Configuration file version {} was written by a newer release",
+                file_version
+            ));
         }
 
-        db_path
+        for (index, migration) in CONFIGURATION_MIGRATIONS
+            .iter()
+            .enumerate()
+            .skip(file_version as usize - 1)
+        {
+            log::info!("Migrating configuration file to version {}", index + 2);
+            migration(&mut table);
+        }
+        table.insert(
+            String::from("schemaVersion"),
+            toml::Value::from(CONFIGURATION_VERSION),
+        );
+
+        let known = toml::Value::try_from(Configuration {
+            profiles: vec![Profile {
+                name: String::from(DEFAULT_PROFILE),
+                db_path: PathBuf::new(),
+            }],
+            ..Configuration::default()
+        })
+        .map_err(|e| e.to_string())?;
+        if let toml::Value::Table(known) = known {
+            for key in find_unknown_keys(&table, &known, "") {
+                log::warn!("Ignoring unknown configuration key {}", key);
+                load_warnings.push(format!("Unknown configuration key {}", key));
+            }
+            for key in remove_invalid_values(&mut table, &known) {
+                log::warn!("Invalid value for configuration key {}, using default", key);
+                load_warnings.push(format!(
+                    "Invalid value for configuration key {} was reset to its default",
+                    key
+                ));
+            }
+        }
+
+        let needs_backup = file_version != CONFIGURATION_VERSION || !load_warnings.is_empty();
+        let mut config: Configuration = toml::Value::Table(table)
+            .try_into()
+            .map_err(|e: toml::de::Error| e.to_string())?;
+        config.load_warnings = load_warnings;
+
+        Ok((config, needs_backup))
     }
 
-    fn load_from_file(dev_mode: bool) -> Result<Self, String> {
-        let config_path = Configuration::config_path(dev_mode);
+    fn load_from_file(dev_mode: bool, overrides: &ConfigurationOverrides) -> Result<Self, String> {
+        let config_path = Configuration::config_path(dev_mode, overrides);
         log::debug!("Loading config from {:?}", &config_path);
         let config_str = std::fs::read_to_string(&config_path);
 
-        match config_str {
+        let mut config: Configuration = match config_str {
             Ok(config_str) => {
                 log::debug!("Configuration successfully loaded from file");
-                match toml::from_str(&config_str) {
-                    Ok(config) => Ok(config),
+                match Configuration::parse(&config_str) {
+                    Ok((config, needs_backup)) => {
+                        if needs_backup {
+                            Configuration::backup_file(
+                                &config_path,
+                                config.preferences.backup_retention,
+                            );
+                        }
+                        config
+                    }
                     Err(e) => {
-                        log::error!("Could not parse config file: {:?}", e);
-                        Err(String::from("Could not parse config file"))
+                        // A broken file should not keep the app from opening.
+                        log::error!("Could not parse config file: {}", e);
+                        let backup_path = Configuration::backup_file(
+                            &config_path,
+                            Preferences::default().backup_retention,
+                        );
+                        let mut config = Configuration::bootstrap(dev_mode, overrides)?;
+                        config.load_warnings.push(match backup_path {
+                            Some(backup_path) => format!(
+                                "Configuration file could not be read and was reset, the original was kept at {}: {}",
+                                backup_path.display(),
+                                e
+                            ),
+                            None => format!("Configuration file could not be read and was reset: {}", e),
+                        });
+                        config
                     }
                 }
             }
             Err(_e) => {
                 log::debug!("Configuration file not found, bootstrapping new configuration");
-                Ok(Configuration::bootstrap(dev_mode).unwrap())
+                Configuration::bootstrap(dev_mode, overrides)?
             }
+        };
+
+        if config.version != env!("CARGO_PKG_VERSION") {
+            log::info!(
+                "Configuration was last written by version {}, updating to {}",
+                config.version,
+                env!("CARGO_PKG_VERSION")
+            );
+            config.version = String::from(env!("CARGO_PKG_VERSION"));
+        }
+
+        config.config_path = config_path;
+        config.development_mode = dev_mode;
+        if overrides.data_directory.is_some() || config.data_directory.as_os_str().is_empty() {
+            config.data_directory = Configuration::data_directory(dev_mode, overrides);
+        }
+        if config.db_path.as_os_str().is_empty() {
+            config.db_path = Configuration::default_db_path(dev_mode, &config.data_directory);
+        }
+
+        if let Err(e) = config.preferences.validate() {
+            log::warn!(
+                "Invalid backup retention in config file, using default: {}",
+                e
+            );
+            config.load_warnings.push(format!(
+                "Invalid backup retention was reset to its default: {}",
+                e
+            ));
+            config.preferences.backup_retention = Preferences::default().backup_retention;
+        }
+
+        if !config
+            .profiles
+            .iter()
+            .any(|profile| profile.name == DEFAULT_PROFILE)
+        {
+            config.profiles.insert(
+                0,
+                Profile {
+                    name: String::from(DEFAULT_PROFILE),
+                    db_path: config.db_path.clone(),
+                },
+            );
         }
+
+        let active_profile = config.active_profile.clone();
+        if config.activate_profile(&active_profile).is_err() {
+            log::warn!(
+                "Active profile {} not found, falling back to {}",
+                active_profile,
+                DEFAULT_PROFILE
+            );
+            config.activate_profile(DEFAULT_PROFILE)?;
+        }
+
+        Ok(config)
     }
 
-    fn bootstrap(dev_mode: bool) -> Result<Self, String> {
+    fn bootstrap(dev_mode: bool, overrides: &ConfigurationOverrides) -> Result<Self, String> {
+        let data_directory = Configuration::data_directory(dev_mode, overrides);
+        let db_path = Configuration::default_db_path(dev_mode, &data_directory);
+
+        if !dev_mode && overrides.data_directory.is_none() {
+            Configuration::import_legacy_database(&db_path);
+        }
+
         let config = Configuration {
+            schema_version: CONFIGURATION_VERSION,
             version: String::from(env!("CARGO_PKG_VERSION")),
             development_mode: dev_mode,
-            config_path: Configuration::config_path(dev_mode),
-            db_path: Configuration::db_path(dev_mode),
+            config_path: Configuration::config_path(dev_mode, overrides),
+            data_directory,
+            db_path: db_path.clone(),
+            active_profile: String::from(DEFAULT_PROFILE),
+            profiles: vec![Profile {
+                name: String::from(DEFAULT_PROFILE),
+                db_path,
+            }],
+            preferences: Preferences::default(),
+            database: DatabaseSettings::default(),
+            player_server: PlayerServerSettings::default(),
+            load_warnings: Vec::new(),
         };
 
-        config.save().expect("Could not save config file");
+        config.save()?;
 
         Ok(config)
     }
 
-    fn save(&self) -> Result<(), String> {
+    pub fn save(&self) -> Result<(), String> {
         let config_path = PathBuf::from(&self.config_path);
         let config_str = toml::to_string(&self).expect("Could not serialize config");
 
+        if let Some(parent) = config_path.parent() {
+            if !parent.as_os_str().is_empty() && !parent.exists() {
+                log::info!("Creating configuration directory for {:?}", &config_path);
+                std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+        }
+
         match std::fs::write(&config_path, config_str) {
             Ok(_) => Ok(()),
             Err(e) => {
@@ -122,13 +766,86 @@ impl Configuration {
         }
     }
 
+    pub fn create_profile(&mut self, name: &str) -> Result<&Profile, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(String::from("Profile name cannot be empty"));
+        }
+
+        if self.profiles.iter().any(|profile| profile.name == name) {
+            return Err(format!("Profile {} already exists", name));
+        }
+
+        // Names that differ only in case or punctuation map to the same file.
+        let db_path = self.profile_db_path(name);
+        if let Some(existing) = self
+            .profiles
+            .iter()
+            .find(|profile| profile.db_path == db_path)
+        {
+            return Err(format!(
+                "Profile {} would share a database with profile {}",
+                name, existing.name
+            ));
+        }
+
+        let profile = Profile {
+            name: String::from(name),
+            db_path,
+        };
+        log::info!("Creating profile {} at {:?}", name, &profile.db_path);
+        self.profiles.push(profile);
+
+        Ok(self.profiles.last().unwrap())
+    }
+
+    // Points db_path at the profile's database.
+    pub fn activate_profile(&mut self, name: &str) -> Result<(), String> {
+        let profile = self
+            .profiles
+            .iter()
+            .find(|profile| profile.name == name)
+            .ok_or(format!("Profile {} not found", name))?;
+
+        self.db_path = profile.db_path.clone();
+        self.active_profile = profile.name.clone();
+
+        Ok(())
+    }
+
     pub fn init() -> Result<Self, String> {
-        let dev_mode = cfg!(debug_assertions);
-        plogger::init(dev_mode);
+        Configuration::init_with(ConfigurationOverrides::from_env())
+    }
+
+    pub fn init_with(overrides: ConfigurationOverrides) -> Result<Self, String> {
+        init_logger(cfg!(debug_assertions));
+        // Until the configuration is read, log at the default level.
+        Preferences::default().apply();
         log::debug!("Logger initialised");
+
+        let config = Configuration::load(overrides)?;
+        config.preferences.apply();
+
+        Ok(config)
+    }
+
+    pub fn load(overrides: ConfigurationOverrides) -> Result<Self, String> {
+        let dev_mode = cfg!(debug_assertions);
         log::debug!("Initializing configuration with dev mode - {:?}", dev_mode);
 
-        let config = Configuration::load_from_file(dev_mode).expect("Could not load configuration");
+        let mut config = Configuration::load_from_file(dev_mode, &overrides)?;
+
+        // A profile picked on the command line only applies to this run, it has to exist already.
+        if let Some(profile) = &overrides.profile {
+            if !config.profiles.iter().any(|stored| &stored.name == profile) {
+                return Err(format!("Unknown profile {}", profile));
+            }
+        }
+        config.save()?;
+
+        if let Some(profile) = &overrides.profile {
+            config.activate_profile(profile)?;
+        }
 
         log::debug!("Configuration initialised - {:?}", config);
 
@@ -136,9 +853,104 @@ impl Configuration {
     }
 }
 
+// The logger lets everything through, the logLevel preference sets the level with log::set_max_level.
+fn init_logger(debug: bool) {
+    let mut builder = Builder::new();
+    match debug {
+        true => builder.format(|buf, record| {
+            writeln!(
+                buf,
+                "{} [{}] - {}:{} - {}",
+                Local::now().format("%Y-%m-%dT%H:%M:%S"),
+                record.level(),
+                record.file().unwrap_or_default(),
+                record.line().unwrap_or_default(),
+                record.args()
+            )
+        }),
+        false => builder.format(|buf, record| {
+            writeln!(
+                buf,
+                "{} - {}",
+                Local::now().format("%Y-%m-%dT%H:%M:%S"),
+                record.args()
+            )
+        }),
+    };
+    builder.filter(None, log::LevelFilter::Trace).init();
+}
+
 #[tauri::command]
-pub fn load_configuration_command(configuration: State<Configuration>) -> String {
+pub fn load_configuration_command(configuration: State<Mutex<Configuration>>) -> String {
+    let configuration = configuration.lock().unwrap();
     log::debug!("Running load_configuration_command. {:?}", configuration);
 
-    serde_json::to_string(&configuration.inner()).unwrap()
+    let mut value = serde_json::to_value(&*configuration).unwrap();
+    value["loadWarnings"] = serde_json::json!(configuration.load_warnings);
+
+    value.to_string()
+}
+
+#[tauri::command]
+pub fn create_profile_command(
+    configuration: State<Mutex<Configuration>>,
+    profile_name: String,
+) -> Result<String, String> {
+    log::debug!("Running create_profile_command for {}", profile_name);
+    let mut configuration = configuration.lock().unwrap();
+
+    let profile = configuration.create_profile(&profile_name)?.clone();
+    configuration.save()?;
+
+    Ok(serde_json::to_string(&profile).unwrap())
+}
+
+#[tauri::command]
+pub fn switch_profile_command<R: Runtime>(
+    app: AppHandle<R>,
+    configuration: State<Mutex<Configuration>>,
+    database: State<Database>,
+    profile_name: String,
+) -> Result<String, String> {
+    log::debug!("Running switch_profile_command for {}", profile_name);
+    let mut configuration = configuration.lock().unwrap();
+
+    let previous_profile = configuration.active_profile.clone();
+    configuration.activate_profile(&profile_name)?;
+
+    match storage::setup_database(&configuration) {
+        Ok(db_pool) => database.replace(db_pool),
+        Err(e) => {
+            configuration.activate_profile(&previous_profile)?;
+            return Err(e);
+        }
+    }
+
+    configuration.save()?;
+    log::info!("Switched to profile {}", configuration.active_profile);
+
+    events::emit(
+        &app,
+        [DomainEvent::ProfileSwitched {
+            profile: configuration.active_profile.clone(),
+        }],
+    );
+
+    Ok(serde_json::to_string(&*configuration).unwrap())
+}
+
+#[tauri::command]
+pub fn update_preferences_command(
+    configuration: State<Mutex<Configuration>>,
+    preferences: Preferences,
+) -> Result<String, String> {
+    log::debug!("Running update_preferences_command with {:?}", preferences);
+    preferences.validate()?;
+
+    let mut configuration = configuration.lock().unwrap();
+    configuration.preferences = preferences;
+    configuration.save()?;
+    configuration.preferences.apply();
+
+    Ok(serde_json::to_string(&configuration.preferences).unwrap())
 }
@@ -0,0 +1,300 @@
+use rusqlite::{Connection, Result, Row};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::character::Character;
+use crate::storage;
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum DamageType {
+    Acid,
+    Bludgeoning,
+    Cold,
+    Fire,
+    Force,
+    Lightning,
+    Necrotic,
+    Piercing,
+    Poison,
+    Psychic,
+    Radiant,
+    Slashing,
+    Thunder,
+}
+
+impl DamageType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DamageType::Acid => "acid",
+            DamageType::Bludgeoning => "bludgeoning",
+            DamageType::Cold => "cold",
+            DamageType::Fire => "fire",
+            DamageType::Force => "force",
+            DamageType::Lightning => "lightning",
+            DamageType::Necrotic => "necrotic",
+            DamageType::Piercing => "piercing",
+            DamageType::Poison => "poison",
+            DamageType::Psychic => "psychic",
+            DamageType::Radiant => "radiant",
+            DamageType::Slashing => "slashing",
+            DamageType::Thunder => "thunder",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "acid" => Ok(DamageType::Acid),
+            "bludgeoning" => Ok(DamageType::Bludgeoning),
+            "cold" => Ok(DamageType::Cold),
+            "fire" => Ok(DamageType::Fire),
+            "force" => Ok(DamageType::Force),
+            "lightning" => Ok(DamageType::Lightning),
+            "necrotic" => Ok(DamageType::Necrotic),
+            "piercing" => Ok(DamageType::Piercing),
+            "poison" => Ok(DamageType::Poison),
+            "psychic" => Ok(DamageType::Psychic),
+            "radiant" => Ok(DamageType::Radiant),
+            "slashing" => Ok(DamageType::Slashing),
+            "thunder" => Ok(DamageType::Thunder),
+            _ => Err(format!("Unknown damage type: {}", value)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DamageModifierKind {
+    Resistance,
+    Immunity,
+    Vulnerability,
+}
+
+impl DamageModifierKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DamageModifierKind::Resistance => "resistance",
+            DamageModifierKind::Immunity => "immunity",
+            DamageModifierKind::Vulnerability => "vulnerability",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "resistance" => Ok(DamageModifierKind::Resistance),
+            "immunity" => Ok(DamageModifierKind::Immunity),
+            "vulnerability" => Ok(DamageModifierKind::Vulnerability),
+            _ => Err(format!("Unknown damage modifier: {}", value)),
+        }
+    }
+}
+
+// Limits a modifier to mundane weapons, as in "bludgeoning from nonmagical attacks that aren't
+// silvered". Magical damage always ignores a qualified modifier.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DamageQualifier {
+    Nonmagical,
+    Nonsilvered,
+    Nonadamantine,
+}
+
+impl DamageQualifier {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DamageQualifier::Nonmagical => "nonmagical",
+            DamageQualifier::Nonsilvered => "nonsilvered",
+            DamageQualifier::Nonadamantine => "nonadamantine",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "nonmagical" => Ok(DamageQualifier::Nonmagical),
+            "nonsilvered" => Ok(DamageQualifier::Nonsilvered),
+            "nonadamantine" => Ok(DamageQualifier::Nonadamantine),
+            _ => Err(format!("Unknown damage qualifier: {}", value)),
+        }
+    }
+
+    fn applies_to(&self, damage: &Damage) -> bool {
+        match self {
+            DamageQualifier::Nonmagical => !damage.magical,
+            DamageQualifier::Nonsilvered => !damage.magical && !damage.silvered,
+            DamageQualifier::Nonadamantine => !damage.magical && !damage.adamantine,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct DamageModifier {
+    pub kind: DamageModifierKind,
+    pub damage_type: DamageType,
+    #[serde(default)]
+    pub qualifier: Option<DamageQualifier>,
+}
+
+impl DamageModifier {
+    pub fn new(
+        kind: DamageModifierKind,
+        damage_type: DamageType,
+        qualifier: Option<DamageQualifier>,
+    ) -> Self {
+        DamageModifier {
+            kind,
+            damage_type,
+            qualifier,
+        }
+    }
+
+    pub fn applies_to(&self, damage: &Damage) -> bool {
+        self.damage_type == damage.damage_type
+            && self
+                .qualifier
+                .is_none_or(|qualifier| qualifier.applies_to(damage))
+    }
+
+    // Reads like a stat block entry, e.g. "resistance to nonmagical bludgeoning".
+    pub fn describe(&self) -> String {
+        match self.qualifier {
+            Some(qualifier) => format!(
+                "{} to {} {}",
+                self.kind.as_str(),
+                qualifier.as_str(),
+                self.damage_type.as_str()
+            ),
+            None => format!("{} to {}", self.kind.as_str(), self.damage_type.as_str()),
+        }
+    }
+
+    pub(crate) fn save_all(
+        character_id: Uuid,
+        damage_modifiers: &[DamageModifier],
+        connection: &Connection,
+    ) -> Result<(), rusqlite::Error> {
+        connection.execute(
+            "DELETE FROM character_damage_modifiers WHERE character_id = ?1",
+            rusqlite::params![character_id.to_string()],
+        )?;
+
+        for damage_modifier in damage_modifiers {
+            connection.execute(
+                "INSERT INTO character_damage_modifiers (character_id, kind, damage_type, qualifier) VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![
+                    character_id.to_string(),
+                    damage_modifier.kind.as_str(),
+                    damage_modifier.damage_type.as_str(),
+                    damage_modifier.qualifier.map(|qualifier| qualifier.as_str())
+                ],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        let kind: String = row.get("kind")?;
+        let damage_type: String = row.get("damage_type")?;
+        let qualifier: Option<String> = row.get("qualifier")?;
+
+        Ok(DamageModifier {
+            kind: DamageModifierKind::parse(&kind).unwrap(),
+            damage_type: DamageType::parse(&damage_type).unwrap(),
+            qualifier: qualifier.map(|qualifier| DamageQualifier::parse(&qualifier).unwrap()),
+        })
+    }
+
+    // Loads the modifiers of many characters at once, like their class levels.
+    pub fn load_for<'a, I: IntoIterator<Item = &'a mut Character>>(
+        characters: I,
+        connection: &Connection,
+    ) -> Result<(), rusqlite::Error> {
+        let characters: Vec<&mut Character> = characters.into_iter().collect();
+        let character_ids: Vec<Uuid> = characters.iter().map(|character| character.id).collect();
+        let mut damage_modifiers = storage::load_grouped_by_id(
+            connection,
+            "character_damage_modifiers",
+            "character_id",
+            "rowid",
+            &character_ids,
+            DamageModifier::from_row,
+        )?;
+
+        for character in characters {
+            character.damage_modifiers = damage_modifiers.remove(&character.id).unwrap_or_default();
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Damage {
+    pub amount: i32,
+    pub damage_type: DamageType,
+    #[serde(default)]
+    pub magical: bool,
+    #[serde(default)]
+    pub silvered: bool,
+    #[serde(default)]
+    pub adamantine: bool,
+}
+
+impl Damage {
+    pub fn new(amount: i32, damage_type: DamageType) -> Self {
+        Damage {
+            amount,
+            damage_type,
+            magical: false,
+            silvered: false,
+            adamantine: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct AppliedDamage {
+    pub damage_type: DamageType,
+    pub rolled: i32,
+    pub applied: i32,
+    // The modifiers that changed the amount, e.g. "resistance to fire".
+    pub reasons: Vec<String>,
+}
+
+// Immunity wins outright. Otherwise resistance halves (rounding down) and vulnerability doubles,
+// each at most once and in that order.
+pub fn adjust(damage: &Damage, damage_modifiers: &[DamageModifier]) -> AppliedDamage {
+    let amount = damage.amount.max(0);
+    let matching = |kind: DamageModifierKind| {
+        damage_modifiers.iter().find(|damage_modifier| {
+            damage_modifier.kind == kind && damage_modifier.applies_to(damage)
+        })
+    };
+
+    if let Some(immunity) = matching(DamageModifierKind::Immunity) {
+        return AppliedDamage {
+            damage_type: damage.damage_type,
+            rolled: amount,
+            applied: 0,
+            reasons: vec![immunity.describe()],
+        };
+    }
+
+    let mut applied = amount;
+    let mut reasons = Vec::new();
+    if let Some(resistance) = matching(DamageModifierKind::Resistance) {
+        applied /= 2;
+        reasons.push(resistance.describe());
+    }
+    if let Some(vulnerability) = matching(DamageModifierKind::Vulnerability) {
+        applied *= 2;
+        reasons.push(vulnerability.describe());
+    }
+
+    AppliedDamage {
+        damage_type: damage.damage_type,
+        rolled: amount,
+        applied,
+        reasons,
+    }
+}
@@ -0,0 +1,184 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// Large enough for any stat block, small enough that totals always fit in an i32.
+const MAX_DICE: u32 = 100;
+const MAX_SIDES: u32 = 1000;
+const MAX_MODIFIER: i32 = 10000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DiceExpression {
+    pub count: u32,
+    pub sides: u32,
+    pub modifier: i32,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DiceRoll {
+    pub expression: String,
+    pub rolls: Vec<u32>,
+    pub modifier: i32,
+    pub total: i32,
+}
+
+impl DiceExpression {
+    pub fn new(count: u32, sides: u32, modifier: i32) -> Self {
+        DiceExpression {
+            count,
+            sides,
+            modifier,
+        }
+    }
+
+    // Accepts expressions like "2d6+3", "d20", "1d8-1" or a flat "4".
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let expression: String = expression
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_lowercase();
+
+        if expression.is_empty() {
+            return Err(String::from("Empty dice expression"));
+        }
+
+        let (dice, modifier) = match expression.rfind(['+', '-']) {
+            Some(index) if index > 0 => {
+                let modifier = expression[index..]
+                    .parse::<i32>()
+                    .map_err(|_| format!("Invalid modifier in dice expression: {}", expression))?;
+                (&expression[..index], modifier)
+            }
+            _ => (expression.as_str(), 0),
+        };
+        if modifier.abs() > MAX_MODIFIER {
+            return Err(format!(
+                "Dice modifier must be at most {}: {}",
+                MAX_MODIFIER, expression
+            ));
+        }
+
+        let Some((count, sides)) = dice.split_once('d') else {
+            let flat = dice
+                .parse::<i32>()
+                .ok()
+                .filter(|flat| flat.abs() <= MAX_MODIFIER)
+                .ok_or(format!("Invalid dice expression: {}", expression))?;
+            return Ok(DiceExpression::new(0, 0, flat + modifier));
+        };
+
+        let count = if count.is_empty() {
+            1
+        } else {
+            count
+                .parse::<u32>()
+                .map_err(|_| format!("Invalid dice count in expression: {}", expression))?
+        };
+        let sides = sides
+            .parse::<u32>()
+            .map_err(|_| format!("Invalid dice sides in expression: {}", expression))?;
+
+        if sides == 0 {
+            return Err(format!("Dice must have at least one side: {}", expression));
+        }
+        if count > MAX_DICE {
+            return Err(format!(
+                "At most {} dice can be rolled at once: {}",
+                MAX_DICE, expression
+            ));
+        }
+        if sides > MAX_SIDES {
+            return Err(format!(
+                "Dice can have at most {} sides: {}",
+                MAX_SIDES, expression
+            ));
+        }
+
+        Ok(DiceExpression::new(count, sides, modifier))
+    }
+
+    // Rounds down like the fixed hit point values in stat blocks, e.g. 2d6 averages to 7.
+    pub fn average(&self) -> i32 {
+        (self.count * (self.sides + 1) / 2) as i32 + self.modifier
+    }
+
+    pub fn maximum(&self) -> i32 {
+        (self.count * self.sides) as i32 + self.modifier
+    }
+
+    pub fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> DiceRoll {
+        let rolls: Vec<u32> = (0..self.count)
+            .map(|_| rng.gen_range(1..=self.sides))
+            .collect();
+        let total = rolls.iter().map(|roll| *roll as i32).sum::<i32>() + self.modifier;
+
+        DiceRoll {
+            expression: self.to_string(),
+            rolls,
+            modifier: self.modifier,
+            total,
+        }
+    }
+}
+
+impl std::fmt::Display for DiceExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.count == 0 {
+            return write!(f, "{}", self.modifier);
+        }
+
+        match self.modifier {
+            0 => write!(f, "{}d{}", self.count, self.sides),
+            modifier if modifier > 0 => write!(f, "{}d{}+{}", self.count, self.sides, modifier),
+            modifier => write!(f, "{}d{}{}", self.count, self.sides, modifier),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RollMode {
+    #[default]
+    Normal,
+    Advantage,
+    Disadvantage,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct D20Roll {
+    pub mode: RollMode,
+    pub rolls: Vec<u32>,
+    // The die that counts after advantage or disadvantage.
+    pub natural: u32,
+}
+
+impl RollMode {
+    pub fn roll_d20<R: Rng + ?Sized>(&self, rng: &mut R) -> D20Roll {
+        let rolls: Vec<u32> = match self {
+            RollMode::Normal => vec![rng.gen_range(1..=20)],
+            RollMode::Advantage | RollMode::Disadvantage => {
+                vec![rng.gen_range(1..=20), rng.gen_range(1..=20)]
+            }
+        };
+        let natural = match self {
+            RollMode::Disadvantage => *rolls.iter().min().unwrap(),
+            _ => *rolls.iter().max().unwrap(),
+        };
+
+        D20Roll {
+            mode: *self,
+            rolls,
+            natural,
+        }
+    }
+}
+
+impl D20Roll {
+    pub fn is_critical(&self) -> bool {
+        self.natural == 20
+    }
+
+    pub fn is_fumble(&self) -> bool {
+        self.natural == 1
+    }
+}
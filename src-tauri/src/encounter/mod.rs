@@ -1,12 +1,18 @@
-use chrono::{DateTime, Utc};
-use r2d2::{Pool, PooledConnection};
+use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::{params, Connection, Result, Row, Statement};
-use serde::{Deserialize, Serialize};
-use tauri::State;
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use serde::Serialize;
+use tauri::{AppHandle, Runtime, State};
 use uuid::Uuid;
 
-use crate::character::{self, Character};
+use crate::abilities::Proficiencies;
+use crate::calendar::Clock;
+use crate::character::{Character, ClassLevel};
+use crate::damage::DamageModifier;
+use crate::events::{self, DomainEvent};
+use crate::location::parse_location_id;
+use crate::repository::{CharacterRepository, EncounterRepository, SqliteRepository, UnitOfWork};
+use crate::storage::Database;
 
 #[derive(Debug, Serialize)]
 pub struct EncounterCharacter {
@@ -28,8 +34,7 @@ impl EncounterCharacter {
         }
     }
 
-    pub fn save(&self, db_pool: &Pool<SqliteConnectionManager>) -> Result<(), rusqlite::Error> {
-        let conn = db_pool.get().unwrap();
+    pub fn save(&self, conn: &Connection) -> Result<(), rusqlite::Error> {
         conn.execute(
             "INSERT INTO encounter_characters (id, character_id, encounter_id, initiative) VALUES (?1, ?2, ?3, ?4)",
             params![
@@ -45,32 +50,50 @@ impl EncounterCharacter {
 
     pub fn load_for_encounter(
         encounter: Encounter,
-        conn: &PooledConnection<SqliteConnectionManager>,
-    ) -> Vec<Self> {
-        let mut statement = conn
-            .prepare("SELECT * FROM encounter_characters WHERE encounter_id = ?")
-            .unwrap();
+        conn: &Connection,
+    ) -> Result<Vec<Self>, rusqlite::Error> {
+        let mut statement = conn.prepare(
+            "SELECT encounter_characters.id AS encounter_character_id,
+                encounter_characters.initiative AS encounter_initiative,
+                characters.*
+            FROM encounter_characters
+            INNER JOIN characters ON characters.id = encounter_characters.character_id
+            WHERE encounter_characters.encounter_id = ?",
+        )?;
 
-        let characters = statement
+        let mut characters = statement
             .query_map(params![encounter.id.to_string()], |row| {
-                let uuid: String = row.get("id")?;
-                let character_id: String = row.get("character_id")?;
-                let character =
-                    Character::load_by_id(Uuid::parse_str(&character_id).unwrap(), conn).unwrap();
+                let uuid: String = row.get("encounter_character_id")?;
 
                 Ok(EncounterCharacter {
                     id: Uuid::parse_str(&uuid).unwrap(),
-                    character,
+                    character: Character::from_row(row)?,
                     encounter: encounter.clone(),
-                    initiative: row.get("initiative")?,
+                    initiative: row.get("encounter_initiative")?,
                     status_effects: Vec::new(),
                 })
-            })
-            .unwrap()
-            .map(|character| character.unwrap())
-            .collect();
+            })?
+            .collect::<Result<Vec<Self>>>()?;
+        ClassLevel::load_for(
+            characters
+                .iter_mut()
+                .map(|encounter_character| &mut encounter_character.character),
+            conn,
+        )?;
+        Proficiencies::load_for(
+            characters
+                .iter_mut()
+                .map(|encounter_character| &mut encounter_character.character),
+            conn,
+        )?;
+        DamageModifier::load_for(
+            characters
+                .iter_mut()
+                .map(|encounter_character| &mut encounter_character.character),
+            conn,
+        )?;
 
-        characters
+        Ok(characters)
     }
 }
 
@@ -78,6 +101,11 @@ impl EncounterCharacter {
 pub struct Encounter {
     pub id: Uuid,
     pub encounter_title: String,
+    pub round: i32,
+    pub turn_index: i32,
+    pub location_id: Option<Uuid>,
+    // Minutes on the campaign clock when the encounter was created.
+    pub in_game_time: Option<i64>,
 }
 
 impl Encounter {
@@ -85,25 +113,55 @@ impl Encounter {
         Self {
             id: Uuid::new_v4(),
             encounter_title,
+            round: 0,
+            turn_index: 0,
+            location_id: None,
+            in_game_time: None,
         }
     }
 
-    pub fn save(&self, db_pool: &Pool<SqliteConnectionManager>) -> Result<(), rusqlite::Error> {
-        let conn = db_pool.get().unwrap();
+    pub fn save(&self, conn: &Connection) -> Result<(), rusqlite::Error> {
         conn.execute(
-            "INSERT INTO encounters (id, encounter_title) VALUES (?1, ?2)",
-            params![self.id.to_string(), self.encounter_title],
+            "INSERT INTO encounters (id, encounter_title, round, turn_index, location_id, in_game_time) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                self.id.to_string(),
+                self.encounter_title,
+                self.round,
+                self.turn_index,
+                self.location_id.map(|location_id| location_id.to_string()),
+                self.in_game_time
+            ],
         )?;
 
         Ok(())
     }
 
-    fn from_row(row: &Row) -> Result<Self> {
+    pub fn update(&self, conn: &Connection) -> Result<(), rusqlite::Error> {
+        conn.execute(
+            "UPDATE encounters SET encounter_title = ?2, round = ?3, turn_index = ?4, location_id = ?5 WHERE id = ?1",
+            params![
+                self.id.to_string(),
+                self.encounter_title,
+                self.round,
+                self.turn_index,
+                self.location_id.map(|location_id| location_id.to_string())
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub(crate) fn from_row(row: &Row) -> Result<Self> {
         let encounter_id: String = row.get("id")?;
+        let location_id: Option<String> = row.get("location_id")?;
 
         Ok(Encounter {
             id: Uuid::parse_str(&encounter_id).unwrap(),
             encounter_title: row.get("encounter_title")?,
+            round: row.get("round")?,
+            turn_index: row.get("turn_index")?,
+            location_id: location_id.map(|location_id| Uuid::parse_str(&location_id).unwrap()),
+            in_game_time: row.get("in_game_time")?,
         })
     }
 
@@ -111,10 +169,15 @@ impl Encounter {
         db_pool: &Pool<SqliteConnectionManager>,
     ) -> Result<Vec<Self>, rusqlite::Error> {
         let conn = db_pool.get().unwrap();
+
+        Self::load_all_with_connection(&conn)
+    }
+
+    pub fn load_all_with_connection(conn: &Connection) -> Result<Vec<Self>, rusqlite::Error> {
         let mut statement = conn.prepare("SELECT * FROM encounters")?;
 
         let encounters = statement
-            .query_map([], |row| Self::from_row(row))?
+            .query_map([], Self::from_row)?
             .map(|encounter| encounter.unwrap())
             .collect();
 
@@ -126,15 +189,19 @@ impl Encounter {
         encounter_id: Uuid,
     ) -> Result<Self, rusqlite::Error> {
         let conn = db_pool.get().unwrap();
-        let mut statement = conn.prepare("SELECT * FROM encounters WHERE id = ?")?;
 
-        let encounter = statement
-            .query_map(params![encounter_id.to_string()], |row| Self::from_row(row))?
-            .map(|encounter| encounter.unwrap())
-            .next()
-            .unwrap();
+        Self::load_by_id_with_connection(&conn, encounter_id)
+    }
 
-        Ok(encounter)
+    pub fn load_by_id_with_connection(
+        conn: &Connection,
+        encounter_id: Uuid,
+    ) -> Result<Self, rusqlite::Error> {
+        conn.query_row(
+            "SELECT * FROM encounters WHERE id = ?",
+            params![encounter_id.to_string()],
+            Self::from_row,
+        )
     }
 }
 
@@ -149,79 +216,283 @@ impl EncounterDetail {
         db_pool: &Pool<SqliteConnectionManager>,
         encounter_id: Uuid,
     ) -> Result<Self, rusqlite::Error> {
-        let conn = db_pool.get().unwrap();
-        let encounter = Encounter::load_by_id(&db_pool, encounter_id).unwrap();
+        let mut conn = db_pool.get().unwrap();
+        let transaction = conn.transaction()?;
+        let encounter_detail = Self::load_by_id_with_connection(&transaction, encounter_id)?;
+        transaction.commit()?;
 
-        let encounter_characters = EncounterCharacter::load_for_encounter(encounter.clone(), &conn);
+        Ok(encounter_detail)
+    }
+
+    pub fn load_by_id_with_connection(
+        conn: &Connection,
+        encounter_id: Uuid,
+    ) -> Result<Self, rusqlite::Error> {
+        let encounter = Encounter::load_by_id_with_connection(conn, encounter_id)?;
+        let encounter_characters = EncounterCharacter::load_for_encounter(encounter.clone(), conn)?;
 
         Ok(EncounterDetail {
             encounter,
             characters: encounter_characters,
         })
     }
+
+    // Highest initiative acts first, participants without initiative go last. Ties go by name.
+    pub fn turn_order(&self) -> Vec<&EncounterCharacter> {
+        let mut turn_order: Vec<&EncounterCharacter> = self.characters.iter().collect();
+        turn_order.sort_by(|a, b| {
+            b.initiative
+                .cmp(&a.initiative)
+                .then_with(|| a.character.name.cmp(&b.character.name))
+                .then_with(|| a.id.cmp(&b.id))
+        });
+
+        turn_order
+    }
+
+    pub fn active_participant(&self) -> Option<&EncounterCharacter> {
+        if self.encounter.round == 0 {
+            return None;
+        }
+
+        self.turn_order()
+            .get(self.encounter.turn_index as usize)
+            .copied()
+    }
+}
+
+pub fn advance_turn<R: EncounterRepository>(
+    repository: &R,
+    encounter_id: Uuid,
+) -> Result<EncounterDetail, String> {
+    let encounter_detail = repository
+        .load_encounter_detail(encounter_id)?
+        .ok_or(format!("Encounter {} not found", encounter_id))?;
+
+    if encounter_detail.characters.is_empty() {
+        return Err(String::from(
+            "Cannot advance turns in an encounter without participants",
+        ));
+    }
+
+    let mut encounter = encounter_detail.encounter;
+    if encounter.round == 0 {
+        encounter.round = 1;
+        encounter.turn_index = 0;
+    } else if encounter.turn_index + 1 >= encounter_detail.characters.len() as i32 {
+        encounter.round += 1;
+        encounter.turn_index = 0;
+    } else {
+        encounter.turn_index += 1;
+    }
+
+    repository.update_encounter(&encounter)?;
+
+    repository
+        .load_encounter_detail(encounter_id)?
+        .ok_or(format!("Encounter {} not found", encounter_id))
+}
+
+// New encounters start at the campaign clock's current time.
+pub fn create_encounter(
+    repository: &SqliteRepository,
+    encounter_title: String,
+    location_id: Option<String>,
+) -> Result<Encounter, String> {
+    let mut encounter = Encounter::new(encounter_title);
+    {
+        let conn = repository.connection()?;
+        encounter.location_id = parse_location_id(location_id, &conn)?;
+        encounter.in_game_time = Clock::current_time(&conn).map_err(|e| e.to_string())?;
+    }
+    repository.save_encounter(&encounter)?;
+
+    Ok(encounter)
 }
 
 #[tauri::command]
-pub fn create_encounter_command(
-    db_pool: State<Pool<SqliteConnectionManager>>,
+pub fn create_encounter_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
     encounter_title: String,
+    location_id: Option<String>,
 ) -> Result<(), String> {
+    let db_pool = database.pool();
     log::debug!("Creating encounter with title: {}", encounter_title);
-    let encounter = Encounter::new(encounter_title);
-    match encounter.save(&db_pool) {
-        Ok(_) => Ok(()),
-        Err(e) => Err(e.to_string()),
-    }
+    let encounter = create_encounter(
+        &SqliteRepository::new(&db_pool),
+        encounter_title,
+        location_id,
+    )?;
+    events::emit(
+        &app,
+        [DomainEvent::EncounterCreated {
+            encounter_id: encounter.id,
+        }],
+    );
+
+    Ok(())
 }
 
 #[tauri::command]
-pub fn load_encounters_command(
-    db_pool: State<Pool<SqliteConnectionManager>>,
+pub fn set_encounter_location_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    encounter_id: String,
+    location_id: Option<String>,
 ) -> Result<String, String> {
-    let encounters = Encounter::load_all_encounters(&db_pool).unwrap();
+    let db_pool = database.pool();
+    log::debug!("Moving encounter {} to {:?}", encounter_id, location_id);
+    let encounter_id = Uuid::parse_str(&encounter_id).map_err(|e| e.to_string())?;
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+
+    let mut encounter = Encounter::load_by_id_with_connection(&conn, encounter_id)
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or(format!("Encounter {} not found", encounter_id))?;
+    encounter.location_id = parse_location_id(location_id, &conn)?;
+    encounter.update(&conn).map_err(|e| e.to_string())?;
+
+    events::emit(&app, [DomainEvent::EncounterUpdated { encounter_id }]);
+
+    Ok(serde_json::to_string(&encounter).unwrap())
+}
+
+#[tauri::command]
+pub fn load_encounters_command(database: State<Database>) -> Result<String, String> {
+    let db_pool = database.pool();
+    let encounters = SqliteRepository::new(&db_pool).load_encounters()?;
 
     Ok(serde_json::to_string(&encounters).unwrap())
 }
 
 #[tauri::command]
 pub fn load_encounter_detail_command(
-    db_pool: State<Pool<SqliteConnectionManager>>,
+    database: State<Database>,
     encounter_id: String,
 ) -> Result<String, String> {
+    let db_pool = database.pool();
     log::debug!(
         "Running load_encounter_detail_command for encounter_id: {}",
         encounter_id
     );
 
-    let encounter_detail =
-        EncounterDetail::load_by_id(&db_pool, Uuid::parse_str(&encounter_id).unwrap()).unwrap();
+    let encounter_id = Uuid::parse_str(&encounter_id).map_err(|e| e.to_string())?;
+    let encounter_detail = SqliteRepository::new(&db_pool)
+        .load_encounter_detail(encounter_id)?
+        .ok_or(format!("Encounter {} not found", encounter_id))?;
 
     Ok(serde_json::to_string(&encounter_detail).unwrap())
 }
 
+// The participant is added and the turn moved in one transaction.
+pub fn add_character_to_encounter<R: UnitOfWork>(
+    repository: &R,
+    encounter_id: Uuid,
+    character_id: Uuid,
+    initiative: Option<i32>,
+) -> Result<EncounterCharacter, String> {
+    repository.in_transaction(|repository| {
+        let character = repository
+            .load_character(character_id)?
+            .ok_or(format!("Character {} not found", character_id))?;
+
+        let encounter = repository
+            .load_encounter(encounter_id)?
+            .ok_or(format!("Encounter {} not found", encounter_id))?;
+
+        let active_participant_id = match encounter.round {
+            0 => None,
+            _ => repository
+                .load_encounter_detail(encounter_id)?
+                .and_then(|encounter_detail| {
+                    encounter_detail
+                        .active_participant()
+                        .map(|participant| participant.id)
+                }),
+        };
+
+        let mut encounter_character = EncounterCharacter::new(character, encounter);
+        encounter_character.initiative = initiative;
+        repository.save_participant(&encounter_character)?;
+
+        // Joining mid-combat can shift the turn order, the turn stays with whoever had it.
+        if let Some(active_participant_id) = active_participant_id {
+            let encounter_detail = repository
+                .load_encounter_detail(encounter_id)?
+                .ok_or(format!("Encounter {} not found", encounter_id))?;
+            let turn_index = encounter_detail
+                .turn_order()
+                .iter()
+                .position(|participant| participant.id == active_participant_id);
+
+            if let Some(turn_index) = turn_index {
+                let mut encounter = encounter_detail.encounter;
+                encounter.turn_index = turn_index as i32;
+                repository.update_encounter(&encounter)?;
+                encounter_character.encounter = encounter;
+            }
+        }
+
+        Ok(encounter_character)
+    })
+}
+
 #[tauri::command]
-pub fn add_character_to_encounter_command(
-    db_pool: State<Pool<SqliteConnectionManager>>,
+pub fn add_character_to_encounter_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
     encounter_id: String,
     character_id: String,
+    initiative: Option<i32>,
 ) -> Result<String, String> {
+    let db_pool = database.pool();
     log::debug!(
         "Adding character {} to encounter {}",
         character_id,
         encounter_id
     );
-    let conn = db_pool.get().unwrap();
+    let encounter_id = Uuid::parse_str(&encounter_id).map_err(|e| e.to_string())?;
+    let character_id = Uuid::parse_str(&character_id).map_err(|e| e.to_string())?;
 
-    let character =
-        character::Character::load_by_id(Uuid::parse_str(&character_id).unwrap(), &conn).unwrap();
+    let participant = add_character_to_encounter(
+        &SqliteRepository::new(&db_pool),
+        encounter_id,
+        character_id,
+        initiative,
+    )?;
+    events::emit(
+        &app,
+        [DomainEvent::EncounterParticipantAdded {
+            encounter_id,
+            participant_id: participant.id,
+            character_id,
+        }],
+    );
 
-    let encounter =
-        Encounter::load_by_id(&db_pool, Uuid::parse_str(&encounter_id).unwrap()).unwrap();
+    Ok(serde_json::to_string("").unwrap())
+}
 
-    let encounter_character = EncounterCharacter::new(character, encounter);
-    encounter_character.save(&db_pool).unwrap();
+#[tauri::command]
+pub fn advance_turn_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    encounter_id: String,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Advancing turn in encounter {}", encounter_id);
+    let encounter_id = Uuid::parse_str(&encounter_id).map_err(|e| e.to_string())?;
+
+    let encounter_detail = advance_turn(&SqliteRepository::new(&db_pool), encounter_id)?;
+    events::emit(
+        &app,
+        [DomainEvent::TurnAdvanced {
+            encounter_id,
+            round: encounter_detail.encounter.round,
+        }],
+    );
 
-    Ok(serde_json::to_string("").unwrap())
+    Ok(serde_json::to_string(&encounter_detail).unwrap())
 }
 
 // impl EncounterCharacter {
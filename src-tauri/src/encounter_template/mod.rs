@@ -0,0 +1,418 @@
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rand::Rng;
+use rusqlite::{params, Connection, Result, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Runtime, State};
+use uuid::Uuid;
+
+use crate::calendar::Clock;
+use crate::character::Character;
+use crate::configuration::{Configuration, HitPointMethod, Preferences};
+use crate::dice::DiceExpression;
+use crate::encounter::{Encounter, EncounterCharacter, EncounterDetail};
+use crate::events::{self, DomainEvent};
+use crate::storage::Database;
+
+// Each copy becomes a character row when the template is instantiated.
+const MAX_COMBATANT_COUNT: i32 = 50;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TemplateCombatant {
+    pub id: Uuid,
+    pub template_id: Uuid,
+    pub name: String,
+    pub class: String,
+    pub race: String,
+    pub level: i32,
+    pub hit_points: i32,
+    pub hit_dice: Option<String>,
+    pub armor_class: i32,
+    pub count: i32,
+    pub notes: String,
+}
+
+impl TemplateCombatant {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        template_id: Uuid,
+        name: String,
+        class: String,
+        race: String,
+        level: i32,
+        hit_points: i32,
+        hit_dice: Option<String>,
+        armor_class: i32,
+        count: i32,
+        notes: String,
+    ) -> Result<Self, String> {
+        if !(1..=20).contains(&level) {
+            return Err(String::from("A combatant needs a level between 1 and 20"));
+        }
+
+        if !(1..=MAX_COMBATANT_COUNT).contains(&count) {
+            return Err(format!(
+                "A combatant needs a count between 1 and {}",
+                MAX_COMBATANT_COUNT
+            ));
+        }
+
+        if let Some(hit_dice) = &hit_dice {
+            DiceExpression::parse(hit_dice)?;
+        }
+
+        Ok(TemplateCombatant {
+            id: Uuid::new_v4(),
+            template_id,
+            name,
+            class,
+            race,
+            level,
+            hit_points,
+            hit_dice,
+            armor_class,
+            count,
+            notes,
+        })
+    }
+
+    pub fn save(&self, connection: &Connection) -> Result<(), rusqlite::Error> {
+        connection.execute(
+            "INSERT INTO encounter_template_combatants (id, template_id, name, class, race, level, hit_points, hit_dice, armor_class, count, notes) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                self.id.to_string(),
+                self.template_id.to_string(),
+                self.name,
+                self.class,
+                self.race,
+                self.level,
+                self.hit_points,
+                self.hit_dice,
+                self.armor_class,
+                self.count,
+                self.notes
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        let id: String = row.get("id")?;
+        let template_id: String = row.get("template_id")?;
+
+        Ok(TemplateCombatant {
+            id: Uuid::parse_str(&id).unwrap(),
+            template_id: Uuid::parse_str(&template_id).unwrap(),
+            name: row.get("name")?,
+            class: row.get("class")?,
+            race: row.get("race")?,
+            level: row.get("level")?,
+            hit_points: row.get("hit_points")?,
+            hit_dice: row.get("hit_dice")?,
+            armor_class: row.get("armor_class")?,
+            count: row.get("count")?,
+            notes: row.get("notes")?,
+        })
+    }
+
+    fn hit_points_for<R: Rng + ?Sized>(&self, method: HitPointMethod, rng: &mut R) -> i32 {
+        let Some(Ok(expression)) = self.hit_dice.as_deref().map(DiceExpression::parse) else {
+            return self.hit_points;
+        };
+
+        let hit_points = match method {
+            HitPointMethod::Average => expression.average(),
+            HitPointMethod::Roll => expression.roll(rng).total,
+            HitPointMethod::Maximum => expression.maximum(),
+        };
+
+        hit_points.max(1)
+    }
+
+    pub fn to_character<R: Rng + ?Sized>(
+        &self,
+        number: i32,
+        preferences: &Preferences,
+        rng: &mut R,
+    ) -> Character {
+        let name = if self.count > 1 {
+            format!("{} {}", self.name, number)
+        } else {
+            self.name.clone()
+        };
+
+        Character::new(
+            name,
+            self.class.clone(),
+            self.race.clone(),
+            None,
+            self.level,
+            0,
+            self.hit_points_for(preferences.monster_hit_point_method(), rng),
+            self.armor_class,
+            self.notes.clone(),
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct EncounterTemplate {
+    pub id: Uuid,
+    pub template_title: String,
+    pub combatants: Vec<TemplateCombatant>,
+}
+
+impl EncounterTemplate {
+    pub fn new(template_title: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            template_title,
+            combatants: Vec::new(),
+        }
+    }
+
+    pub fn save(&self, db_pool: &Pool<SqliteConnectionManager>) -> Result<(), rusqlite::Error> {
+        let conn = db_pool.get().unwrap();
+        conn.execute(
+            "INSERT INTO encounter_templates (id, template_title) VALUES (?1, ?2)",
+            params![self.id.to_string(), self.template_title],
+        )?;
+
+        Ok(())
+    }
+
+    // Combatants are filled in by the caller, so a list of templates needs one query for all of them.
+    fn from_row(row: &Row) -> Result<Self> {
+        let template_id: String = row.get("id")?;
+
+        Ok(EncounterTemplate {
+            id: Uuid::parse_str(&template_id).unwrap(),
+            template_title: row.get("template_title")?,
+            combatants: Vec::new(),
+        })
+    }
+
+    fn load_combatants(template_id: Uuid, conn: &Connection) -> Result<Vec<TemplateCombatant>> {
+        let mut statement =
+            conn.prepare("SELECT * FROM encounter_template_combatants WHERE template_id = ?")?;
+
+        let combatants = statement
+            .query_map(params![template_id.to_string()], |row| {
+                TemplateCombatant::from_row(row)
+            })?
+            .collect();
+
+        combatants
+    }
+
+    pub fn load_all_templates(
+        db_pool: &Pool<SqliteConnectionManager>,
+    ) -> Result<Vec<Self>, rusqlite::Error> {
+        let conn = db_pool.get().unwrap();
+        let mut statement = conn.prepare("SELECT * FROM encounter_templates")?;
+        let mut templates = statement
+            .query_map([], Self::from_row)?
+            .collect::<Result<Vec<Self>>>()?;
+
+        let mut statement = conn
+            .prepare("SELECT * FROM encounter_template_combatants ORDER BY template_id, rowid")?;
+        let mut combatants: HashMap<Uuid, Vec<TemplateCombatant>> = HashMap::new();
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let combatant = TemplateCombatant::from_row(row)?;
+            combatants
+                .entry(combatant.template_id)
+                .or_default()
+                .push(combatant);
+        }
+
+        for template in templates.iter_mut() {
+            template.combatants = combatants.remove(&template.id).unwrap_or_default();
+        }
+
+        Ok(templates)
+    }
+
+    pub fn load_by_id(
+        db_pool: &Pool<SqliteConnectionManager>,
+        template_id: Uuid,
+    ) -> Result<Option<Self>, rusqlite::Error> {
+        let conn = db_pool.get().unwrap();
+        let mut statement = conn.prepare("SELECT * FROM encounter_templates WHERE id = ?")?;
+
+        let mut template = statement
+            .query_map(params![template_id.to_string()], Self::from_row)?
+            .next()
+            .transpose()?;
+        if let Some(template) = template.as_mut() {
+            template.combatants = Self::load_combatants(template.id, &conn)?;
+        }
+
+        Ok(template)
+    }
+
+    pub fn instantiate<R: Rng + ?Sized>(
+        &self,
+        encounter_title: String,
+        db_pool: &Pool<SqliteConnectionManager>,
+        preferences: &Preferences,
+        rng: &mut R,
+    ) -> Result<Encounter, rusqlite::Error> {
+        // Everything goes through one transaction, so a failed combatant leaves no half-built encounter.
+        let mut conn = db_pool.get().unwrap();
+        let transaction = conn.transaction()?;
+        let mut encounter = Encounter::new(encounter_title);
+        encounter.in_game_time = Clock::current_time(&transaction)?;
+        encounter.save(&transaction)?;
+
+        for combatant in &self.combatants {
+            for number in 1..=combatant.count {
+                let character = combatant.to_character(number, preferences, rng);
+                character.save(&transaction)?;
+
+                let mut encounter_character = EncounterCharacter::new(character, encounter.clone());
+                if preferences.rolls_monster_initiative() {
+                    encounter_character.initiative =
+                        Some(DiceExpression::new(1, 20, 0).roll(rng).total);
+                }
+                encounter_character.save(&transaction)?;
+            }
+        }
+        transaction.commit()?;
+
+        Ok(encounter)
+    }
+}
+
+#[tauri::command]
+pub fn create_encounter_template_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    template_title: String,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Creating encounter template with title: {}", template_title);
+    let template = EncounterTemplate::new(template_title);
+    template.save(&db_pool).map_err(|e| e.to_string())?;
+
+    events::emit(
+        &app,
+        [DomainEvent::EncounterTemplateCreated {
+            template_id: template.id,
+        }],
+    );
+
+    Ok(serde_json::to_string(&template).unwrap())
+}
+
+#[tauri::command]
+pub fn load_encounter_templates_command(database: State<Database>) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Running load_encounter_templates_command");
+    let templates = EncounterTemplate::load_all_templates(&db_pool).map_err(|e| e.to_string())?;
+
+    Ok(serde_json::to_string(&templates).unwrap())
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn add_combatant_to_encounter_template_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    template_id: String,
+    name: String,
+    class: String,
+    race: String,
+    level: i32,
+    hit_points: i32,
+    hit_dice: Option<String>,
+    armor_class: i32,
+    count: i32,
+    notes: String,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!(
+        "Adding combatant {} x{} to encounter template {}",
+        name,
+        count,
+        template_id
+    );
+    let template_id = Uuid::parse_str(&template_id).map_err(|e| e.to_string())?;
+
+    let template = EncounterTemplate::load_by_id(&db_pool, template_id)
+        .map_err(|e| e.to_string())?
+        .ok_or(format!("Encounter template {} not found", template_id))?;
+
+    let combatant = TemplateCombatant::new(
+        template.id,
+        name,
+        class,
+        race,
+        level,
+        hit_points,
+        hit_dice,
+        armor_class,
+        count,
+        notes,
+    )?;
+    combatant
+        .save(&db_pool.get().unwrap())
+        .map_err(|e| e.to_string())?;
+
+    events::emit(
+        &app,
+        [DomainEvent::EncounterTemplateUpdated {
+            template_id: template.id,
+        }],
+    );
+
+    Ok(serde_json::to_string(&combatant).unwrap())
+}
+
+#[tauri::command]
+pub fn instantiate_encounter_template_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    configuration: State<Mutex<Configuration>>,
+    template_id: String,
+    encounter_title: Option<String>,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Instantiating encounter template {}", template_id);
+    let template_id = Uuid::parse_str(&template_id).map_err(|e| e.to_string())?;
+
+    let template = EncounterTemplate::load_by_id(&db_pool, template_id)
+        .map_err(|e| e.to_string())?
+        .ok_or(format!("Encounter template {} not found", template_id))?;
+
+    let encounter_title = encounter_title.unwrap_or(template.template_title.clone());
+    let preferences = configuration.lock().unwrap().preferences.clone();
+    let encounter = template
+        .instantiate(
+            encounter_title,
+            &db_pool,
+            &preferences,
+            &mut rand::thread_rng(),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let encounter_detail =
+        EncounterDetail::load_by_id(&db_pool, encounter.id).map_err(|e| e.to_string())?;
+
+    events::emit(
+        &app,
+        std::iter::once(DomainEvent::EncounterCreated {
+            encounter_id: encounter.id,
+        })
+        .chain(encounter_detail.characters.iter().map(|participant| {
+            DomainEvent::CharacterCreated {
+                character_id: participant.character.id,
+            }
+        })),
+    );
+
+    Ok(serde_json::to_string(&encounter_detail).unwrap())
+}
@@ -0,0 +1,156 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use tauri::{AppHandle, Emitter, Runtime};
+use uuid::Uuid;
+
+use crate::player_view;
+
+// What changed, emitted once the change is committed so every open window can reload what it
+// shows instead of polling. Each event goes out under its own name (see `name`) with its fields
+// as the payload, e.g. `character_updated` with `{"character_id": "..."}`.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum DomainEvent {
+    CharacterCreated {
+        character_id: Uuid,
+    },
+    CharacterUpdated {
+        character_id: Uuid,
+    },
+    CharacterHistoryAdded {
+        character_id: Uuid,
+        entry_id: Uuid,
+    },
+    EncounterCreated {
+        encounter_id: Uuid,
+    },
+    EncounterUpdated {
+        encounter_id: Uuid,
+    },
+    EncounterParticipantAdded {
+        encounter_id: Uuid,
+        participant_id: Uuid,
+        character_id: Uuid,
+    },
+    // Hit points or conditions of someone in the encounter changed.
+    EncounterParticipantUpdated {
+        encounter_id: Uuid,
+        participant_id: Uuid,
+    },
+    TurnAdvanced {
+        encounter_id: Uuid,
+        round: i32,
+    },
+    EncounterTemplateCreated {
+        template_id: Uuid,
+    },
+    EncounterTemplateUpdated {
+        template_id: Uuid,
+    },
+    LocationCreated {
+        location_id: Uuid,
+    },
+    LocationUpdated {
+        location_id: Uuid,
+    },
+    NoteCreated {
+        note_id: Uuid,
+    },
+    NoteUpdated {
+        note_id: Uuid,
+    },
+    NpcCreated {
+        npc_id: Uuid,
+    },
+    NpcUpdated {
+        npc_id: Uuid,
+    },
+    RelationshipCreated {
+        relationship_id: Uuid,
+    },
+    QuestCreated {
+        quest_id: Uuid,
+    },
+    QuestUpdated {
+        quest_id: Uuid,
+    },
+    CalendarCreated {
+        calendar_id: Uuid,
+    },
+    ClockChanged {
+        calendar_id: Uuid,
+        current_time: i64,
+    },
+    CalendarEventScheduled {
+        event_id: Uuid,
+    },
+    // Everything was swapped for the data of another profile.
+    ProfileSwitched {
+        profile: String,
+    },
+}
+
+impl DomainEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DomainEvent::CharacterCreated { .. } => "character_created",
+            DomainEvent::CharacterUpdated { .. } => "character_updated",
+            DomainEvent::CharacterHistoryAdded { .. } => "character_history_added",
+            DomainEvent::EncounterCreated { .. } => "encounter_created",
+            DomainEvent::EncounterUpdated { .. } => "encounter_updated",
+            DomainEvent::EncounterParticipantAdded { .. } => "encounter_participant_added",
+            DomainEvent::EncounterParticipantUpdated { .. } => "encounter_participant_updated",
+            DomainEvent::TurnAdvanced { .. } => "turn_advanced",
+            DomainEvent::EncounterTemplateCreated { .. } => "encounter_template_created",
+            DomainEvent::EncounterTemplateUpdated { .. } => "encounter_template_updated",
+            DomainEvent::LocationCreated { .. } => "location_created",
+            DomainEvent::LocationUpdated { .. } => "location_updated",
+            DomainEvent::NoteCreated { .. } => "note_created",
+            DomainEvent::NoteUpdated { .. } => "note_updated",
+            DomainEvent::NpcCreated { .. } => "npc_created",
+            DomainEvent::NpcUpdated { .. } => "npc_updated",
+            DomainEvent::RelationshipCreated { .. } => "relationship_created",
+            DomainEvent::QuestCreated { .. } => "quest_created",
+            DomainEvent::QuestUpdated { .. } => "quest_updated",
+            DomainEvent::CalendarCreated { .. } => "calendar_created",
+            DomainEvent::ClockChanged { .. } => "clock_changed",
+            DomainEvent::CalendarEventScheduled { .. } => "calendar_event_scheduled",
+            DomainEvent::ProfileSwitched { .. } => "profile_switched",
+        }
+    }
+
+    // The encounter whose participants or turn order changed, if any.
+    pub fn encounter_id(&self) -> Option<Uuid> {
+        match self {
+            DomainEvent::EncounterCreated { encounter_id }
+            | DomainEvent::EncounterUpdated { encounter_id }
+            | DomainEvent::EncounterParticipantAdded { encounter_id, .. }
+            | DomainEvent::EncounterParticipantUpdated { encounter_id, .. }
+            | DomainEvent::TurnAdvanced { encounter_id, .. } => Some(*encounter_id),
+            _ => None,
+        }
+    }
+}
+
+// Sends the events in order and then refreshes the player view when its encounter was touched.
+// Like the player view, a failure is only logged since the change itself has been committed.
+pub fn emit<R: Runtime, I: IntoIterator<Item = DomainEvent>>(app: &AppHandle<R>, events: I) {
+    let mut encounter_ids = Vec::new();
+    let mut seen = HashSet::new();
+
+    for event in events {
+        log::debug!("Emitting {} {:?}", event.name(), event);
+        if let Err(e) = app.emit(event.name(), &event) {
+            log::warn!("Could not emit {}: {}", event.name(), e);
+        }
+        if let Some(encounter_id) = event.encounter_id() {
+            if seen.insert(encounter_id) {
+                encounter_ids.push(encounter_id);
+            }
+        }
+    }
+
+    for encounter_id in encounter_ids {
+        player_view::publish(app, encounter_id);
+    }
+}
@@ -0,0 +1,705 @@
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Runtime, State};
+
+use crate::abilities::{Ability, AbilityScores};
+use crate::character::{Character, ClassLevel};
+use crate::events::{self, DomainEvent};
+use crate::storage::Database;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportFormat {
+    // Character JSON from the D&D Beyond character service.
+    DndBeyond,
+    // Actor export from the Foundry VTT dnd5e system.
+    FoundryVtt,
+    // Characters or a full export written by dm-companion-cli.
+    DmCompanion,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportedCharacter {
+    pub character: Character,
+    pub unmapped_fields: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub format: ImportFormat,
+    pub dry_run: bool,
+    pub characters: Vec<ImportedCharacter>,
+    // Parts of the export that were left out, with the reason.
+    pub skipped: Vec<String>,
+}
+
+impl ImportReport {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let format = detect_format(&value)?;
+
+        let mut skipped = Vec::new();
+        let characters = match format {
+            ImportFormat::DndBeyond => {
+                // The character service wraps the sheet in {"success": ..., "data": ...}.
+                let sheet = match value.get("data") {
+                    Some(data) if data.is_object() => data,
+                    _ => &value,
+                };
+                vec![import_dnd_beyond(sheet)?]
+            }
+            ImportFormat::FoundryVtt => vec![import_foundry_vtt(&value)?],
+            ImportFormat::DmCompanion => import_dm_companion(&value, &mut skipped),
+        };
+
+        Ok(ImportReport {
+            format,
+            dry_run: true,
+            characters,
+            skipped,
+        })
+    }
+
+    // Saves every character in one transaction so a failed import leaves nothing behind.
+    pub fn save(&mut self, connection: &mut Connection) -> Result<(), rusqlite::Error> {
+        let transaction = connection.transaction()?;
+        for imported in &self.characters {
+            imported.character.save(&transaction)?;
+        }
+        transaction.commit()?;
+        self.dry_run = false;
+
+        Ok(())
+    }
+}
+
+fn detect_format(value: &Value) -> Result<ImportFormat, String> {
+    if value.is_array() || value.get("characters").is_some_and(Value::is_array) {
+        return Ok(ImportFormat::DmCompanion);
+    }
+
+    let sheet = match value.get("data") {
+        Some(data) if data.get("classes").is_some() => data,
+        _ => value,
+    };
+    if sheet.get("classes").is_some_and(Value::is_array) && sheet.get("baseHitPoints").is_some() {
+        return Ok(ImportFormat::DndBeyond);
+    }
+
+    if value.get("type").and_then(Value::as_str) == Some("character")
+        && (value.get("system").is_some() || value.get("data").is_some())
+    {
+        return Ok(ImportFormat::FoundryVtt);
+    }
+
+    if value.get("race").is_some() && value.get("hit_points").is_some() {
+        return Ok(ImportFormat::DmCompanion);
+    }
+
+    Err(String::from("Unrecognised character export format"))
+}
+
+// Reads fields by dotted path and remembers which ones were used for the dry-run report.
+struct Fields<'a> {
+    value: &'a Value,
+    used: Vec<String>,
+}
+
+impl<'a> Fields<'a> {
+    fn new(value: &'a Value) -> Self {
+        Fields {
+            value,
+            used: Vec::new(),
+        }
+    }
+
+    fn get(&mut self, path: &str) -> Option<&'a Value> {
+        let value = path
+            .split('.')
+            .try_fold(self.value, |value, key| value.get(key))?;
+        self.used.push(String::from(path));
+
+        if value.is_null() {
+            return None;
+        }
+
+        Some(value)
+    }
+
+    fn string(&mut self, path: &str) -> Option<String> {
+        self.get(path)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(String::from)
+    }
+
+    fn integer(&mut self, path: &str) -> Option<i64> {
+        self.get(path).and_then(Value::as_i64)
+    }
+
+    fn array(&mut self, path: &str) -> Vec<&'a Value> {
+        self.get(path)
+            .and_then(Value::as_array)
+            .map(|items| items.iter().collect())
+            .unwrap_or_default()
+    }
+
+    fn unmapped(&self) -> Vec<String> {
+        let mut unmapped = Vec::new();
+        self.collect_unmapped(self.value, "", &mut unmapped);
+
+        unmapped
+    }
+
+    fn collect_unmapped(&self, value: &Value, path: &str, unmapped: &mut Vec<String>) {
+        let Value::Object(map) = value else {
+            return;
+        };
+
+        for (key, child) in map {
+            let child_path = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", path, key)
+            };
+
+            if is_empty(child) || self.used.contains(&child_path) {
+                continue;
+            }
+
+            let prefix = format!("{}.", child_path);
+            if self.used.iter().any(|used| used.starts_with(&prefix)) {
+                self.collect_unmapped(child, &child_path, unmapped);
+            } else {
+                unmapped.push(child_path);
+            }
+        }
+    }
+}
+
+// Nothing is lost by skipping fields that carry no data.
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(value) => value.is_empty(),
+        Value::Array(items) => items.is_empty(),
+        Value::Object(map) => map.is_empty(),
+        _ => false,
+    }
+}
+
+// Exports can be edited by hand, so numbers are clamped instead of wrapping around.
+fn clamp_i32(value: i64) -> i32 {
+    value.clamp(i32::MIN.into(), i32::MAX.into()) as i32
+}
+
+fn ability_modifier(score: i64) -> i32 {
+    clamp_i32(score.saturating_sub(10).div_euclid(2))
+}
+
+fn join_notes(sections: Vec<(&str, Option<String>)>) -> String {
+    sections
+        .into_iter()
+        .filter_map(|(heading, text)| text.map(|text| format!("## {}\n\n{}", heading, text)))
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+// The fields every format maps to, before they are checked against the database constraints.
+struct ImportedSheet {
+    name: Option<String>,
+    classes: Vec<ClassLevel>,
+    race: Option<String>,
+    background: Option<String>,
+    experience: i32,
+    hit_points: i32,
+    current_hit_points: Option<i32>,
+    temporary_hit_points: i32,
+    armor_class: i32,
+    notes: String,
+}
+
+fn total_level(classes: &[ClassLevel]) -> i64 {
+    classes.iter().map(|class| i64::from(class.levels)).sum()
+}
+
+// Brings classes within what the database accepts: at least one class, levels adding up to at
+// most 20 and hit dice the rules know.
+fn normalize_classes(classes: Vec<ClassLevel>, warnings: &mut Vec<String>) -> Vec<ClassLevel> {
+    let mut classes: Vec<ClassLevel> = classes
+        .into_iter()
+        .filter(|class| class.levels >= 1)
+        .collect();
+    if classes.is_empty() {
+        warnings.push(String::from("Export has no classes, imported as level 1"));
+        classes.push(ClassLevel::new(String::new(), None, 1));
+    }
+
+    // Levels above 20 are taken off the classes picked up last.
+    let level = total_level(&classes);
+    if level > 20 {
+        warnings.push(format!("Level {} was clamped to 20", level));
+        let mut excess = level - 20;
+        while excess > 0 {
+            let last = classes.last_mut().unwrap();
+            let removed = excess.min(i64::from(last.levels));
+            last.levels -= removed as i32;
+            excess -= removed;
+            if last.levels == 0 {
+                classes.pop();
+            }
+        }
+    }
+
+    for class in &mut classes {
+        // Zero means the export left it out, set_class_levels looks it up.
+        if ![0, 6, 8, 10, 12].contains(&class.hit_die) {
+            let hit_die = ClassLevel::default_hit_die(&class.class);
+            warnings.push(format!(
+                "Hit die d{} of {} is not a d6, d8, d10 or d12, imported as d{}",
+                class.hit_die, class.class, hit_die
+            ));
+            class.hit_die = hit_die;
+        }
+    }
+
+    classes
+}
+
+fn clamp_abilities(abilities: AbilityScores, warnings: &mut Vec<String>) -> AbilityScores {
+    let clamp = |ability: Ability, warnings: &mut Vec<String>| {
+        let score = abilities.score(ability);
+        let clamped = score.clamp(1, 30);
+        if clamped != score {
+            warnings.push(format!(
+                "{} {} was clamped to {}",
+                ability.as_str(),
+                score,
+                clamped
+            ));
+        }
+
+        clamped
+    };
+
+    AbilityScores {
+        strength: clamp(Ability::Strength, warnings),
+        dexterity: clamp(Ability::Dexterity, warnings),
+        constitution: clamp(Ability::Constitution, warnings),
+        intelligence: clamp(Ability::Intelligence, warnings),
+        wisdom: clamp(Ability::Wisdom, warnings),
+        charisma: clamp(Ability::Charisma, warnings),
+    }
+}
+
+fn build_character(sheet: ImportedSheet, warnings: &mut Vec<String>) -> Character {
+    let ImportedSheet {
+        name,
+        classes,
+        race,
+        background,
+        experience,
+        hit_points,
+        current_hit_points,
+        temporary_hit_points,
+        armor_class,
+        notes,
+    } = sheet;
+    let name = name.unwrap_or_else(|| {
+        warnings.push(String::from("Export has no name, imported as Unnamed"));
+        String::from("Unnamed")
+    });
+    let race = race.unwrap_or_else(|| {
+        warnings.push(String::from("Export has no race"));
+        String::new()
+    });
+
+    let classes = normalize_classes(classes, warnings);
+
+    let hit_points = hit_points.max(0);
+    let temporary_hit_points = temporary_hit_points.max(0);
+    let mut character = Character::new(
+        name,
+        classes[0].class.clone(),
+        race,
+        background,
+        classes[0].levels,
+        experience.max(0),
+        hit_points,
+        armor_class,
+        notes,
+    );
+    character.set_class_levels(classes);
+    character.current_hit_points = current_hit_points
+        .unwrap_or(hit_points)
+        .clamp(0, hit_points.saturating_add(temporary_hit_points));
+    character.temporary_hit_points = temporary_hit_points;
+    character.alive = character.current_hit_points > 0 || hit_points == 0;
+
+    character
+}
+
+fn import_dnd_beyond(sheet: &Value) -> Result<ImportedCharacter, String> {
+    let mut fields = Fields::new(sheet);
+    let mut warnings = Vec::new();
+
+    let mut classes: Vec<(bool, ClassLevel)> = fields
+        .array("classes")
+        .into_iter()
+        .filter_map(|class| {
+            let mut class_level = ClassLevel::new(
+                String::from(class.pointer("/definition/name")?.as_str()?),
+                class
+                    .pointer("/subclassDefinition/name")
+                    .and_then(Value::as_str)
+                    .map(String::from),
+                clamp_i32(class.get("level").and_then(Value::as_i64).unwrap_or(1)),
+            );
+            if let Some(hit_die) = class.pointer("/definition/hitDice").and_then(Value::as_u64) {
+                class_level.hit_die = u32::try_from(hit_die).unwrap_or(u32::MAX);
+            }
+
+            Some((
+                class.get("isStartingClass").and_then(Value::as_bool) == Some(true),
+                class_level,
+            ))
+        })
+        .collect();
+    // The starting class comes first, it decides saving throw proficiencies.
+    classes.sort_by_key(|(starting, _)| !starting);
+    let classes: Vec<ClassLevel> = classes.into_iter().map(|(_, class)| class).collect();
+    let level = total_level(&classes);
+
+    // Stats are listed by ability id, 1 is strength through 6 for charisma.
+    let stats = fields.array("stats");
+    let bonus_stats = fields.array("bonusStats");
+    let override_stats = fields.array("overrideStats");
+    let ability_score = |id: i64| -> i64 {
+        let find = |stats: &[&Value]| {
+            stats
+                .iter()
+                .find(|stat| stat.get("id").and_then(Value::as_i64) == Some(id))
+                .and_then(|stat| stat.get("value"))
+                .and_then(Value::as_i64)
+        };
+
+        find(&override_stats).unwrap_or_else(|| {
+            find(&stats)
+                .unwrap_or(10)
+                .saturating_add(find(&bonus_stats).unwrap_or(0))
+        })
+    };
+    let dexterity_modifier = ability_modifier(ability_score(2));
+    let constitution_modifier = ability_modifier(ability_score(3));
+
+    let hit_points = match fields.integer("overrideHitPoints") {
+        Some(hit_points) => hit_points,
+        None => fields
+            .integer("baseHitPoints")
+            .unwrap_or(0)
+            .saturating_add(fields.integer("bonusHitPoints").unwrap_or(0))
+            .saturating_add(i64::from(constitution_modifier).saturating_mul(level)),
+    };
+    let removed_hit_points = fields.integer("removedHitPoints").unwrap_or(0);
+
+    warnings.push(format!(
+        "Armor class is not part of the export, set to {} from dexterity",
+        10 + dexterity_modifier
+    ));
+
+    let notes = join_notes(vec![
+        ("Backstory", fields.string("notes.backstory")),
+        (
+            "Personality Traits",
+            fields.string("traits.personalityTraits"),
+        ),
+        ("Ideals", fields.string("traits.ideals")),
+        ("Bonds", fields.string("traits.bonds")),
+        ("Flaws", fields.string("traits.flaws")),
+        ("Appearance", fields.string("traits.appearance")),
+        ("Allies", fields.string("notes.allies")),
+        ("Enemies", fields.string("notes.enemies")),
+        ("Organizations", fields.string("notes.organizations")),
+        ("Other Notes", fields.string("notes.otherNotes")),
+    ]);
+
+    let base_race = fields.string("race.baseName");
+    let race = fields.string("race.fullName").or(base_race);
+    let sheet = ImportedSheet {
+        name: fields.string("name"),
+        classes,
+        race,
+        background: fields.string("background.definition.name"),
+        experience: clamp_i32(fields.integer("currentXp").unwrap_or(0)),
+        hit_points: clamp_i32(hit_points),
+        current_hit_points: Some(clamp_i32(hit_points.saturating_sub(removed_hit_points))),
+        temporary_hit_points: clamp_i32(fields.integer("temporaryHitPoints").unwrap_or(0)),
+        armor_class: 10 + dexterity_modifier,
+        notes,
+    };
+    let character = build_character(sheet, &mut warnings);
+
+    Ok(ImportedCharacter {
+        character,
+        unmapped_fields: fields.unmapped(),
+        warnings,
+    })
+}
+
+fn item_name(item: &Value) -> Option<String> {
+    item.get("name").and_then(Value::as_str).map(String::from)
+}
+
+fn item_system<'v>(item: &'v Value, field: &str) -> Option<&'v Value> {
+    item.get("system")
+        .or_else(|| item.get("data"))
+        .and_then(|system| system.get(field))
+}
+
+fn import_foundry_vtt(actor: &Value) -> Result<ImportedCharacter, String> {
+    let mut fields = Fields::new(actor);
+    let mut warnings = Vec::new();
+    fields.get("type");
+    // Exports from before Foundry v10 keep the system data under "data".
+    let system = if actor.get("system").is_some() {
+        "system"
+    } else {
+        "data"
+    };
+    let path = |field: &str| format!("{}.{}", system, field);
+
+    let items = fields.array("items");
+    let item_names = |item_type: &str| -> Vec<&Value> {
+        items
+            .iter()
+            .copied()
+            .filter(|item| item.get("type").and_then(Value::as_str) == Some(item_type))
+            .collect()
+    };
+
+    let subclasses = item_names("subclass");
+    let classes: Vec<ClassLevel> = item_names("class")
+        .into_iter()
+        .filter_map(|class| {
+            let name = item_name(class)?;
+            let identifier = item_system(class, "identifier")
+                .and_then(Value::as_str)
+                .map(String::from)
+                .unwrap_or_else(|| name.to_lowercase());
+            let subclass = subclasses
+                .iter()
+                .find(|subclass| {
+                    item_system(subclass, "classIdentifier").and_then(Value::as_str)
+                        == Some(identifier.as_str())
+                })
+                .and_then(|subclass| item_name(subclass))
+                .or_else(|| {
+                    item_system(class, "subclass")
+                        .and_then(Value::as_str)
+                        .filter(|subclass| !subclass.is_empty())
+                        .map(String::from)
+                });
+            let levels = clamp_i32(
+                item_system(class, "levels")
+                    .and_then(Value::as_i64)
+                    .unwrap_or(1),
+            );
+
+            let mut class_level = ClassLevel::new(name, subclass, levels);
+            // Hit dice are stored as "d10".
+            if let Some(hit_die) = item_system(class, "hitDice")
+                .and_then(Value::as_str)
+                .and_then(|hit_dice| hit_dice.trim_start_matches('d').parse().ok())
+            {
+                class_level.hit_die = hit_die;
+            }
+
+            Some(class_level)
+        })
+        .collect();
+
+    // Newer dnd5e versions store race and background as items and keep only their ids in details.
+    let race = item_names("race")
+        .first()
+        .and_then(|race| item_name(race))
+        .or_else(|| fields.string(&path("details.race")));
+    let background = item_names("background")
+        .first()
+        .and_then(|background| item_name(background))
+        .or_else(|| fields.string(&path("details.background")));
+
+    for item in &items {
+        let item_type = item.get("type").and_then(Value::as_str).unwrap_or_default();
+        if !["class", "subclass", "race", "background"].contains(&item_type) {
+            warnings.push(format!(
+                "Item {} ({}) was not imported",
+                item_name(item).unwrap_or_default(),
+                item_type
+            ));
+        }
+    }
+
+    let hit_points = clamp_i32(
+        fields
+            .integer(&path("attributes.hp.max"))
+            .or_else(|| fields.integer(&path("attributes.hp.value")))
+            .unwrap_or(0),
+    );
+    let armor_class = fields
+        .integer(&path("attributes.ac.flat"))
+        .or_else(|| fields.integer(&path("attributes.ac.value")))
+        .unwrap_or_else(|| {
+            warnings.push(String::from(
+                "Armor class is calculated by Foundry and not exported, set to 10",
+            ));
+            10
+        });
+
+    let notes = join_notes(vec![
+        ("Biography", fields.string(&path("details.biography.value"))),
+        ("Personality Traits", fields.string(&path("details.trait"))),
+        ("Ideals", fields.string(&path("details.ideal"))),
+        ("Bonds", fields.string(&path("details.bond"))),
+        ("Flaws", fields.string(&path("details.flaw"))),
+        ("Appearance", fields.string(&path("details.appearance"))),
+    ]);
+
+    let sheet = ImportedSheet {
+        name: fields.string("name"),
+        classes,
+        race,
+        background,
+        experience: clamp_i32(fields.integer(&path("details.xp.value")).unwrap_or(0)),
+        hit_points,
+        current_hit_points: fields.integer(&path("attributes.hp.value")).map(clamp_i32),
+        temporary_hit_points: clamp_i32(fields.integer(&path("attributes.hp.temp")).unwrap_or(0)),
+        armor_class: clamp_i32(armor_class),
+        notes,
+    };
+    let character = build_character(sheet, &mut warnings);
+
+    Ok(ImportedCharacter {
+        character,
+        unmapped_fields: fields.unmapped(),
+        warnings,
+    })
+}
+
+// Our own exports map one to one, but can be edited by hand, so they get the same checks as the
+// other formats and new ids so an import never overwrites. Records that cannot be read are
+// reported as skipped instead of failing the whole import.
+fn import_dm_companion(value: &Value, skipped: &mut Vec<String>) -> Vec<ImportedCharacter> {
+    let characters = match value {
+        Value::Array(characters) => characters.clone(),
+        _ => match value.get("characters") {
+            Some(Value::Array(characters)) => characters.clone(),
+            _ => vec![value.clone()],
+        },
+    };
+
+    // Full exports also carry encounters, which point at characters by their old ids.
+    if let Some(Value::Array(encounters)) = value.get("encounters") {
+        for encounter in encounters {
+            skipped.push(format!(
+                "Encounter {} was not imported, only characters are",
+                encounter
+                    .pointer("/encounter/encounter_title")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+            ));
+        }
+    }
+
+    characters
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, value)| {
+            let name = value
+                .get("name")
+                .and_then(Value::as_str)
+                .map(String::from)
+                .unwrap_or_else(|| format!("#{}", index + 1));
+            let character: Character = match serde_json::from_value(value) {
+                Ok(character) => character,
+                Err(e) => {
+                    skipped.push(format!("Character {} could not be read: {}", name, e));
+                    return None;
+                }
+            };
+            let mut warnings = Vec::new();
+
+            // Exports from before multiclassing only carry a class and level.
+            let classes = if character.class_levels.is_empty() {
+                vec![ClassLevel::new(
+                    character.class.clone(),
+                    None,
+                    character.level,
+                )]
+            } else {
+                character.class_levels.clone()
+            };
+            let sheet = ImportedSheet {
+                name: Some(character.name.clone()),
+                classes,
+                race: Some(character.race.clone()),
+                background: character.background.clone(),
+                experience: character.experience,
+                hit_points: character.hit_points,
+                current_hit_points: Some(character.current_hit_points),
+                temporary_hit_points: character.temporary_hit_points,
+                armor_class: character.armor_class,
+                notes: character.notes.clone(),
+            };
+            let character = Character {
+                abilities: clamp_abilities(character.abilities, &mut warnings),
+                saving_throw_proficiencies: character.saving_throw_proficiencies,
+                skill_proficiencies: character.skill_proficiencies,
+                evasion: character.evasion,
+                damage_modifiers: character.damage_modifiers,
+                initiative: character.initiative,
+                alive: character.alive,
+                campaign: character.campaign,
+                created_at_utc: character.created_at_utc,
+                ..build_character(sheet, &mut warnings)
+            };
+
+            Some(ImportedCharacter {
+                character,
+                unmapped_fields: Vec::new(),
+                warnings,
+            })
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn import_characters_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    path: String,
+    dry_run: bool,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Importing characters from {} (dry run: {})", path, dry_run);
+    let json = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+
+    let mut report = ImportReport::parse(&json)?;
+    if !dry_run {
+        let mut conn = db_pool.get().map_err(|e| e.to_string())?;
+        report.save(&mut conn).map_err(|e| e.to_string())?;
+        events::emit(
+            &app,
+            report
+                .characters
+                .iter()
+                .map(|imported| DomainEvent::CharacterCreated {
+                    character_id: imported.character.id,
+                }),
+        );
+    }
+
+    Ok(serde_json::to_string(&report).unwrap())
+}
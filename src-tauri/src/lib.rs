@@ -1,29 +1,105 @@
-mod character;
-mod configuration;
-mod encounter;
-mod storage;
+pub mod abilities;
+pub mod calendar;
+pub mod character;
+pub mod checks;
+pub mod combat;
+pub mod configuration;
+pub mod damage;
+pub mod dice;
+pub mod encounter;
+pub mod encounter_template;
+pub mod events;
+pub mod import;
+pub mod location;
+pub mod notes;
+pub mod npc;
+pub mod player_server;
+pub mod player_view;
+pub mod quest;
+pub mod repository;
+pub mod storage;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let configuration = configuration::Configuration::init().unwrap();
+    let overrides = configuration::ConfigurationOverrides::from_args(std::env::args().skip(1));
+    let configuration = configuration::Configuration::init_with(overrides).unwrap();
 
     log::info!("Starting DM Companion!");
     log::debug!("Configuration loaded {:?}", configuration);
 
     let db_pool = storage::setup_database(&configuration).expect("Could not set up database.");
 
+    // A port that is taken should not keep the app from opening, the DM can pick another one.
+    let player_server = player_server::PlayerServerState::default();
+    if let Err(e) = player_server.apply(&configuration.player_server, &None) {
+        log::error!("{}", e);
+    }
+
     tauri::Builder::default()
-        .manage(configuration)
-        .manage(db_pool)
+        .manage(std::sync::Mutex::new(configuration))
+        .manage(storage::Database::new(db_pool))
+        .manage(player_view::PlayerScreen::default())
+        .manage(player_server)
         .plugin(tauri_plugin_shell::init())
         .invoke_handler(tauri::generate_handler![
             configuration::load_configuration_command,
+            configuration::create_profile_command,
+            configuration::switch_profile_command,
+            configuration::update_preferences_command,
+            calendar::create_calendar_command,
+            calendar::load_calendars_command,
+            calendar::set_clock_command,
+            calendar::load_clock_command,
+            calendar::advance_time_command,
+            calendar::schedule_event_command,
+            calendar::load_upcoming_events_command,
             character::create_character_command,
             character::load_characters_command,
+            character::query_characters_command,
+            character::set_damage_modifiers_command,
+            character::set_abilities_command,
+            character::add_character_history_command,
+            character::load_character_history_command,
+            combat::attack_command,
+            combat::area_effect_command,
+            checks::group_roll_command,
             encounter::load_encounters_command,
             encounter::create_encounter_command,
             encounter::load_encounter_detail_command,
             encounter::add_character_to_encounter_command,
+            encounter::advance_turn_command,
+            encounter::set_encounter_location_command,
+            encounter_template::create_encounter_template_command,
+            encounter_template::load_encounter_templates_command,
+            encounter_template::add_combatant_to_encounter_template_command,
+            encounter_template::instantiate_encounter_template_command,
+            import::import_characters_command,
+            location::create_location_command,
+            location::update_location_command,
+            location::load_locations_command,
+            location::load_location_detail_command,
+            notes::create_note_command,
+            notes::update_note_command,
+            notes::load_notes_command,
+            notes::load_note_detail_command,
+            notes::search_notes_command,
+            npc::create_npc_command,
+            npc::update_npc_command,
+            npc::load_npcs_command,
+            npc::create_relationship_command,
+            npc::load_relationships_command,
+            player_server::load_player_server_status_command,
+            player_server::update_player_server_command,
+            player_view::show_encounter_to_players_command,
+            player_view::load_player_view_command,
+            player_view::open_player_window_command,
+            quest::create_quest_command,
+            quest::set_quest_status_command,
+            quest::add_objective_command,
+            quest::set_objective_completed_command,
+            quest::link_encounter_to_quest_command,
+            quest::load_quests_command,
+            storage::check_database_integrity_command,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
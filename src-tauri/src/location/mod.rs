@@ -0,0 +1,387 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime, State};
+use uuid::Uuid;
+
+use crate::encounter::Encounter;
+use crate::events::{self, DomainEvent};
+use crate::npc::Npc;
+use crate::storage::Database;
+
+// Every location below the given one, including itself.
+const SUBTREE: &str = "WITH RECURSIVE subtree (id) AS (
+        SELECT ?1
+        UNION
+        SELECT locations.id FROM locations INNER JOIN subtree ON locations.parent_id = subtree.id
+    )";
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LocationKind {
+    Plane,
+    Region,
+    City,
+    Building,
+    Room,
+}
+
+impl LocationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LocationKind::Plane => "plane",
+            LocationKind::Region => "region",
+            LocationKind::City => "city",
+            LocationKind::Building => "building",
+            LocationKind::Room => "room",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "plane" => Ok(LocationKind::Plane),
+            "region" => Ok(LocationKind::Region),
+            "city" => Ok(LocationKind::City),
+            "building" => Ok(LocationKind::Building),
+            "room" => Ok(LocationKind::Room),
+            _ => Err(format!("Unknown location kind: {}", value)),
+        }
+    }
+
+    // Children sit deeper than their parent, levels may be skipped (a tower in the wilderness
+    // is a building directly inside a region). This also keeps the tree free of cycles.
+    fn depth(&self) -> u8 {
+        match self {
+            LocationKind::Plane => 0,
+            LocationKind::Region => 1,
+            LocationKind::City => 2,
+            LocationKind::Building => 3,
+            LocationKind::Room => 4,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Location {
+    pub id: Uuid,
+    pub name: String,
+    pub kind: LocationKind,
+    pub parent_id: Option<Uuid>,
+    pub description: String,
+    pub notes: String,
+    pub created_at_utc: DateTime<Utc>,
+    pub updated_at_utc: DateTime<Utc>,
+}
+
+impl Location {
+    pub fn new(
+        name: String,
+        kind: LocationKind,
+        parent_id: Option<Uuid>,
+        description: String,
+        notes: String,
+    ) -> Self {
+        Location {
+            id: Uuid::new_v4(),
+            name,
+            kind,
+            parent_id,
+            description,
+            notes,
+            created_at_utc: Utc::now(),
+            updated_at_utc: Utc::now(),
+        }
+    }
+
+    // Checks the location fits between its parent and its children.
+    pub fn validate(&self, connection: &Connection) -> Result<(), String> {
+        if let Some(parent_id) = self.parent_id {
+            let parent = Location::load_by_id(parent_id, connection)
+                .map_err(|e| e.to_string())?
+                .ok_or(format!("Location {} not found", parent_id))?;
+            if parent.kind.depth() >= self.kind.depth() {
+                return Err(format!(
+                    "A {} cannot be inside a {}",
+                    self.kind.as_str(),
+                    parent.kind.as_str()
+                ));
+            }
+        }
+
+        let children = Location::load_children(self.id, connection).map_err(|e| e.to_string())?;
+        if let Some(child) = children
+            .iter()
+            .find(|child| child.kind.depth() <= self.kind.depth())
+        {
+            return Err(format!(
+                "{} is a {} and cannot be inside a {}",
+                child.name,
+                child.kind.as_str(),
+                self.kind.as_str()
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn save(&self, connection: &Connection) -> Result<(), rusqlite::Error> {
+        connection.execute(
+            "INSERT INTO locations (id, name, kind, parent_id, description, notes, created_at_utc, updated_at_utc) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                self.id.to_string(),
+                self.name,
+                self.kind.as_str(),
+                self.parent_id.map(|parent_id| parent_id.to_string()),
+                self.description,
+                self.notes,
+                self.created_at_utc.to_rfc3339(),
+                self.updated_at_utc.to_rfc3339()
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn update(&self, connection: &Connection) -> Result<(), rusqlite::Error> {
+        connection.execute(
+            "UPDATE locations SET name = ?2, kind = ?3, parent_id = ?4, description = ?5, notes = ?6, updated_at_utc = ?7 WHERE id = ?1",
+            params![
+                self.id.to_string(),
+                self.name,
+                self.kind.as_str(),
+                self.parent_id.map(|parent_id| parent_id.to_string()),
+                self.description,
+                self.notes,
+                self.updated_at_utc.to_rfc3339()
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        let id: String = row.get("id")?;
+        let kind: String = row.get("kind")?;
+        let parent_id: Option<String> = row.get("parent_id")?;
+        let created_at: String = row.get("created_at_utc")?;
+        let updated_at: String = row.get("updated_at_utc")?;
+
+        Ok(Location {
+            id: Uuid::parse_str(&id).unwrap(),
+            name: row.get("name")?,
+            kind: LocationKind::parse(&kind).unwrap(),
+            parent_id: parent_id.map(|parent_id| Uuid::parse_str(&parent_id).unwrap()),
+            description: row.get("description")?,
+            notes: row.get("notes")?,
+            created_at_utc: DateTime::parse_from_rfc3339(&created_at).unwrap().into(),
+            updated_at_utc: DateTime::parse_from_rfc3339(&updated_at).unwrap().into(),
+        })
+    }
+
+    pub fn load_by_id(id: Uuid, connection: &Connection) -> Result<Option<Self>> {
+        connection
+            .query_row(
+                "SELECT * FROM locations WHERE id = ?1",
+                params![id.to_string()],
+                Location::from_row,
+            )
+            .optional()
+    }
+
+    pub fn load_all(connection: &Connection) -> Result<Vec<Self>> {
+        let mut statement =
+            connection.prepare("SELECT * FROM locations ORDER BY name COLLATE NOCASE")?;
+
+        let locations = statement.query_map([], Location::from_row)?.collect();
+
+        locations
+    }
+
+    pub fn load_children(id: Uuid, connection: &Connection) -> Result<Vec<Self>> {
+        let mut statement = connection
+            .prepare("SELECT * FROM locations WHERE parent_id = ?1 ORDER BY name COLLATE NOCASE")?;
+
+        let locations = statement
+            .query_map(params![id.to_string()], Location::from_row)?
+            .collect();
+
+        locations
+    }
+
+    // Ancestors from the outermost down to the direct parent.
+    pub fn load_path(&self, connection: &Connection) -> Result<Vec<Self>> {
+        let mut path = Vec::new();
+        let mut parent_id = self.parent_id;
+        while let Some(id) = parent_id {
+            match Location::load_by_id(id, connection)? {
+                Some(parent) => {
+                    parent_id = parent.parent_id;
+                    path.insert(0, parent);
+                }
+                None => break,
+            }
+        }
+
+        Ok(path)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LocationDetail {
+    pub location: Location,
+    pub path: Vec<Location>,
+    pub children: Vec<Location>,
+    // Encounters and NPCs here or anywhere below, so a city lists what happened in its buildings.
+    pub encounters: Vec<Encounter>,
+    pub npcs: Vec<Npc>,
+}
+
+impl LocationDetail {
+    pub fn load(location: Location, connection: &Connection) -> Result<Self> {
+        let path = location.load_path(connection)?;
+        let children = Location::load_children(location.id, connection)?;
+
+        let mut statement = connection.prepare(&format!(
+            "{} SELECT encounters.* FROM encounters INNER JOIN subtree ON encounters.location_id = subtree.id ORDER BY encounters.encounter_title COLLATE NOCASE",
+            SUBTREE
+        ))?;
+        let encounters = statement
+            .query_map(params![location.id.to_string()], |row| {
+                Encounter::from_row(row)
+            })?
+            .collect::<Result<Vec<Encounter>>>()?;
+
+        let mut statement = connection.prepare(&format!(
+            "{} SELECT npcs.* FROM npcs INNER JOIN subtree ON npcs.location_id = subtree.id ORDER BY npcs.name COLLATE NOCASE",
+            SUBTREE
+        ))?;
+        let npcs = statement
+            .query_map(params![location.id.to_string()], Npc::from_row)?
+            .collect::<Result<Vec<Npc>>>()?;
+
+        Ok(LocationDetail {
+            location,
+            path,
+            children,
+            encounters,
+            npcs,
+        })
+    }
+}
+
+pub(crate) fn ensure_location_exists(
+    location_id: Option<Uuid>,
+    connection: &Connection,
+) -> Result<(), String> {
+    if let Some(location_id) = location_id {
+        Location::load_by_id(location_id, connection)
+            .map_err(|e| e.to_string())?
+            .ok_or(format!("Location {} not found", location_id))?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn parse_location_id(
+    location_id: Option<String>,
+    connection: &Connection,
+) -> Result<Option<Uuid>, String> {
+    let location_id = location_id
+        .map(|location_id| Uuid::parse_str(&location_id))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    ensure_location_exists(location_id, connection)?;
+
+    Ok(location_id)
+}
+
+#[tauri::command]
+pub fn create_location_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    name: String,
+    kind: LocationKind,
+    parent_id: Option<String>,
+    description: String,
+    notes: String,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Creating location {}", name);
+    let parent_id = parent_id
+        .map(|parent_id| Uuid::parse_str(&parent_id))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let location = Location::new(name, kind, parent_id, description, notes);
+
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+    location.validate(&conn)?;
+    location.save(&conn).map_err(|e| e.to_string())?;
+
+    events::emit(
+        &app,
+        [DomainEvent::LocationCreated {
+            location_id: location.id,
+        }],
+    );
+
+    Ok(serde_json::to_string(&location).unwrap())
+}
+
+#[tauri::command]
+pub fn update_location_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    location: Location,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Updating location {}", location.id);
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+
+    let stored = Location::load_by_id(location.id, &conn)
+        .map_err(|e| e.to_string())?
+        .ok_or(format!("Location {} not found", location.id))?;
+    let location = Location {
+        created_at_utc: stored.created_at_utc,
+        updated_at_utc: Utc::now(),
+        ..location
+    };
+    location.validate(&conn)?;
+    location.update(&conn).map_err(|e| e.to_string())?;
+
+    events::emit(
+        &app,
+        [DomainEvent::LocationUpdated {
+            location_id: location.id,
+        }],
+    );
+
+    Ok(serde_json::to_string(&location).unwrap())
+}
+
+#[tauri::command]
+pub fn load_locations_command(database: State<Database>) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Running load_locations_command");
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+    let locations = Location::load_all(&conn).map_err(|e| e.to_string())?;
+
+    Ok(serde_json::to_string(&locations).unwrap())
+}
+
+#[tauri::command]
+pub fn load_location_detail_command(
+    database: State<Database>,
+    location_id: String,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Running load_location_detail_command for {}", location_id);
+    let location_id = Uuid::parse_str(&location_id).map_err(|e| e.to_string())?;
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+
+    let location = Location::load_by_id(location_id, &conn)
+        .map_err(|e| e.to_string())?
+        .ok_or(format!("Location {} not found", location_id))?;
+    let location_detail = LocationDetail::load(location, &conn).map_err(|e| e.to_string())?;
+
+    Ok(serde_json::to_string(&location_detail).unwrap())
+}
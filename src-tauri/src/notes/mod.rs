@@ -0,0 +1,485 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime, State};
+use uuid::Uuid;
+
+use crate::events::{self, DomainEvent};
+use crate::storage::{self, match_expression, Database};
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentKind {
+    Character,
+    Encounter,
+    Npc,
+}
+
+impl AttachmentKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttachmentKind::Character => "character",
+            AttachmentKind::Encounter => "encounter",
+            AttachmentKind::Npc => "npc",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "character" => Ok(AttachmentKind::Character),
+            "encounter" => Ok(AttachmentKind::Encounter),
+            "npc" => Ok(AttachmentKind::Npc),
+            _ => Err(format!("Unknown attachment kind: {}", value)),
+        }
+    }
+
+    fn table(&self) -> &'static str {
+        match self {
+            AttachmentKind::Character => "characters",
+            AttachmentKind::Encounter => "encounters",
+            AttachmentKind::Npc => "npcs",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct Attachment {
+    pub kind: AttachmentKind,
+    pub id: Uuid,
+}
+
+impl Attachment {
+    pub fn exists(&self, connection: &Connection) -> Result<bool> {
+        connection.query_row(
+            &format!(
+                "SELECT EXISTS (SELECT 1 FROM {} WHERE id = ?1)",
+                self.kind.table()
+            ),
+            params![self.id.to_string()],
+            |row| row.get(0),
+        )
+    }
+}
+
+// Finds [[Title]] references, ignoring the display text in [[Title|shown text]] and
+// headings in [[Title#Heading]]. Titles are matched without regard to case.
+pub fn parse_wikilinks(body: &str) -> Vec<String> {
+    let mut links: Vec<String> = Vec::new();
+    let mut rest = body;
+
+    while let Some(start) = rest.find("[[") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find("]]") else {
+            break;
+        };
+
+        let target = rest[..end]
+            .split(['|', '#'])
+            .next()
+            .unwrap_or_default()
+            .trim();
+        if !target.is_empty()
+            && !target.contains('[')
+            && !links.iter().any(|link| link.eq_ignore_ascii_case(target))
+        {
+            links.push(String::from(target));
+        }
+
+        rest = &rest[end + 2..];
+    }
+
+    links
+}
+
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().trim_start_matches('#').to_lowercase();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+
+    normalized
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Note {
+    pub id: Uuid,
+    pub title: String,
+    // Markdown source.
+    pub body: String,
+    pub tags: Vec<String>,
+    pub attachment: Option<Attachment>,
+    pub created_at_utc: DateTime<Utc>,
+    pub updated_at_utc: DateTime<Utc>,
+}
+
+impl Note {
+    pub fn new(
+        title: String,
+        body: String,
+        tags: Vec<String>,
+        attachment: Option<Attachment>,
+    ) -> Result<Self, String> {
+        let title = title.trim().to_string();
+        if title.is_empty() {
+            return Err(String::from("A note needs a title"));
+        }
+
+        Ok(Note {
+            id: Uuid::new_v4(),
+            title,
+            body,
+            tags: normalize_tags(tags),
+            attachment,
+            created_at_utc: Utc::now(),
+            updated_at_utc: Utc::now(),
+        })
+    }
+
+    pub fn links(&self) -> Vec<String> {
+        parse_wikilinks(&self.body)
+    }
+
+    // Tags and links live in their own tables, callers wrap this in a transaction.
+    pub fn save(&self, connection: &Connection) -> Result<(), rusqlite::Error> {
+        connection.execute(
+            "INSERT INTO notes (id, title, body, attachment_kind, attachment_id, created_at_utc, updated_at_utc) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                self.id.to_string(),
+                self.title,
+                self.body,
+                self.attachment.map(|attachment| attachment.kind.as_str()),
+                self.attachment.map(|attachment| attachment.id.to_string()),
+                self.created_at_utc.to_rfc3339(),
+                self.updated_at_utc.to_rfc3339()
+            ],
+        )?;
+        self.save_index(connection)
+    }
+
+    pub fn update(&self, connection: &Connection) -> Result<(), rusqlite::Error> {
+        connection.execute(
+            "UPDATE notes SET title = ?2, body = ?3, attachment_kind = ?4, attachment_id = ?5, updated_at_utc = ?6 WHERE id = ?1",
+            params![
+                self.id.to_string(),
+                self.title,
+                self.body,
+                self.attachment.map(|attachment| attachment.kind.as_str()),
+                self.attachment.map(|attachment| attachment.id.to_string()),
+                self.updated_at_utc.to_rfc3339()
+            ],
+        )?;
+        self.save_index(connection)
+    }
+
+    fn save_index(&self, connection: &Connection) -> Result<(), rusqlite::Error> {
+        connection.execute(
+            "DELETE FROM note_tags WHERE note_id = ?1",
+            params![self.id.to_string()],
+        )?;
+        for tag in &self.tags {
+            connection.execute(
+                "INSERT INTO note_tags (note_id, tag) VALUES (?1, ?2)",
+                params![self.id.to_string(), tag],
+            )?;
+        }
+
+        connection.execute(
+            "DELETE FROM note_links WHERE note_id = ?1",
+            params![self.id.to_string()],
+        )?;
+        for link in self.links() {
+            connection.execute(
+                "INSERT INTO note_links (note_id, target_title) VALUES (?1, ?2)",
+                params![self.id.to_string(), link],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        let id: String = row.get("id")?;
+        let attachment_kind: Option<String> = row.get("attachment_kind")?;
+        let attachment_id: Option<String> = row.get("attachment_id")?;
+        let created_at: String = row.get("created_at_utc")?;
+        let updated_at: String = row.get("updated_at_utc")?;
+
+        Ok(Note {
+            id: Uuid::parse_str(&id).unwrap(),
+            title: row.get("title")?,
+            body: row.get("body")?,
+            tags: Vec::new(),
+            attachment: attachment_kind
+                .zip(attachment_id)
+                .map(|(kind, id)| Attachment {
+                    kind: AttachmentKind::parse(&kind).unwrap(),
+                    id: Uuid::parse_str(&id).unwrap(),
+                }),
+            created_at_utc: DateTime::parse_from_rfc3339(&created_at).unwrap().into(),
+            updated_at_utc: DateTime::parse_from_rfc3339(&updated_at).unwrap().into(),
+        })
+    }
+
+    fn load_tags(notes: &mut [Note], connection: &Connection) -> Result<(), rusqlite::Error> {
+        let note_ids: Vec<Uuid> = notes.iter().map(|note| note.id).collect();
+        let mut tags = storage::load_grouped_by_id(
+            connection,
+            "note_tags",
+            "note_id",
+            "tag",
+            &note_ids,
+            |row| row.get("tag"),
+        )?;
+
+        for note in notes.iter_mut() {
+            note.tags = tags.remove(&note.id).unwrap_or_default();
+        }
+
+        Ok(())
+    }
+
+    fn load_where(
+        connection: &Connection,
+        condition: &str,
+        params: &[&dyn rusqlite::ToSql],
+    ) -> Result<Vec<Self>> {
+        let mut statement = connection.prepare(&format!(
+            "SELECT notes.* FROM notes WHERE {} ORDER BY notes.title COLLATE NOCASE",
+            condition
+        ))?;
+        let mut notes = statement
+            .query_map(params, Note::from_row)?
+            .collect::<Result<Vec<Self>>>()?;
+        Note::load_tags(&mut notes, connection)?;
+
+        Ok(notes)
+    }
+
+    pub fn load_by_id(id: Uuid, connection: &Connection) -> Result<Option<Self>> {
+        Ok(
+            Note::load_where(connection, "notes.id = ?1", &[&id.to_string()])?
+                .into_iter()
+                .next(),
+        )
+    }
+
+    pub fn load_by_title(title: &str, connection: &Connection) -> Result<Option<Self>> {
+        Ok(
+            Note::load_where(connection, "notes.title = ?1 COLLATE NOCASE", &[&title])?
+                .into_iter()
+                .next(),
+        )
+    }
+
+    pub fn load_all(
+        connection: &Connection,
+        tag: Option<&str>,
+        attachment: Option<Attachment>,
+    ) -> Result<Vec<Self>> {
+        let tag = tag.map(|tag| tag.trim().trim_start_matches('#').to_lowercase());
+
+        Note::load_where(
+            connection,
+            "(?1 IS NULL OR EXISTS (SELECT 1 FROM note_tags WHERE note_tags.note_id = notes.id AND note_tags.tag = ?1))
+            AND (?2 IS NULL OR (notes.attachment_kind = ?2 AND notes.attachment_id = ?3))",
+            &[
+                &tag,
+                &attachment.map(|attachment| attachment.kind.as_str()),
+                &attachment.map(|attachment| attachment.id.to_string()),
+            ],
+        )
+    }
+
+    // Full-text search over titles and bodies, every word is matched as a prefix.
+    pub fn search(text: &str, connection: &Connection) -> Result<Vec<Self>> {
+        let Some(search) = match_expression(text) else {
+            return Ok(Vec::new());
+        };
+
+        Note::load_where(
+            connection,
+            "notes.id IN (SELECT id FROM notes_fts WHERE notes_fts MATCH ?1)",
+            &[&search],
+        )
+    }
+
+    pub fn backlinks(&self, connection: &Connection) -> Result<Vec<Self>> {
+        Note::load_where(
+            connection,
+            "notes.id IN (SELECT note_id FROM note_links WHERE target_title = ?1 COLLATE NOCASE) AND notes.id <> ?2",
+            &[&self.title, &self.id.to_string()],
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct NoteLink {
+    pub title: String,
+    // None while no note with that title exists yet.
+    pub note_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NoteDetail {
+    pub note: Note,
+    pub links: Vec<NoteLink>,
+    pub backlinks: Vec<Note>,
+}
+
+impl NoteDetail {
+    pub fn load(note: Note, connection: &Connection) -> Result<Self> {
+        let links = note
+            .links()
+            .into_iter()
+            .map(|title| {
+                let note_id = connection
+                    .query_row(
+                        "SELECT id FROM notes WHERE title = ?1 COLLATE NOCASE",
+                        params![title],
+                        |row| row.get::<_, String>(0),
+                    )
+                    .optional()?
+                    .map(|id| Uuid::parse_str(&id).unwrap());
+
+                Ok(NoteLink { title, note_id })
+            })
+            .collect::<Result<Vec<NoteLink>>>()?;
+        let backlinks = note.backlinks(connection)?;
+
+        Ok(NoteDetail {
+            note,
+            links,
+            backlinks,
+        })
+    }
+}
+
+fn parse_attachment(
+    attachment_kind: Option<AttachmentKind>,
+    attachment_id: Option<String>,
+) -> Result<Option<Attachment>, String> {
+    match (attachment_kind, attachment_id) {
+        (Some(kind), Some(id)) => Ok(Some(Attachment {
+            kind,
+            id: Uuid::parse_str(&id).map_err(|e| e.to_string())?,
+        })),
+        (None, None) => Ok(None),
+        _ => Err(String::from("An attachment needs both a kind and an id")),
+    }
+}
+
+#[tauri::command]
+pub fn create_note_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    title: String,
+    body: String,
+    tags: Vec<String>,
+    attachment_kind: Option<AttachmentKind>,
+    attachment_id: Option<String>,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Creating note {}", title);
+    let attachment = parse_attachment(attachment_kind, attachment_id)?;
+    let note = Note::new(title, body, tags, attachment)?;
+
+    let mut conn = db_pool.get().map_err(|e| e.to_string())?;
+    let transaction = conn.transaction().map_err(|e| e.to_string())?;
+    if let Some(attachment) = note.attachment {
+        if !attachment.exists(&transaction).map_err(|e| e.to_string())? {
+            return Err(format!(
+                "{} {} not found",
+                attachment.kind.as_str(),
+                attachment.id
+            ));
+        }
+    }
+    note.save(&transaction).map_err(|e| e.to_string())?;
+    transaction.commit().map_err(|e| e.to_string())?;
+
+    events::emit(&app, [DomainEvent::NoteCreated { note_id: note.id }]);
+
+    Ok(serde_json::to_string(&note).unwrap())
+}
+
+#[tauri::command]
+pub fn update_note_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    note_id: String,
+    title: String,
+    body: String,
+    tags: Vec<String>,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Updating note {}", note_id);
+    let note_id = Uuid::parse_str(&note_id).map_err(|e| e.to_string())?;
+
+    let mut conn = db_pool.get().map_err(|e| e.to_string())?;
+    let transaction = conn.transaction().map_err(|e| e.to_string())?;
+    let stored = Note::load_by_id(note_id, &transaction)
+        .map_err(|e| e.to_string())?
+        .ok_or(format!("Note {} not found", note_id))?;
+
+    let note = Note {
+        id: stored.id,
+        created_at_utc: stored.created_at_utc,
+        updated_at_utc: Utc::now(),
+        ..Note::new(title, body, tags, stored.attachment)?
+    };
+    note.update(&transaction).map_err(|e| e.to_string())?;
+    transaction.commit().map_err(|e| e.to_string())?;
+
+    events::emit(&app, [DomainEvent::NoteUpdated { note_id: note.id }]);
+
+    Ok(serde_json::to_string(&note).unwrap())
+}
+
+#[tauri::command]
+pub fn load_notes_command(
+    database: State<Database>,
+    tag: Option<String>,
+    attachment_kind: Option<AttachmentKind>,
+    attachment_id: Option<String>,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Running load_notes_command");
+    let attachment = parse_attachment(attachment_kind, attachment_id)?;
+
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+    let notes = Note::load_all(&conn, tag.as_deref(), attachment).map_err(|e| e.to_string())?;
+
+    Ok(serde_json::to_string(&notes).unwrap())
+}
+
+#[tauri::command]
+pub fn load_note_detail_command(
+    database: State<Database>,
+    note_id: String,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Running load_note_detail_command for {}", note_id);
+    let note_id = Uuid::parse_str(&note_id).map_err(|e| e.to_string())?;
+
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+    let note = Note::load_by_id(note_id, &conn)
+        .map_err(|e| e.to_string())?
+        .ok_or(format!("Note {} not found", note_id))?;
+    let note_detail = NoteDetail::load(note, &conn).map_err(|e| e.to_string())?;
+
+    Ok(serde_json::to_string(&note_detail).unwrap())
+}
+
+#[tauri::command]
+pub fn search_notes_command(database: State<Database>, text: String) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Searching notes for {}", text);
+
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+    let notes = Note::search(&text, &conn).map_err(|e| e.to_string())?;
+
+    Ok(serde_json::to_string(&notes).unwrap())
+}
@@ -0,0 +1,497 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime, State};
+use uuid::Uuid;
+
+use crate::events::{self, DomainEvent};
+use crate::location::ensure_location_exists;
+use crate::storage::Database;
+
+// Attitudes towards the party, as used for social interaction in the Dungeon Master's Guide.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Attitude {
+    Hostile,
+    Unfriendly,
+    Indifferent,
+    Friendly,
+    Helpful,
+}
+
+impl Attitude {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Attitude::Hostile => "hostile",
+            Attitude::Unfriendly => "unfriendly",
+            Attitude::Indifferent => "indifferent",
+            Attitude::Friendly => "friendly",
+            Attitude::Helpful => "helpful",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "hostile" => Ok(Attitude::Hostile),
+            "unfriendly" => Ok(Attitude::Unfriendly),
+            "indifferent" => Ok(Attitude::Indifferent),
+            "friendly" => Ok(Attitude::Friendly),
+            "helpful" => Ok(Attitude::Helpful),
+            _ => Err(format!("Unknown attitude: {}", value)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Npc {
+    pub id: Uuid,
+    pub name: String,
+    pub role: String,
+    pub location: Option<String>,
+    #[serde(default)]
+    pub location_id: Option<Uuid>,
+    pub faction: Option<String>,
+    pub attitude: Attitude,
+    // Kept from the players, e.g. in the player-facing views.
+    pub secrets: String,
+    pub notes: String,
+    pub created_at_utc: DateTime<Utc>,
+    pub updated_at_utc: DateTime<Utc>,
+}
+
+impl Npc {
+    pub fn new(
+        name: String,
+        role: String,
+        location: Option<String>,
+        faction: Option<String>,
+        attitude: Attitude,
+        secrets: String,
+        notes: String,
+    ) -> Self {
+        Npc {
+            id: Uuid::new_v4(),
+            name,
+            role,
+            location,
+            location_id: None,
+            faction,
+            attitude,
+            secrets,
+            notes,
+            created_at_utc: Utc::now(),
+            updated_at_utc: Utc::now(),
+        }
+    }
+
+    pub fn save(&self, connection: &Connection) -> Result<(), rusqlite::Error> {
+        connection.execute(
+            "INSERT INTO npcs (id, name, role, location, faction, attitude, secrets, notes, created_at_utc, updated_at_utc, location_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                self.id.to_string(),
+                self.name,
+                self.role,
+                self.location,
+                self.faction,
+                self.attitude.as_str(),
+                self.secrets,
+                self.notes,
+                self.created_at_utc.to_rfc3339(),
+                self.updated_at_utc.to_rfc3339(),
+                self.location_id.map(|location_id| location_id.to_string())
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn update(&self, connection: &Connection) -> Result<(), rusqlite::Error> {
+        connection.execute(
+            "UPDATE npcs SET name = ?2, role = ?3, location = ?4, faction = ?5, attitude = ?6, secrets = ?7, notes = ?8, updated_at_utc = ?9, location_id = ?10 WHERE id = ?1",
+            params![
+                self.id.to_string(),
+                self.name,
+                self.role,
+                self.location,
+                self.faction,
+                self.attitude.as_str(),
+                self.secrets,
+                self.notes,
+                self.updated_at_utc.to_rfc3339(),
+                self.location_id.map(|location_id| location_id.to_string())
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub(crate) fn from_row(row: &Row) -> Result<Self> {
+        let id: String = row.get("id")?;
+        let attitude: String = row.get("attitude")?;
+        let location_id: Option<String> = row.get("location_id")?;
+        let created_at: String = row.get("created_at_utc")?;
+        let updated_at: String = row.get("updated_at_utc")?;
+
+        Ok(Npc {
+            id: Uuid::parse_str(&id).unwrap(),
+            name: row.get("name")?,
+            role: row.get("role")?,
+            location: row.get("location")?,
+            location_id: location_id.map(|location_id| Uuid::parse_str(&location_id).unwrap()),
+            faction: row.get("faction")?,
+            attitude: Attitude::parse(&attitude).unwrap_or(Attitude::Indifferent),
+            secrets: row.get("secrets")?,
+            notes: row.get("notes")?,
+            created_at_utc: DateTime::parse_from_rfc3339(&created_at).unwrap().into(),
+            updated_at_utc: DateTime::parse_from_rfc3339(&updated_at).unwrap().into(),
+        })
+    }
+
+    pub fn load_by_id(id: Uuid, connection: &Connection) -> Result<Self> {
+        connection.query_row(
+            "SELECT * FROM npcs WHERE id = ?1",
+            params![id.to_string()],
+            Npc::from_row,
+        )
+    }
+
+    pub fn load_all(connection: &Connection, faction: Option<&str>) -> Result<Vec<Self>> {
+        let mut statement = connection.prepare(
+            "SELECT * FROM npcs WHERE ?1 IS NULL OR faction = ?1 ORDER BY name COLLATE NOCASE",
+        )?;
+
+        let npcs = statement
+            .query_map(params![faction], Npc::from_row)?
+            .collect();
+
+        npcs
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EntityKind {
+    Character,
+    Npc,
+}
+
+impl EntityKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntityKind::Character => "character",
+            EntityKind::Npc => "npc",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "character" => Ok(EntityKind::Character),
+            "npc" => Ok(EntityKind::Npc),
+            _ => Err(format!("Unknown entity kind: {}", value)),
+        }
+    }
+
+    fn table(&self) -> &'static str {
+        match self {
+            EntityKind::Character => "characters",
+            EntityKind::Npc => "npcs",
+        }
+    }
+}
+
+// Either side of a relationship, with the name resolved for display.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EntityRef {
+    pub kind: EntityKind,
+    pub id: Uuid,
+    pub name: Option<String>,
+}
+
+impl EntityRef {
+    pub fn new(kind: EntityKind, id: Uuid) -> Self {
+        EntityRef {
+            kind,
+            id,
+            name: None,
+        }
+    }
+
+    pub fn load(kind: EntityKind, id: Uuid, connection: &Connection) -> Result<Option<Self>> {
+        let name: Option<String> = connection
+            .query_row(
+                &format!("SELECT name FROM {} WHERE id = ?1", kind.table()),
+                params![id.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(name.map(|name| EntityRef {
+            kind,
+            id,
+            name: Some(name),
+        }))
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RelationshipKind {
+    Ally,
+    Rival,
+    Family,
+    // The source employs the target.
+    Employer,
+}
+
+impl RelationshipKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RelationshipKind::Ally => "ally",
+            RelationshipKind::Rival => "rival",
+            RelationshipKind::Family => "family",
+            RelationshipKind::Employer => "employer",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "ally" => Ok(RelationshipKind::Ally),
+            "rival" => Ok(RelationshipKind::Rival),
+            "family" => Ok(RelationshipKind::Family),
+            "employer" => Ok(RelationshipKind::Employer),
+            _ => Err(format!("Unknown relationship kind: {}", value)),
+        }
+    }
+}
+
+// Links any two characters or NPCs. Rows are removed with either side by database triggers.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Relationship {
+    pub id: Uuid,
+    pub source: EntityRef,
+    pub target: EntityRef,
+    pub kind: RelationshipKind,
+    pub description: String,
+}
+
+impl Relationship {
+    pub fn new(
+        source: EntityRef,
+        target: EntityRef,
+        kind: RelationshipKind,
+        description: String,
+    ) -> Result<Self, String> {
+        if source.kind == target.kind && source.id == target.id {
+            return Err(String::from("A relationship needs two different entities"));
+        }
+
+        Ok(Relationship {
+            id: Uuid::new_v4(),
+            source,
+            target,
+            kind,
+            description,
+        })
+    }
+
+    pub fn save(&self, connection: &Connection) -> Result<(), rusqlite::Error> {
+        connection.execute(
+            "INSERT INTO relationships (id, source_kind, source_id, target_kind, target_id, kind, description) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                self.id.to_string(),
+                self.source.kind.as_str(),
+                self.source.id.to_string(),
+                self.target.kind.as_str(),
+                self.target.id.to_string(),
+                self.kind.as_str(),
+                self.description
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        let entity = |side: &str| -> Result<EntityRef> {
+            let kind: String = row.get(format!("{}_kind", side).as_str())?;
+            let id: String = row.get(format!("{}_id", side).as_str())?;
+
+            Ok(EntityRef {
+                kind: EntityKind::parse(&kind).unwrap(),
+                id: Uuid::parse_str(&id).unwrap(),
+                name: row.get(format!("{}_name", side).as_str())?,
+            })
+        };
+        let id: String = row.get("id")?;
+        let kind: String = row.get("kind")?;
+
+        Ok(Relationship {
+            id: Uuid::parse_str(&id).unwrap(),
+            source: entity("source")?,
+            target: entity("target")?,
+            kind: RelationshipKind::parse(&kind).unwrap(),
+            description: row.get("description")?,
+        })
+    }
+
+    // Every relationship the entity takes part in, on either side.
+    pub fn load_for(
+        entity_kind: EntityKind,
+        entity_id: Uuid,
+        connection: &Connection,
+    ) -> Result<Vec<Self>> {
+        let mut statement = connection.prepare(
+            "SELECT relationships.*,
+                CASE source_kind
+                    WHEN 'character' THEN (SELECT name FROM characters WHERE id = source_id)
+                    ELSE (SELECT name FROM npcs WHERE id = source_id)
+                END AS source_name,
+                CASE target_kind
+                    WHEN 'character' THEN (SELECT name FROM characters WHERE id = target_id)
+                    ELSE (SELECT name FROM npcs WHERE id = target_id)
+                END AS target_name
+            FROM relationships
+            WHERE (source_kind = ?1 AND source_id = ?2) OR (target_kind = ?1 AND target_id = ?2)",
+        )?;
+
+        let relationships = statement
+            .query_map(
+                params![entity_kind.as_str(), entity_id.to_string()],
+                Relationship::from_row,
+            )?
+            .collect();
+
+        relationships
+    }
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn create_npc_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    name: String,
+    role: String,
+    location: Option<String>,
+    faction: Option<String>,
+    attitude: Attitude,
+    secrets: String,
+    notes: String,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Creating NPC {}", name);
+    let npc = Npc::new(name, role, location, faction, attitude, secrets, notes);
+
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+    npc.save(&conn).map_err(|e| e.to_string())?;
+
+    events::emit(&app, [DomainEvent::NpcCreated { npc_id: npc.id }]);
+
+    Ok(serde_json::to_string(&npc).unwrap())
+}
+
+#[tauri::command]
+pub fn update_npc_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    npc: Npc,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Updating NPC {}", npc.id);
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+
+    let stored = Npc::load_by_id(npc.id, &conn)
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or(format!("NPC {} not found", npc.id))?;
+    ensure_location_exists(npc.location_id, &conn)?;
+    let npc = Npc {
+        created_at_utc: stored.created_at_utc,
+        updated_at_utc: Utc::now(),
+        ..npc
+    };
+    npc.update(&conn).map_err(|e| e.to_string())?;
+
+    events::emit(&app, [DomainEvent::NpcUpdated { npc_id: npc.id }]);
+
+    Ok(serde_json::to_string(&npc).unwrap())
+}
+
+#[tauri::command]
+pub fn load_npcs_command(
+    database: State<Database>,
+    faction: Option<String>,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Running load_npcs_command");
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+    let npcs = Npc::load_all(&conn, faction.as_deref()).map_err(|e| e.to_string())?;
+
+    Ok(serde_json::to_string(&npcs).unwrap())
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn create_relationship_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    source_kind: EntityKind,
+    source_id: String,
+    target_kind: EntityKind,
+    target_id: String,
+    kind: RelationshipKind,
+    description: String,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!(
+        "Creating {} relationship from {} {} to {} {}",
+        kind.as_str(),
+        source_kind.as_str(),
+        source_id,
+        target_kind.as_str(),
+        target_id
+    );
+    let source_id = Uuid::parse_str(&source_id).map_err(|e| e.to_string())?;
+    let target_id = Uuid::parse_str(&target_id).map_err(|e| e.to_string())?;
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+
+    let source = EntityRef::load(source_kind, source_id, &conn)
+        .map_err(|e| e.to_string())?
+        .ok_or(format!("{} {} not found", source_kind.as_str(), source_id))?;
+    let target = EntityRef::load(target_kind, target_id, &conn)
+        .map_err(|e| e.to_string())?
+        .ok_or(format!("{} {} not found", target_kind.as_str(), target_id))?;
+
+    let relationship = Relationship::new(source, target, kind, description)?;
+    relationship.save(&conn).map_err(|e| e.to_string())?;
+
+    events::emit(
+        &app,
+        [DomainEvent::RelationshipCreated {
+            relationship_id: relationship.id,
+        }],
+    );
+
+    Ok(serde_json::to_string(&relationship).unwrap())
+}
+
+#[tauri::command]
+pub fn load_relationships_command(
+    database: State<Database>,
+    entity_kind: EntityKind,
+    entity_id: String,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!(
+        "Loading relationships for {} {}",
+        entity_kind.as_str(),
+        entity_id
+    );
+    let entity_id = Uuid::parse_str(&entity_id).map_err(|e| e.to_string())?;
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+
+    let relationships =
+        Relationship::load_for(entity_kind, entity_id, &conn).map_err(|e| e.to_string())?;
+
+    Ok(serde_json::to_string(&relationships).unwrap())
+}
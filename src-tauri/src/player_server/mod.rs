@@ -0,0 +1,407 @@
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, SyncSender, TryRecvError, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tauri::State;
+use tungstenite::{Message, WebSocket};
+
+use crate::configuration::{Configuration, PlayerServerSettings};
+use crate::player_view::{PlayerScreen, PlayerView};
+use crate::repository::SqliteRepository;
+use crate::storage::Database;
+
+const PAGE: &str = include_str!("player.html");
+
+// A phone that stopped listening should not hold up everyone else.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+// How long a client waits on its socket before looking for a newer view.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// Every connection has its own thread, a table full of phones stays well below this.
+const MAX_CONNECTIONS: usize = 32;
+
+struct Shared {
+    // The last player view sent out as JSON, so new clients start from the current state.
+    latest: Mutex<String>,
+    // Wakes the thread of each WebSocket client, which sends the latest view itself.
+    clients: Mutex<Vec<SyncSender<()>>>,
+    connections: AtomicUsize,
+    stopped: AtomicBool,
+}
+
+// Holds one of the MAX_CONNECTIONS places until the connection's thread is done.
+struct ConnectionSlot(Arc<Shared>);
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.connections.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Serves the player page on / and streams the player view over a WebSocket on /ws. The current
+// view is also available as JSON on /state. What players send is only read to answer pings and
+// closes.
+pub struct PlayerServer {
+    address: SocketAddr,
+    shared: Arc<Shared>,
+    accepting: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl PlayerServer {
+    pub fn start(
+        settings: &PlayerServerSettings,
+        player_view: &Option<PlayerView>,
+    ) -> Result<Self, String> {
+        settings.validate()?;
+        let listener = TcpListener::bind((settings.bind_address.as_str(), settings.port))
+            .map_err(|e| format!("Could not start the player server: {}", e))?;
+        let address = listener.local_addr().map_err(|e| e.to_string())?;
+        let shared = Arc::new(Shared {
+            latest: Mutex::new(serde_json::to_string(player_view).unwrap()),
+            clients: Mutex::new(Vec::new()),
+            connections: AtomicUsize::new(0),
+            stopped: AtomicBool::new(false),
+        });
+
+        let accepting = shared.clone();
+        let accept_thread = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if accepting.stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                match stream {
+                    Ok(stream) => {
+                        if accepting.connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                            accepting.connections.fetch_sub(1, Ordering::SeqCst);
+                            log::warn!(
+                                "Player server has {} connections, refusing another",
+                                MAX_CONNECTIONS
+                            );
+                            continue;
+                        }
+                        let slot = ConnectionSlot(accepting.clone());
+                        thread::spawn(move || {
+                            if let Err(e) = handle_connection(stream, &slot.0) {
+                                log::debug!("Player server connection failed: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => log::warn!("Player server could not accept a connection: {}", e),
+                }
+            }
+        });
+        log::info!("Player server listening on {}", address);
+
+        Ok(PlayerServer {
+            address,
+            shared,
+            accepting: Mutex::new(Some(accept_thread)),
+        })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    // Only hands the view to the client threads, a slow phone never holds up the caller.
+    pub fn broadcast(&self, player_view: &Option<PlayerView>) {
+        *self.shared.latest.lock().unwrap() = serde_json::to_string(player_view).unwrap();
+
+        // A full channel already has a wake-up waiting, which will pick up this view.
+        self.shared
+            .clients
+            .lock()
+            .unwrap()
+            .retain(|client| !matches!(client.try_send(()), Err(TrySendError::Disconnected(_))));
+    }
+
+    pub fn stop(&self) {
+        if self.shared.stopped.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        // The accept loop only notices the flag once the next connection comes in.
+        let wake_address = match self.address.ip() {
+            IpAddr::V4(ip) if ip.is_unspecified() => {
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), self.address.port())
+            }
+            IpAddr::V6(ip) if ip.is_unspecified() => {
+                SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), self.address.port())
+            }
+            _ => self.address,
+        };
+        let _ = TcpStream::connect_timeout(&wake_address, READ_TIMEOUT);
+        // Once the loop is done the listener is closed and the port can be taken again.
+        if let Some(accept_thread) = self.accepting.lock().unwrap().take() {
+            let _ = accept_thread.join();
+        }
+
+        // Client threads close their sockets once their wake-up channel is gone.
+        self.shared.clients.lock().unwrap().clear();
+        log::info!("Player server on {} stopped", self.address);
+    }
+}
+
+impl Drop for PlayerServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn respond(
+    mut stream: TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn handle_connection(stream: TcpStream, shared: &Shared) -> Result<(), String> {
+    stream
+        .set_read_timeout(Some(READ_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    stream
+        .set_write_timeout(Some(WRITE_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+
+    // Only the request line is peeked at, the WebSocket handshake needs the request untouched.
+    // It can arrive in pieces, so wait until all of it is there.
+    let mut buffer = [0; 512];
+    let started = Instant::now();
+    let mut peeked = stream.peek(&mut buffer).map_err(|e| e.to_string())?;
+    while !buffer[..peeked].contains(&b'\n') && peeked > 0 && peeked < buffer.len() {
+        if started.elapsed() > READ_TIMEOUT {
+            return Err(String::from("Timed out waiting for the request line"));
+        }
+        thread::sleep(Duration::from_millis(10));
+        peeked = stream.peek(&mut buffer).map_err(|e| e.to_string())?;
+    }
+    let request = String::from_utf8_lossy(&buffer[..peeked]);
+    let mut request_line = request
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace();
+    let (method, path) = (
+        request_line.next().unwrap_or_default(),
+        request_line.next().unwrap_or_default(),
+    );
+
+    if method == "GET" && path == "/ws" {
+        let websocket = tungstenite::accept(stream).map_err(|e| e.to_string())?;
+        return serve_websocket(websocket, shared);
+    }
+
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    let mut line = String::new();
+    while reader.read_line(&mut line).map_err(|e| e.to_string())? > 0 {
+        if line.trim().is_empty() {
+            break;
+        }
+        line.clear();
+    }
+
+    match (method, path) {
+        ("GET", "/") => respond(stream, "200 OK", "text/html; charset=utf-8", PAGE),
+        ("GET", "/state") => respond(
+            stream,
+            "200 OK",
+            "application/json",
+            &shared.latest.lock().unwrap().clone(),
+        ),
+        _ => respond(stream, "404 Not Found", "text/plain", "Not found"),
+    }
+    .map_err(|e| e.to_string())
+}
+
+fn is_timeout(error: &tungstenite::Error) -> bool {
+    matches!(
+        error,
+        tungstenite::Error::Io(e)
+            if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut)
+    )
+}
+
+// Sends every new view to one client and reads from it in between, so pings are answered and a
+// close from the player ends the connection.
+fn serve_websocket(mut websocket: WebSocket<TcpStream>, shared: &Shared) -> Result<(), String> {
+    websocket
+        .get_ref()
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .map_err(|e| e.to_string())?;
+    let (notify, updates) = mpsc::sync_channel(1);
+    // Registered before the first send, a view broadcast in between only causes a wake-up.
+    shared.clients.lock().unwrap().push(notify);
+    let mut sent = String::new();
+    let mut pending = true;
+
+    loop {
+        if shared.stopped.load(Ordering::SeqCst) {
+            let _ = websocket.close(None);
+            let _ = websocket.flush();
+            return Ok(());
+        }
+
+        if pending {
+            let latest = shared.latest.lock().unwrap().clone();
+            if latest != sent {
+                websocket
+                    .send(Message::text(latest.clone()))
+                    .map_err(|e| e.to_string())?;
+                sent = latest;
+            }
+        }
+
+        match websocket.read() {
+            // Pongs and close replies are queued by tungstenite and go out with the flush below.
+            Ok(_) => {}
+            Err(e) if is_timeout(&e) => {}
+            Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                return Ok(());
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+        match websocket.flush() {
+            Ok(()) => {}
+            Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Err(e) => return Err(e.to_string()),
+        }
+
+        pending = match updates.try_recv() {
+            Ok(()) => true,
+            Err(TryRecvError::Empty) => false,
+            Err(TryRecvError::Disconnected) => {
+                let _ = websocket.close(None);
+                let _ = websocket.flush();
+                return Ok(());
+            }
+        };
+    }
+}
+
+// The running server, if the settings asked for one.
+#[derive(Default)]
+pub struct PlayerServerState {
+    server: Mutex<Option<PlayerServer>>,
+}
+
+impl PlayerServerState {
+    // Stops the running server and starts a new one when enabled, so port changes apply at once.
+    pub fn apply(
+        &self,
+        settings: &PlayerServerSettings,
+        player_view: &Option<PlayerView>,
+    ) -> Result<(), String> {
+        let mut server = self.server.lock().unwrap();
+        if let Some(running) = server.take() {
+            running.stop();
+        }
+        if settings.enabled {
+            *server = Some(PlayerServer::start(settings, player_view)?);
+        }
+
+        Ok(())
+    }
+
+    // Like apply, but when the new settings cannot be served the previous ones are served again.
+    pub fn switch(
+        &self,
+        settings: &PlayerServerSettings,
+        previous: &PlayerServerSettings,
+        player_view: &Option<PlayerView>,
+    ) -> Result<(), String> {
+        if let Err(e) = self.apply(settings, player_view) {
+            self.restore(previous, player_view);
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    // Goes back to settings that were working, there is nothing left to fall back on if it fails.
+    pub fn restore(&self, previous: &PlayerServerSettings, player_view: &Option<PlayerView>) {
+        if let Err(e) = self.apply(previous, player_view) {
+            log::error!("Could not restart the previous player server: {}", e);
+        }
+    }
+
+    pub fn broadcast(&self, player_view: &Option<PlayerView>) {
+        if let Some(server) = self.server.lock().unwrap().as_ref() {
+            server.broadcast(player_view);
+        }
+    }
+
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.server
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|server| server.address())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlayerServerStatus {
+    pub settings: PlayerServerSettings,
+    pub address: Option<String>,
+}
+
+#[tauri::command]
+pub fn load_player_server_status_command(
+    configuration: State<Mutex<Configuration>>,
+    player_server: State<PlayerServerState>,
+) -> Result<String, String> {
+    log::debug!("Running load_player_server_status_command");
+    let status = PlayerServerStatus {
+        settings: configuration.lock().unwrap().player_server.clone(),
+        address: player_server.address().map(|address| address.to_string()),
+    };
+
+    Ok(serde_json::to_string(&status).unwrap())
+}
+
+#[tauri::command]
+pub fn update_player_server_command(
+    configuration: State<Mutex<Configuration>>,
+    database: State<Database>,
+    player_screen: State<PlayerScreen>,
+    player_server: State<PlayerServerState>,
+    settings: PlayerServerSettings,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Running update_player_server_command with {:?}", settings);
+    settings.validate()?;
+
+    let player_view = player_screen.view(&SqliteRepository::new(&db_pool))?;
+    let mut configuration = configuration.lock().unwrap();
+    let previous = configuration.player_server.clone();
+    player_server.switch(&settings, &previous, &player_view)?;
+
+    // Settings that could not be saved would be lost on restart, so the old server comes back.
+    configuration.player_server = settings;
+    if let Err(e) = configuration.save() {
+        player_server.restore(&previous, &player_view);
+        configuration.player_server = previous;
+        return Err(e);
+    }
+
+    let status = PlayerServerStatus {
+        settings: configuration.player_server.clone(),
+        address: player_server.address().map(|address| address.to_string()),
+    };
+
+    Ok(serde_json::to_string(&status).unwrap())
+}
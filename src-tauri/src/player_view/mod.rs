@@ -0,0 +1,201 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State, WebviewUrl, WebviewWindowBuilder};
+use uuid::Uuid;
+
+use crate::character::Character;
+use crate::encounter::EncounterDetail;
+use crate::player_server::PlayerServerState;
+use crate::repository::{EncounterRepository, SqliteRepository};
+use crate::storage::Database;
+
+pub const PLAYER_VIEW_UPDATED: &str = "player_view_updated";
+pub const PLAYER_WINDOW: &str = "players";
+
+// What the table gets to know about someone's health instead of their hit points.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthDescriptor {
+    Healthy,
+    Wounded,
+    Bloodied,
+    Down,
+    Dead,
+}
+
+impl HealthDescriptor {
+    // Bloodied at half hit points or below.
+    pub fn of(character: &Character) -> Self {
+        if !character.alive {
+            HealthDescriptor::Dead
+        } else if character.current_hit_points <= 0 {
+            HealthDescriptor::Down
+        } else if character.current_hit_points * 2 <= character.hit_points {
+            HealthDescriptor::Bloodied
+        } else if character.current_hit_points < character.hit_points {
+            HealthDescriptor::Wounded
+        } else {
+            HealthDescriptor::Healthy
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PlayerParticipant {
+    pub id: Uuid,
+    pub name: String,
+    pub initiative: Option<i32>,
+    pub conditions: Vec<String>,
+    pub health: HealthDescriptor,
+    pub active: bool,
+}
+
+// The player-safe projection of an encounter: no hit points, armor class, abilities or notes.
+#[derive(Debug, Serialize, Clone)]
+pub struct PlayerView {
+    pub encounter_id: Uuid,
+    pub encounter_title: String,
+    pub round: i32,
+    // In turn order.
+    pub participants: Vec<PlayerParticipant>,
+}
+
+impl PlayerView {
+    pub fn from_detail(encounter_detail: &EncounterDetail) -> Self {
+        let active_id = encounter_detail
+            .active_participant()
+            .map(|participant| participant.id);
+
+        PlayerView {
+            encounter_id: encounter_detail.encounter.id,
+            encounter_title: encounter_detail.encounter.encounter_title.clone(),
+            round: encounter_detail.encounter.round,
+            participants: encounter_detail
+                .turn_order()
+                .into_iter()
+                .map(|participant| PlayerParticipant {
+                    id: participant.id,
+                    name: participant.character.name.clone(),
+                    initiative: participant.initiative,
+                    conditions: participant.status_effects.clone(),
+                    health: HealthDescriptor::of(&participant.character),
+                    active: Some(participant.id) == active_id,
+                })
+                .collect(),
+        }
+    }
+}
+
+// The encounter currently shown to the players, if any.
+#[derive(Debug, Default)]
+pub struct PlayerScreen {
+    encounter_id: Mutex<Option<Uuid>>,
+}
+
+impl PlayerScreen {
+    pub fn encounter_id(&self) -> Option<Uuid> {
+        *self.encounter_id.lock().unwrap()
+    }
+
+    pub fn show(&self, encounter_id: Option<Uuid>) {
+        *self.encounter_id.lock().unwrap() = encounter_id;
+    }
+
+    pub fn view<R: EncounterRepository>(
+        &self,
+        repository: &R,
+    ) -> Result<Option<PlayerView>, String> {
+        let Some(encounter_id) = self.encounter_id() else {
+            return Ok(None);
+        };
+
+        Ok(repository
+            .load_encounter_detail(encounter_id)?
+            .map(|encounter_detail| PlayerView::from_detail(&encounter_detail)))
+    }
+}
+
+// Sends the view to the player window and, when it runs, the player server.
+fn send<R: Runtime>(app: &AppHandle<R>, player_view: Option<PlayerView>) -> Result<(), String> {
+    if let Some(player_server) = app.try_state::<PlayerServerState>() {
+        player_server.broadcast(&player_view);
+    }
+
+    app.emit(PLAYER_VIEW_UPDATED, player_view)
+        .map_err(|e| e.to_string())
+}
+
+// Called after an encounter changed. Only the encounter on the player screen is sent out, and a
+// failure is logged rather than returned since the change itself has already been committed.
+pub fn publish<R: Runtime>(app: &AppHandle<R>, encounter_id: Uuid) {
+    let Some(player_screen) = app.try_state::<PlayerScreen>() else {
+        return;
+    };
+    if player_screen.encounter_id() != Some(encounter_id) {
+        return;
+    }
+
+    let db_pool = app.state::<Database>().pool();
+    let published = player_screen
+        .view(&SqliteRepository::new(&db_pool))
+        .and_then(|player_view| send(app, player_view));
+    if let Err(error) = published {
+        log::warn!("Could not update the player view: {}", error);
+    }
+}
+
+#[tauri::command]
+pub fn show_encounter_to_players_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    player_screen: State<PlayerScreen>,
+    encounter_id: Option<String>,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Showing encounter {:?} to the players", encounter_id);
+    let encounter_id = encounter_id
+        .map(|encounter_id| Uuid::parse_str(&encounter_id))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let repository = SqliteRepository::new(&db_pool);
+    if let Some(encounter_id) = encounter_id {
+        repository
+            .load_encounter(encounter_id)?
+            .ok_or(format!("Encounter {} not found", encounter_id))?;
+    }
+
+    player_screen.show(encounter_id);
+    let player_view = player_screen.view(&repository)?;
+    send(&app, player_view.clone())?;
+
+    Ok(serde_json::to_string(&player_view).unwrap())
+}
+
+#[tauri::command]
+pub fn load_player_view_command(
+    database: State<Database>,
+    player_screen: State<PlayerScreen>,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Running load_player_view_command");
+    let player_view = player_screen.view(&SqliteRepository::new(&db_pool))?;
+
+    Ok(serde_json::to_string(&player_view).unwrap())
+}
+
+// Async so the window is not created on the main thread, which deadlocks on Windows.
+#[tauri::command]
+pub async fn open_player_window_command<R: Runtime>(app: AppHandle<R>) -> Result<(), String> {
+    log::debug!("Opening the player window");
+    if let Some(window) = app.get_webview_window(PLAYER_WINDOW) {
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+
+    WebviewWindowBuilder::new(&app, PLAYER_WINDOW, WebviewUrl::App("player".into()))
+        .title("dm-companion - Players")
+        .inner_size(800.0, 600.0)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
@@ -0,0 +1,428 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime, State};
+use uuid::Uuid;
+
+use crate::character::Character;
+use crate::encounter::Encounter;
+use crate::events::{self, DomainEvent};
+use crate::storage::Database;
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum QuestStatus {
+    Rumored,
+    Active,
+    Completed,
+    Failed,
+}
+
+impl QuestStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuestStatus::Rumored => "rumored",
+            QuestStatus::Active => "active",
+            QuestStatus::Completed => "completed",
+            QuestStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "rumored" => Ok(QuestStatus::Rumored),
+            "active" => Ok(QuestStatus::Active),
+            "completed" => Ok(QuestStatus::Completed),
+            "failed" => Ok(QuestStatus::Failed),
+            _ => Err(format!("Unknown quest status: {}", value)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Objective {
+    pub id: Uuid,
+    pub description: String,
+    pub completed: bool,
+}
+
+impl Objective {
+    pub fn new(description: String) -> Self {
+        Objective {
+            id: Uuid::new_v4(),
+            description,
+            completed: false,
+        }
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        let id: String = row.get("id")?;
+
+        Ok(Objective {
+            id: Uuid::parse_str(&id).unwrap(),
+            description: row.get("description")?,
+            completed: row.get("completed")?,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Quest {
+    pub id: Uuid,
+    pub title: String,
+    pub description: String,
+    pub status: QuestStatus,
+    pub reward: String,
+    // The character who handed out the quest.
+    pub giver_id: Option<Uuid>,
+    pub giver_name: Option<String>,
+    pub objectives: Vec<Objective>,
+    pub encounter_ids: Vec<Uuid>,
+    pub created_at_utc: DateTime<Utc>,
+    pub updated_at_utc: DateTime<Utc>,
+}
+
+impl Quest {
+    pub fn new(
+        title: String,
+        description: String,
+        reward: String,
+        giver: Option<&Character>,
+        objectives: Vec<String>,
+    ) -> Self {
+        Quest {
+            id: Uuid::new_v4(),
+            title,
+            description,
+            status: QuestStatus::Rumored,
+            reward,
+            giver_id: giver.map(|giver| giver.id),
+            giver_name: giver.map(|giver| giver.name.clone()),
+            objectives: objectives.into_iter().map(Objective::new).collect(),
+            encounter_ids: Vec::new(),
+            created_at_utc: Utc::now(),
+            updated_at_utc: Utc::now(),
+        }
+    }
+
+    pub fn save(&self, connection: &Connection) -> Result<(), rusqlite::Error> {
+        connection.execute(
+            "INSERT INTO quests (id, title, description, status, reward, giver_id, created_at_utc, updated_at_utc) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                self.id.to_string(),
+                self.title,
+                self.description,
+                self.status.as_str(),
+                self.reward,
+                self.giver_id.map(|giver_id| giver_id.to_string()),
+                self.created_at_utc.to_rfc3339(),
+                self.updated_at_utc.to_rfc3339()
+            ],
+        )?;
+
+        for objective in &self.objectives {
+            self.save_objective(objective, connection)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn save_objective(
+        &self,
+        objective: &Objective,
+        connection: &Connection,
+    ) -> Result<(), rusqlite::Error> {
+        connection.execute(
+            "INSERT INTO quest_objectives (id, quest_id, position, description, completed)
+            VALUES (?1, ?2, (SELECT COUNT(*) FROM quest_objectives WHERE quest_id = ?2), ?3, ?4)",
+            params![
+                objective.id.to_string(),
+                self.id.to_string(),
+                objective.description,
+                objective.completed
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn update_status(
+        &mut self,
+        status: QuestStatus,
+        connection: &Connection,
+    ) -> Result<(), rusqlite::Error> {
+        self.status = status;
+        self.updated_at_utc = Utc::now();
+        connection.execute(
+            "UPDATE quests SET status = ?2, updated_at_utc = ?3 WHERE id = ?1",
+            params![
+                self.id.to_string(),
+                self.status.as_str(),
+                self.updated_at_utc.to_rfc3339()
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn link_encounter(
+        &mut self,
+        encounter_id: Uuid,
+        connection: &Connection,
+    ) -> Result<(), rusqlite::Error> {
+        connection.execute(
+            "INSERT OR IGNORE INTO quest_encounters (quest_id, encounter_id) VALUES (?1, ?2)",
+            params![self.id.to_string(), encounter_id.to_string()],
+        )?;
+        if !self.encounter_ids.contains(&encounter_id) {
+            self.encounter_ids.push(encounter_id);
+        }
+
+        Ok(())
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        let id: String = row.get("id")?;
+        let status: String = row.get("status")?;
+        let giver_id: Option<String> = row.get("giver_id")?;
+        let created_at: String = row.get("created_at_utc")?;
+        let updated_at: String = row.get("updated_at_utc")?;
+
+        Ok(Quest {
+            id: Uuid::parse_str(&id).unwrap(),
+            title: row.get("title")?,
+            description: row.get("description")?,
+            status: QuestStatus::parse(&status).unwrap(),
+            reward: row.get("reward")?,
+            giver_id: giver_id.map(|giver_id| Uuid::parse_str(&giver_id).unwrap()),
+            giver_name: row.get("giver_name")?,
+            objectives: Vec::new(),
+            encounter_ids: Vec::new(),
+            created_at_utc: DateTime::parse_from_rfc3339(&created_at).unwrap().into(),
+            updated_at_utc: DateTime::parse_from_rfc3339(&updated_at).unwrap().into(),
+        })
+    }
+
+    fn load_details(&mut self, connection: &Connection) -> Result<(), rusqlite::Error> {
+        let mut statement = connection
+            .prepare("SELECT * FROM quest_objectives WHERE quest_id = ?1 ORDER BY position")?;
+        self.objectives = statement
+            .query_map(params![self.id.to_string()], Objective::from_row)?
+            .collect::<Result<Vec<Objective>>>()?;
+
+        let mut statement = connection.prepare(
+            "SELECT encounter_id FROM quest_encounters WHERE quest_id = ?1 ORDER BY rowid",
+        )?;
+        self.encounter_ids = statement
+            .query_map(params![self.id.to_string()], |row| {
+                let encounter_id: String = row.get(0)?;
+                Ok(Uuid::parse_str(&encounter_id).unwrap())
+            })?
+            .collect::<Result<Vec<Uuid>>>()?;
+
+        Ok(())
+    }
+
+    fn load_where(
+        connection: &Connection,
+        condition: &str,
+        params: &[&dyn rusqlite::ToSql],
+    ) -> Result<Vec<Self>> {
+        let mut statement = connection.prepare(&format!(
+            "SELECT quests.*, characters.name AS giver_name
+            FROM quests
+            LEFT JOIN characters ON characters.id = quests.giver_id
+            WHERE {}
+            ORDER BY quests.created_at_utc",
+            condition
+        ))?;
+        let mut quests = statement
+            .query_map(params, Quest::from_row)?
+            .collect::<Result<Vec<Self>>>()?;
+        for quest in quests.iter_mut() {
+            quest.load_details(connection)?;
+        }
+
+        Ok(quests)
+    }
+
+    pub fn load_by_id(id: Uuid, connection: &Connection) -> Result<Option<Self>> {
+        Ok(
+            Quest::load_where(connection, "quests.id = ?1", &[&id.to_string()])?
+                .into_iter()
+                .next(),
+        )
+    }
+
+    pub fn load_all(connection: &Connection, status: Option<QuestStatus>) -> Result<Vec<Self>> {
+        Quest::load_where(
+            connection,
+            "?1 IS NULL OR quests.status = ?1",
+            &[&status.map(|status| status.as_str())],
+        )
+    }
+}
+
+fn load_quest(quest_id: &str, connection: &Connection) -> Result<Quest, String> {
+    let quest_id = Uuid::parse_str(quest_id).map_err(|e| e.to_string())?;
+
+    Quest::load_by_id(quest_id, connection)
+        .map_err(|e| e.to_string())?
+        .ok_or(format!("Quest {} not found", quest_id))
+}
+
+#[tauri::command]
+pub fn create_quest_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    title: String,
+    description: String,
+    reward: String,
+    giver_id: Option<String>,
+    objectives: Vec<String>,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Creating quest {}", title);
+    let mut conn = db_pool.get().map_err(|e| e.to_string())?;
+
+    let giver = match giver_id {
+        Some(giver_id) => {
+            let giver_id = Uuid::parse_str(&giver_id).map_err(|e| e.to_string())?;
+            Some(
+                Character::load_by_id(giver_id, &conn)
+                    .optional()
+                    .map_err(|e| e.to_string())?
+                    .ok_or(format!("Character {} not found", giver_id))?,
+            )
+        }
+        None => None,
+    };
+    let quest = Quest::new(title, description, reward, giver.as_ref(), objectives);
+
+    let transaction = conn.transaction().map_err(|e| e.to_string())?;
+    quest.save(&transaction).map_err(|e| e.to_string())?;
+    transaction.commit().map_err(|e| e.to_string())?;
+
+    events::emit(&app, [DomainEvent::QuestCreated { quest_id: quest.id }]);
+
+    Ok(serde_json::to_string(&quest).unwrap())
+}
+
+#[tauri::command]
+pub fn set_quest_status_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    quest_id: String,
+    status: QuestStatus,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Marking quest {} as {}", quest_id, status.as_str());
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+
+    let mut quest = load_quest(&quest_id, &conn)?;
+    quest
+        .update_status(status, &conn)
+        .map_err(|e| e.to_string())?;
+
+    events::emit(&app, [DomainEvent::QuestUpdated { quest_id: quest.id }]);
+
+    Ok(serde_json::to_string(&quest).unwrap())
+}
+
+#[tauri::command]
+pub fn add_objective_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    quest_id: String,
+    description: String,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Adding objective to quest {}", quest_id);
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+
+    let mut quest = load_quest(&quest_id, &conn)?;
+    let objective = Objective::new(description);
+    quest
+        .save_objective(&objective, &conn)
+        .map_err(|e| e.to_string())?;
+    quest.objectives.push(objective);
+
+    events::emit(&app, [DomainEvent::QuestUpdated { quest_id: quest.id }]);
+
+    Ok(serde_json::to_string(&quest).unwrap())
+}
+
+#[tauri::command]
+pub fn set_objective_completed_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    quest_id: String,
+    objective_id: String,
+    completed: bool,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!(
+        "Setting objective {} of quest {} to completed: {}",
+        objective_id,
+        quest_id,
+        completed
+    );
+    let objective_id = Uuid::parse_str(&objective_id).map_err(|e| e.to_string())?;
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+
+    let mut quest = load_quest(&quest_id, &conn)?;
+    let objective = quest
+        .objectives
+        .iter_mut()
+        .find(|objective| objective.id == objective_id)
+        .ok_or(format!("Objective {} not found", objective_id))?;
+    objective.completed = completed;
+    conn.execute(
+        "UPDATE quest_objectives SET completed = ?2 WHERE id = ?1",
+        params![objective_id.to_string(), completed],
+    )
+    .map_err(|e| e.to_string())?;
+
+    events::emit(&app, [DomainEvent::QuestUpdated { quest_id: quest.id }]);
+
+    Ok(serde_json::to_string(&quest).unwrap())
+}
+
+#[tauri::command]
+pub fn link_encounter_to_quest_command<R: Runtime>(
+    app: AppHandle<R>,
+    database: State<Database>,
+    quest_id: String,
+    encounter_id: String,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Linking encounter {} to quest {}", encounter_id, quest_id);
+    let encounter_id = Uuid::parse_str(&encounter_id).map_err(|e| e.to_string())?;
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+
+    let mut quest = load_quest(&quest_id, &conn)?;
+    Encounter::load_by_id_with_connection(&conn, encounter_id)
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or(format!("Encounter {} not found", encounter_id))?;
+    quest
+        .link_encounter(encounter_id, &conn)
+        .map_err(|e| e.to_string())?;
+
+    events::emit(&app, [DomainEvent::QuestUpdated { quest_id: quest.id }]);
+
+    Ok(serde_json::to_string(&quest).unwrap())
+}
+
+#[tauri::command]
+pub fn load_quests_command(
+    database: State<Database>,
+    status: Option<QuestStatus>,
+) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Running load_quests_command");
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+    let quests = Quest::load_all(&conn, status).map_err(|e| e.to_string())?;
+
+    Ok(serde_json::to_string(&quests).unwrap())
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use uuid::Uuid;
+
+use super::{CharacterRepository, EncounterRepository, UnitOfWork};
+use crate::character::{Character, CharacterPage, CharacterQuery};
+use crate::encounter::{Encounter, EncounterCharacter, EncounterDetail};
+
+#[derive(Clone)]
+struct StoredParticipant {
+    id: Uuid,
+    encounter_id: Uuid,
+    character_id: Uuid,
+    initiative: Option<i32>,
+}
+
+// Keeps everything in process memory. Participants must reference stored encounters and
+// characters, like the foreign keys in the SQLite schema.
+#[derive(Default)]
+pub struct InMemoryRepository {
+    characters: Mutex<Vec<Character>>,
+    encounters: Mutex<HashMap<Uuid, Encounter>>,
+    participants: Mutex<Vec<StoredParticipant>>,
+    transaction: Mutex<()>,
+}
+
+impl InMemoryRepository {
+    pub fn new() -> Self {
+        InMemoryRepository::default()
+    }
+}
+
+// Transactions run one at a time and put the snapshot back when the work fails.
+impl UnitOfWork for InMemoryRepository {
+    type Transaction<'a> = InMemoryRepository;
+
+    fn in_transaction<T>(
+        &self,
+        work: impl FnOnce(&InMemoryRepository) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let _transaction = self.transaction.lock().unwrap();
+        let characters = self.characters.lock().unwrap().clone();
+        let encounters = self.encounters.lock().unwrap().clone();
+        let participants = self.participants.lock().unwrap().clone();
+
+        let result = work(self);
+        if result.is_err() {
+            *self.characters.lock().unwrap() = characters;
+            *self.encounters.lock().unwrap() = encounters;
+            *self.participants.lock().unwrap() = participants;
+        }
+
+        result
+    }
+}
+
+impl CharacterRepository for InMemoryRepository {
+    fn save_character(&self, character: &Character) -> Result<(), String> {
+        let mut characters = self.characters.lock().unwrap();
+        if characters.iter().any(|stored| stored.id == character.id) {
+            return Err(format!("Character {} already exists", character.id));
+        }
+
+        characters.push(character.clone());
+
+        Ok(())
+    }
+
+    fn update_character(&self, character: &Character) -> Result<(), String> {
+        let mut characters = self.characters.lock().unwrap();
+
+        match characters
+            .iter_mut()
+            .find(|stored| stored.id == character.id)
+        {
+            Some(stored) => {
+                *stored = character.clone();
+                Ok(())
+            }
+            None => Err(format!("Character {} not found", character.id)),
+        }
+    }
+
+    fn load_character(&self, character_id: Uuid) -> Result<Option<Character>, String> {
+        let characters = self.characters.lock().unwrap();
+
+        Ok(characters
+            .iter()
+            .find(|character| character.id == character_id)
+            .cloned())
+    }
+
+    fn load_characters(&self) -> Result<Vec<Character>, String> {
+        Ok(self.characters.lock().unwrap().clone())
+    }
+
+    fn query_characters(&self, query: &CharacterQuery) -> Result<CharacterPage, String> {
+        Ok(query.apply(&self.characters.lock().unwrap()))
+    }
+}
+
+impl EncounterRepository for InMemoryRepository {
+    fn save_encounter(&self, encounter: &Encounter) -> Result<(), String> {
+        let mut encounters = self.encounters.lock().unwrap();
+        if encounters.contains_key(&encounter.id) {
+            return Err(format!("Encounter {} already exists", encounter.id));
+        }
+
+        encounters.insert(encounter.id, encounter.clone());
+
+        Ok(())
+    }
+
+    fn update_encounter(&self, encounter: &Encounter) -> Result<(), String> {
+        let mut encounters = self.encounters.lock().unwrap();
+
+        match encounters.get_mut(&encounter.id) {
+            Some(stored) => {
+                *stored = encounter.clone();
+                Ok(())
+            }
+            None => Err(format!("Encounter {} not found", encounter.id)),
+        }
+    }
+
+    fn load_encounter(&self, encounter_id: Uuid) -> Result<Option<Encounter>, String> {
+        Ok(self.encounters.lock().unwrap().get(&encounter_id).cloned())
+    }
+
+    fn load_encounters(&self) -> Result<Vec<Encounter>, String> {
+        Ok(self.encounters.lock().unwrap().values().cloned().collect())
+    }
+
+    fn save_participant(&self, participant: &EncounterCharacter) -> Result<(), String> {
+        if self.load_encounter(participant.encounter.id)?.is_none() {
+            return Err(format!("Encounter {} not found", participant.encounter.id));
+        }
+
+        if self.load_character(participant.character.id)?.is_none() {
+            return Err(format!("Character {} not found", participant.character.id));
+        }
+
+        self.participants.lock().unwrap().push(StoredParticipant {
+            id: participant.id,
+            encounter_id: participant.encounter.id,
+            character_id: participant.character.id,
+            initiative: participant.initiative,
+        });
+
+        Ok(())
+    }
+
+    fn load_encounter_detail(&self, encounter_id: Uuid) -> Result<Option<EncounterDetail>, String> {
+        let Some(encounter) = self.load_encounter(encounter_id)? else {
+            return Ok(None);
+        };
+
+        let participants = self.participants.lock().unwrap();
+        let mut characters = Vec::new();
+        for participant in participants
+            .iter()
+            .filter(|participant| participant.encounter_id == encounter_id)
+        {
+            let character = self
+                .load_character(participant.character_id)?
+                .ok_or(format!("Character {} not found", participant.character_id))?;
+
+            characters.push(EncounterCharacter {
+                id: participant.id,
+                character,
+                encounter: encounter.clone(),
+                initiative: participant.initiative,
+                status_effects: Vec::new(),
+            });
+        }
+
+        Ok(Some(EncounterDetail {
+            encounter,
+            characters,
+        }))
+    }
+}
@@ -0,0 +1,46 @@
+use uuid::Uuid;
+
+use crate::character::{Character, CharacterPage, CharacterQuery};
+use crate::encounter::{Encounter, EncounterCharacter, EncounterDetail};
+
+mod memory;
+mod sqlite;
+
+pub use memory::InMemoryRepository;
+pub use sqlite::{SqliteRepository, SqliteTransaction};
+
+pub trait CharacterRepository {
+    fn save_character(&self, character: &Character) -> Result<(), String>;
+
+    fn update_character(&self, character: &Character) -> Result<(), String>;
+
+    fn load_character(&self, character_id: Uuid) -> Result<Option<Character>, String>;
+
+    fn load_characters(&self) -> Result<Vec<Character>, String>;
+
+    fn query_characters(&self, query: &CharacterQuery) -> Result<CharacterPage, String>;
+}
+
+pub trait EncounterRepository {
+    fn save_encounter(&self, encounter: &Encounter) -> Result<(), String>;
+
+    fn update_encounter(&self, encounter: &Encounter) -> Result<(), String>;
+
+    fn load_encounter(&self, encounter_id: Uuid) -> Result<Option<Encounter>, String>;
+
+    fn load_encounters(&self) -> Result<Vec<Encounter>, String>;
+
+    fn save_participant(&self, participant: &EncounterCharacter) -> Result<(), String>;
+
+    fn load_encounter_detail(&self, encounter_id: Uuid) -> Result<Option<EncounterDetail>, String>;
+}
+
+// Runs several reads and writes as one unit, either all of the changes are kept or none are.
+pub trait UnitOfWork: CharacterRepository + EncounterRepository {
+    type Transaction<'a>: CharacterRepository + EncounterRepository;
+
+    fn in_transaction<T>(
+        &self,
+        work: impl FnOnce(&Self::Transaction<'_>) -> Result<T, String>,
+    ) -> Result<T, String>;
+}
@@ -0,0 +1,161 @@
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{OptionalExtension, Transaction};
+use uuid::Uuid;
+
+use super::{CharacterRepository, EncounterRepository, UnitOfWork};
+use crate::character::{Character, CharacterPage, CharacterQuery};
+use crate::encounter::{Encounter, EncounterCharacter, EncounterDetail};
+
+#[derive(Clone)]
+pub struct SqliteRepository {
+    db_pool: Pool<SqliteConnectionManager>,
+}
+
+impl SqliteRepository {
+    pub fn new(db_pool: &Pool<SqliteConnectionManager>) -> Self {
+        SqliteRepository {
+            db_pool: db_pool.clone(),
+        }
+    }
+
+    pub fn connection(&self) -> Result<PooledConnection<SqliteConnectionManager>, String> {
+        self.db_pool.get().map_err(|e| e.to_string())
+    }
+}
+
+impl UnitOfWork for SqliteRepository {
+    type Transaction<'a> = SqliteTransaction<'a>;
+
+    fn in_transaction<T>(
+        &self,
+        work: impl FnOnce(&SqliteTransaction<'_>) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut conn = self.connection()?;
+        let transaction = conn.transaction().map_err(|e| e.to_string())?;
+        let result = work(&SqliteTransaction {
+            transaction: &transaction,
+        })?;
+        transaction.commit().map_err(|e| e.to_string())?;
+
+        Ok(result)
+    }
+}
+
+// Every call on the pooled repository is its own transaction.
+impl CharacterRepository for SqliteRepository {
+    fn save_character(&self, character: &Character) -> Result<(), String> {
+        self.in_transaction(|repository| repository.save_character(character))
+    }
+
+    fn update_character(&self, character: &Character) -> Result<(), String> {
+        self.in_transaction(|repository| repository.update_character(character))
+    }
+
+    fn load_character(&self, character_id: Uuid) -> Result<Option<Character>, String> {
+        self.in_transaction(|repository| repository.load_character(character_id))
+    }
+
+    fn load_characters(&self) -> Result<Vec<Character>, String> {
+        self.in_transaction(|repository| repository.load_characters())
+    }
+
+    fn query_characters(&self, query: &CharacterQuery) -> Result<CharacterPage, String> {
+        self.in_transaction(|repository| repository.query_characters(query))
+    }
+}
+
+impl EncounterRepository for SqliteRepository {
+    fn save_encounter(&self, encounter: &Encounter) -> Result<(), String> {
+        self.in_transaction(|repository| repository.save_encounter(encounter))
+    }
+
+    fn update_encounter(&self, encounter: &Encounter) -> Result<(), String> {
+        self.in_transaction(|repository| repository.update_encounter(encounter))
+    }
+
+    fn load_encounter(&self, encounter_id: Uuid) -> Result<Option<Encounter>, String> {
+        self.in_transaction(|repository| repository.load_encounter(encounter_id))
+    }
+
+    fn load_encounters(&self) -> Result<Vec<Encounter>, String> {
+        self.in_transaction(|repository| repository.load_encounters())
+    }
+
+    fn save_participant(&self, participant: &EncounterCharacter) -> Result<(), String> {
+        self.in_transaction(|repository| repository.save_participant(participant))
+    }
+
+    fn load_encounter_detail(&self, encounter_id: Uuid) -> Result<Option<EncounterDetail>, String> {
+        self.in_transaction(|repository| repository.load_encounter_detail(encounter_id))
+    }
+}
+
+// The repository inside SqliteRepository::in_transaction, it commits when the work succeeds.
+pub struct SqliteTransaction<'a> {
+    transaction: &'a Transaction<'a>,
+}
+
+impl CharacterRepository for SqliteTransaction<'_> {
+    fn save_character(&self, character: &Character) -> Result<(), String> {
+        character
+            .save(self.transaction)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    fn update_character(&self, character: &Character) -> Result<(), String> {
+        character
+            .update(self.transaction)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    fn load_character(&self, character_id: Uuid) -> Result<Option<Character>, String> {
+        Character::load_by_id(character_id, self.transaction)
+            .optional()
+            .map_err(|e| e.to_string())
+    }
+
+    fn load_characters(&self) -> Result<Vec<Character>, String> {
+        Character::load_all(self.transaction).map_err(|e| e.to_string())
+    }
+
+    fn query_characters(&self, query: &CharacterQuery) -> Result<CharacterPage, String> {
+        query.run(self.transaction).map_err(|e| e.to_string())
+    }
+}
+
+impl EncounterRepository for SqliteTransaction<'_> {
+    fn save_encounter(&self, encounter: &Encounter) -> Result<(), String> {
+        encounter.save(self.transaction).map_err(|e| e.to_string())
+    }
+
+    fn update_encounter(&self, encounter: &Encounter) -> Result<(), String> {
+        encounter
+            .update(self.transaction)
+            .map_err(|e| e.to_string())
+    }
+
+    fn load_encounter(&self, encounter_id: Uuid) -> Result<Option<Encounter>, String> {
+        Encounter::load_by_id_with_connection(self.transaction, encounter_id)
+            .optional()
+            .map_err(|e| e.to_string())
+    }
+
+    fn load_encounters(&self) -> Result<Vec<Encounter>, String> {
+        Encounter::load_all_with_connection(self.transaction).map_err(|e| e.to_string())
+    }
+
+    fn save_participant(&self, participant: &EncounterCharacter) -> Result<(), String> {
+        participant
+            .save(self.transaction)
+            .map_err(|e| e.to_string())
+    }
+
+    fn load_encounter_detail(&self, encounter_id: Uuid) -> Result<Option<EncounterDetail>, String> {
+        EncounterDetail::load_by_id_with_connection(self.transaction, encounter_id)
+            .optional()
+            .map_err(|e| e.to_string())
+    }
+}
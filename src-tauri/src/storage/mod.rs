@@ -1,17 +1,58 @@
 use log;
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::types::Type;
+use rusqlite::{params_from_iter, Connection, Row};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use tauri::State;
+use uuid::Uuid;
+
+use crate::configuration::{DatabaseSettings, JournalMode, Synchronous};
+
+// Managed as Tauri state so the pool can be swapped when the active profile changes.
+pub struct Database {
+    pool: RwLock<Pool<SqliteConnectionManager>>,
+}
+
+impl Database {
+    pub fn new(pool: Pool<SqliteConnectionManager>) -> Self {
+        Database {
+            pool: RwLock::new(pool),
+        }
+    }
+
+    pub fn pool(&self) -> Pool<SqliteConnectionManager> {
+        self.pool.read().unwrap().clone()
+    }
+
+    pub fn replace(&self, pool: Pool<SqliteConnectionManager>) {
+        *self.pool.write().unwrap() = pool;
+    }
+}
 
 pub fn setup_database(
     configuration: &super::configuration::Configuration,
 ) -> Result<Pool<SqliteConnectionManager>, String> {
     log::debug!("Initializing db {:?}", &configuration.db_path);
-    let manager = SqliteConnectionManager::file(std::path::PathBuf::from(&configuration.db_path));
+    let pragmas = connection_pragmas(&configuration.database);
+    if let Some(parent) = configuration.db_path.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+    }
+
+    let manager = SqliteConnectionManager::file(std::path::PathBuf::from(&configuration.db_path))
+        .with_init(move |conn| conn.execute_batch(&pragmas));
     log::debug!("DB Was initialized");
 
     match r2d2::Pool::new(manager) {
         Ok(pool) => {
-            setup_structure(&pool, configuration).unwrap();
+            setup_structure(&pool, configuration).map_err(|e| {
+                log::error!("Could not migrate db: {:?}", e);
+                format!("Could not migrate database: {}", e)
+            })?;
             log::debug!("Pool Was initialized");
             Ok(pool)
         }
@@ -22,11 +63,560 @@ pub fn setup_database(
     }
 }
 
-// TODO: Manage db versions
+fn connection_pragmas(settings: &DatabaseSettings) -> String {
+    let journal_mode = match settings.journal_mode {
+        JournalMode::Delete => "DELETE",
+        JournalMode::Truncate => "TRUNCATE",
+        JournalMode::Persist => "PERSIST",
+        JournalMode::Memory => "MEMORY",
+        JournalMode::Wal => "WAL",
+        JournalMode::Off => "OFF",
+    };
+    let synchronous = match settings.synchronous {
+        Synchronous::Off => "OFF",
+        Synchronous::Normal => "NORMAL",
+        Synchronous::Full => "FULL",
+        Synchronous::Extra => "EXTRA",
+    };
+
+    format!(
+        "PRAGMA journal_mode = {};
+        PRAGMA busy_timeout = {};
+        PRAGMA foreign_keys = {};
+        PRAGMA synchronous = {};",
+        journal_mode,
+        settings.busy_timeout_ms,
+        if settings.foreign_keys { "ON" } else { "OFF" },
+        synchronous
+    )
+}
+
+// Each migration brings the schema up by one version, tracked in PRAGMA user_version.
+// Migrations run with foreign keys disabled so tables can be rebuilt in place.
+const MIGRATIONS: [&str; 15] = [
+    "
+    CREATE TABLE IF NOT EXISTS characters (
+        id UUID PRIMARY KEY,
+        name TEXT NOT NULL,
+        class TEXT NOT NULL,
+        race TEXT NOT NULL,
+        background TEXT,
+        level INTEGER NOT NULL,
+        experience INTEGER NOT NULL,
+        hit_points INTEGER NOT NULL,
+        current_hit_points INTEGER NOT NULL,
+        armor_class INTEGER NOT NULL,
+        initiative INTEGER,
+        alive BOOLEAN NOT NULL,
+        notes TEXT NOT NULL,
+        created_at_utc TIMESTAMP WITH TIME ZONE NOT NULL,
+        updated_at_utc TIMESTAMP WITH TIME ZONE NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS encounters (
+        id TEXT PRIMARY KEY,
+        encounter_title TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS encounter_characters (
+        id TEXT PRIMARY KEY,
+        encounter_id TEXT NOT NULL,
+        character_id TEXT NOT NULL,
+        initiative INTEGER
+    );
+
+    CREATE TABLE IF NOT EXISTS encounter_templates (
+        id TEXT PRIMARY KEY,
+        template_title TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS encounter_template_combatants (
+        id TEXT PRIMARY KEY,
+        template_id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        class TEXT NOT NULL,
+        race TEXT NOT NULL,
+        level INTEGER NOT NULL,
+        hit_points INTEGER NOT NULL,
+        hit_dice TEXT,
+        armor_class INTEGER NOT NULL,
+        count INTEGER NOT NULL,
+        notes TEXT NOT NULL
+    );
+    ",
+    "
+    CREATE TABLE characters_migrated (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        class TEXT NOT NULL,
+        race TEXT NOT NULL,
+        background TEXT,
+        level INTEGER NOT NULL CHECK (level BETWEEN 1 AND 20),
+        experience INTEGER NOT NULL CHECK (experience >= 0),
+        hit_points INTEGER NOT NULL CHECK (hit_points >= 0),
+        current_hit_points INTEGER NOT NULL,
+        temporary_hit_points INTEGER NOT NULL DEFAULT 0 CHECK (temporary_hit_points >= 0),
+        armor_class INTEGER NOT NULL,
+        initiative INTEGER,
+        alive BOOLEAN NOT NULL,
+        notes TEXT NOT NULL,
+        created_at_utc TIMESTAMP WITH TIME ZONE NOT NULL,
+        updated_at_utc TIMESTAMP WITH TIME ZONE NOT NULL,
+        CHECK (current_hit_points <= hit_points + temporary_hit_points)
+    );
+
+    INSERT INTO characters_migrated (id, name, class, race, background, level, experience, hit_points, current_hit_points, temporary_hit_points, armor_class, initiative, alive, notes, created_at_utc, updated_at_utc)
+    SELECT id, name, class, race, background, MIN(MAX(level, 1), 20), MAX(experience, 0), MAX(hit_points, 0), MIN(current_hit_points, MAX(hit_points, 0)), 0, armor_class, initiative, alive, notes, created_at_utc, updated_at_utc
+    FROM characters;
+
+    DROP TABLE characters;
+    ALTER TABLE characters_migrated RENAME TO characters;
+
+    CREATE TABLE encounter_characters_migrated (
+        id TEXT PRIMARY KEY,
+        encounter_id TEXT NOT NULL REFERENCES encounters (id) ON DELETE CASCADE,
+        character_id TEXT NOT NULL REFERENCES characters (id) ON DELETE CASCADE,
+        initiative INTEGER
+    );
+
+    INSERT INTO encounter_characters_migrated (id, encounter_id, character_id, initiative)
+    SELECT id, encounter_id, character_id, initiative FROM encounter_characters;
+
+    DROP TABLE encounter_characters;
+    ALTER TABLE encounter_characters_migrated RENAME TO encounter_characters;
+
+    CREATE INDEX encounter_characters_encounter_id ON encounter_characters (encounter_id);
+    CREATE INDEX encounter_characters_character_id ON encounter_characters (character_id);
+
+    CREATE TABLE encounter_template_combatants_migrated (
+        id TEXT PRIMARY KEY,
+        template_id TEXT NOT NULL REFERENCES encounter_templates (id) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        class TEXT NOT NULL,
+        race TEXT NOT NULL,
+        level INTEGER NOT NULL CHECK (level BETWEEN 1 AND 20),
+        hit_points INTEGER NOT NULL CHECK (hit_points >= 0),
+        hit_dice TEXT,
+        armor_class INTEGER NOT NULL,
+        count INTEGER NOT NULL CHECK (count >= 1),
+        notes TEXT NOT NULL
+    );
+
+    INSERT INTO encounter_template_combatants_migrated (id, template_id, name, class, race, level, hit_points, hit_dice, armor_class, count, notes)
+    SELECT id, template_id, name, class, race, MIN(MAX(level, 1), 20), MAX(hit_points, 0), hit_dice, armor_class, MAX(count, 1), notes
+    FROM encounter_template_combatants;
+
+    DROP TABLE encounter_template_combatants;
+    ALTER TABLE encounter_template_combatants_migrated RENAME TO encounter_template_combatants;
+
+    CREATE INDEX encounter_template_combatants_template_id ON encounter_template_combatants (template_id);
+    ",
+    "
+    ALTER TABLE encounters ADD COLUMN round INTEGER NOT NULL DEFAULT 0 CHECK (round >= 0);
+    ALTER TABLE encounters ADD COLUMN turn_index INTEGER NOT NULL DEFAULT 0 CHECK (turn_index >= 0);
+    ",
+    "
+    CREATE TABLE character_classes (
+        character_id TEXT NOT NULL REFERENCES characters (id) ON DELETE CASCADE,
+        position INTEGER NOT NULL CHECK (position >= 0),
+        class TEXT NOT NULL,
+        subclass TEXT,
+        levels INTEGER NOT NULL CHECK (levels BETWEEN 1 AND 20),
+        hit_die INTEGER NOT NULL CHECK (hit_die IN (6, 8, 10, 12)),
+        PRIMARY KEY (character_id, position)
+    );
+
+    INSERT INTO character_classes (character_id, position, class, subclass, levels, hit_die)
+    SELECT id, 0, class, NULL, level,
+        CASE lower(trim(class))
+            WHEN 'barbarian' THEN 12
+            WHEN 'fighter' THEN 10
+            WHEN 'paladin' THEN 10
+            WHEN 'ranger' THEN 10
+            WHEN 'sorcerer' THEN 6
+            WHEN 'wizard' THEN 6
+            ELSE 8
+        END
+    FROM characters;
+    ",
+    "
+    ALTER TABLE characters ADD COLUMN campaign TEXT;
+
+    CREATE INDEX characters_name ON characters (name COLLATE NOCASE);
+    CREATE INDEX characters_race ON characters (race COLLATE NOCASE);
+    CREATE INDEX characters_level ON characters (level);
+    CREATE INDEX characters_campaign ON characters (campaign);
+    CREATE INDEX character_classes_class ON character_classes (class COLLATE NOCASE);
+
+    CREATE VIRTUAL TABLE characters_fts USING fts5 (
+        name,
+        notes,
+        content = 'characters',
+        content_rowid = 'rowid'
+    );
+    INSERT INTO characters_fts (characters_fts) VALUES ('rebuild');
+
+    CREATE TRIGGER characters_fts_insert AFTER INSERT ON characters BEGIN
+        INSERT INTO characters_fts (rowid, name, notes) VALUES (new.rowid, new.name, new.notes);
+    END;
+
+    CREATE TRIGGER characters_fts_delete AFTER DELETE ON characters BEGIN
+        INSERT INTO characters_fts (characters_fts, rowid, name, notes) VALUES ('delete', old.rowid, old.name, old.notes);
+    END;
+
+    CREATE TRIGGER characters_fts_update AFTER UPDATE OF name, notes ON characters BEGIN
+        INSERT INTO characters_fts (characters_fts, rowid, name, notes) VALUES ('delete', old.rowid, old.name, old.notes);
+        INSERT INTO characters_fts (rowid, name, notes) VALUES (new.rowid, new.name, new.notes);
+    END;
+    ",
+    "
+    CREATE TABLE npcs (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        role TEXT NOT NULL,
+        location TEXT,
+        faction TEXT,
+        attitude TEXT NOT NULL CHECK (attitude IN ('hostile', 'unfriendly', 'indifferent', 'friendly', 'helpful')),
+        secrets TEXT NOT NULL,
+        notes TEXT NOT NULL,
+        created_at_utc TIMESTAMP WITH TIME ZONE NOT NULL,
+        updated_at_utc TIMESTAMP WITH TIME ZONE NOT NULL
+    );
+
+    CREATE INDEX npcs_faction ON npcs (faction);
+
+    CREATE TABLE relationships (
+        id TEXT PRIMARY KEY,
+        source_kind TEXT NOT NULL CHECK (source_kind IN ('character', 'npc')),
+        source_id TEXT NOT NULL,
+        target_kind TEXT NOT NULL CHECK (target_kind IN ('character', 'npc')),
+        target_id TEXT NOT NULL,
+        kind TEXT NOT NULL CHECK (kind IN ('ally', 'rival', 'family', 'employer')),
+        description TEXT NOT NULL,
+        CHECK (source_kind <> target_kind OR source_id <> target_id),
+        UNIQUE (source_kind, source_id, target_kind, target_id, kind)
+    );
+
+    CREATE INDEX relationships_target ON relationships (target_kind, target_id);
+
+    CREATE TRIGGER characters_relationships_delete AFTER DELETE ON characters BEGIN
+        DELETE FROM relationships
+        WHERE (source_kind = 'character' AND source_id = old.id)
+            OR (target_kind = 'character' AND target_id = old.id);
+    END;
+
+    CREATE TRIGGER npcs_relationships_delete AFTER DELETE ON npcs BEGIN
+        DELETE FROM relationships
+        WHERE (source_kind = 'npc' AND source_id = old.id)
+            OR (target_kind = 'npc' AND target_id = old.id);
+    END;
+    ",
+    "
+    CREATE TABLE notes (
+        id TEXT PRIMARY KEY,
+        title TEXT NOT NULL COLLATE NOCASE UNIQUE,
+        body TEXT NOT NULL,
+        attachment_kind TEXT CHECK (attachment_kind IN ('character', 'encounter', 'npc')),
+        attachment_id TEXT,
+        created_at_utc TIMESTAMP WITH TIME ZONE NOT NULL,
+        updated_at_utc TIMESTAMP WITH TIME ZONE NOT NULL,
+        CHECK ((attachment_kind IS NULL) = (attachment_id IS NULL))
+    );
+
+    CREATE INDEX notes_attachment ON notes (attachment_kind, attachment_id);
+
+    CREATE TABLE note_tags (
+        note_id TEXT NOT NULL REFERENCES notes (id) ON DELETE CASCADE,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (note_id, tag)
+    );
+
+    CREATE INDEX note_tags_tag ON note_tags (tag);
+
+    CREATE TABLE note_links (
+        note_id TEXT NOT NULL REFERENCES notes (id) ON DELETE CASCADE,
+        target_title TEXT NOT NULL COLLATE NOCASE,
+        PRIMARY KEY (note_id, target_title)
+    );
+
+    CREATE INDEX note_links_target ON note_links (target_title);
+
+    CREATE VIRTUAL TABLE notes_fts USING fts5 (
+        title,
+        body,
+        content = 'notes',
+        content_rowid = 'rowid'
+    );
+
+    CREATE TRIGGER notes_fts_insert AFTER INSERT ON notes BEGIN
+        INSERT INTO notes_fts (rowid, title, body) VALUES (new.rowid, new.title, new.body);
+    END;
+
+    CREATE TRIGGER notes_fts_delete AFTER DELETE ON notes BEGIN
+        INSERT INTO notes_fts (notes_fts, rowid, title, body) VALUES ('delete', old.rowid, old.title, old.body);
+    END;
+
+    CREATE TRIGGER notes_fts_update AFTER UPDATE OF title, body ON notes BEGIN
+        INSERT INTO notes_fts (notes_fts, rowid, title, body) VALUES ('delete', old.rowid, old.title, old.body);
+        INSERT INTO notes_fts (rowid, title, body) VALUES (new.rowid, new.title, new.body);
+    END;
+
+    CREATE TRIGGER characters_notes_detach AFTER DELETE ON characters BEGIN
+        UPDATE notes SET attachment_kind = NULL, attachment_id = NULL
+        WHERE attachment_kind = 'character' AND attachment_id = old.id;
+    END;
+
+    CREATE TRIGGER encounters_notes_detach AFTER DELETE ON encounters BEGIN
+        UPDATE notes SET attachment_kind = NULL, attachment_id = NULL
+        WHERE attachment_kind = 'encounter' AND attachment_id = old.id;
+    END;
+
+    CREATE TRIGGER npcs_notes_detach AFTER DELETE ON npcs BEGIN
+        UPDATE notes SET attachment_kind = NULL, attachment_id = NULL
+        WHERE attachment_kind = 'npc' AND attachment_id = old.id;
+    END;
+    ",
+    "
+    CREATE TABLE locations (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        kind TEXT NOT NULL CHECK (kind IN ('plane', 'region', 'city', 'building', 'room')),
+        parent_id TEXT REFERENCES locations (id) ON DELETE CASCADE,
+        description TEXT NOT NULL,
+        notes TEXT NOT NULL,
+        created_at_utc TIMESTAMP WITH TIME ZONE NOT NULL,
+        updated_at_utc TIMESTAMP WITH TIME ZONE NOT NULL,
+        CHECK (parent_id IS NULL OR parent_id <> id)
+    );
+
+    CREATE INDEX locations_parent ON locations (parent_id);
+
+    ALTER TABLE encounters ADD COLUMN location_id TEXT REFERENCES locations (id) ON DELETE SET NULL;
+    CREATE INDEX encounters_location ON encounters (location_id);
+
+    ALTER TABLE npcs ADD COLUMN location_id TEXT REFERENCES locations (id) ON DELETE SET NULL;
+    CREATE INDEX npcs_location ON npcs (location_id);
+    ",
+    "
+    CREATE TABLE calendars (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        created_at_utc TIMESTAMP WITH TIME ZONE NOT NULL,
+        updated_at_utc TIMESTAMP WITH TIME ZONE NOT NULL
+    );
+
+    CREATE TABLE calendar_months (
+        calendar_id TEXT NOT NULL REFERENCES calendars (id) ON DELETE CASCADE,
+        position INTEGER NOT NULL CHECK (position >= 0),
+        name TEXT NOT NULL,
+        days INTEGER NOT NULL CHECK (days >= 1),
+        PRIMARY KEY (calendar_id, position)
+    );
+
+    CREATE TABLE calendar_weekdays (
+        calendar_id TEXT NOT NULL REFERENCES calendars (id) ON DELETE CASCADE,
+        position INTEGER NOT NULL CHECK (position >= 0),
+        name TEXT NOT NULL,
+        PRIMARY KEY (calendar_id, position)
+    );
+
+    CREATE TABLE campaign_clock (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        calendar_id TEXT NOT NULL REFERENCES calendars (id),
+        current_time_minutes INTEGER NOT NULL
+    );
+
+    CREATE TABLE calendar_events (
+        id TEXT PRIMARY KEY,
+        title TEXT NOT NULL,
+        description TEXT NOT NULL,
+        occurs_at INTEGER NOT NULL,
+        created_at_utc TIMESTAMP WITH TIME ZONE NOT NULL
+    );
+
+    CREATE INDEX calendar_events_occurs_at ON calendar_events (occurs_at);
+
+    ALTER TABLE encounters ADD COLUMN in_game_time INTEGER;
+
+    CREATE TABLE character_history (
+        id TEXT PRIMARY KEY,
+        character_id TEXT NOT NULL REFERENCES characters (id) ON DELETE CASCADE,
+        description TEXT NOT NULL,
+        in_game_time INTEGER,
+        created_at_utc TIMESTAMP WITH TIME ZONE NOT NULL
+    );
+
+    CREATE INDEX character_history_character_id ON character_history (character_id);
+    ",
+    "
+    CREATE TABLE quests (
+        id TEXT PRIMARY KEY,
+        title TEXT NOT NULL,
+        description TEXT NOT NULL,
+        status TEXT NOT NULL CHECK (status IN ('rumored', 'active', 'completed', 'failed')),
+        reward TEXT NOT NULL,
+        giver_id TEXT REFERENCES characters (id) ON DELETE SET NULL,
+        created_at_utc TIMESTAMP WITH TIME ZONE NOT NULL,
+        updated_at_utc TIMESTAMP WITH TIME ZONE NOT NULL
+    );
+
+    CREATE INDEX quests_status ON quests (status);
+
+    CREATE TABLE quest_objectives (
+        id TEXT PRIMARY KEY,
+        quest_id TEXT NOT NULL REFERENCES quests (id) ON DELETE CASCADE,
+        position INTEGER NOT NULL CHECK (position >= 0),
+        description TEXT NOT NULL,
+        completed BOOLEAN NOT NULL DEFAULT FALSE
+    );
+
+    CREATE INDEX quest_objectives_quest_id ON quest_objectives (quest_id);
+
+    CREATE TABLE quest_encounters (
+        quest_id TEXT NOT NULL REFERENCES quests (id) ON DELETE CASCADE,
+        encounter_id TEXT NOT NULL REFERENCES encounters (id) ON DELETE CASCADE,
+        PRIMARY KEY (quest_id, encounter_id)
+    );
+
+    CREATE INDEX quest_encounters_encounter_id ON quest_encounters (encounter_id);
+    ",
+    "
+    CREATE TABLE character_damage_modifiers (
+        character_id TEXT NOT NULL REFERENCES characters (id) ON DELETE CASCADE,
+        kind TEXT NOT NULL CHECK (kind IN ('resistance', 'immunity', 'vulnerability')),
+        damage_type TEXT NOT NULL CHECK (damage_type IN ('acid', 'bludgeoning', 'cold', 'fire', 'force', 'lightning', 'necrotic', 'piercing', 'poison', 'psychic', 'radiant', 'slashing', 'thunder')),
+        qualifier TEXT CHECK (qualifier IN ('nonmagical', 'nonsilvered', 'nonadamantine'))
+    );
+
+    CREATE INDEX character_damage_modifiers_character_id ON character_damage_modifiers (character_id);
+    ",
+    "
+    ALTER TABLE characters ADD COLUMN strength INTEGER NOT NULL DEFAULT 10 CHECK (strength BETWEEN 1 AND 30);
+    ALTER TABLE characters ADD COLUMN dexterity INTEGER NOT NULL DEFAULT 10 CHECK (dexterity BETWEEN 1 AND 30);
+    ALTER TABLE characters ADD COLUMN constitution INTEGER NOT NULL DEFAULT 10 CHECK (constitution BETWEEN 1 AND 30);
+    ALTER TABLE characters ADD COLUMN intelligence INTEGER NOT NULL DEFAULT 10 CHECK (intelligence BETWEEN 1 AND 30);
+    ALTER TABLE characters ADD COLUMN wisdom INTEGER NOT NULL DEFAULT 10 CHECK (wisdom BETWEEN 1 AND 30);
+    ALTER TABLE characters ADD COLUMN charisma INTEGER NOT NULL DEFAULT 10 CHECK (charisma BETWEEN 1 AND 30);
+    ALTER TABLE characters ADD COLUMN evasion BOOLEAN NOT NULL DEFAULT FALSE;
+
+    CREATE TABLE character_saving_throws (
+        character_id TEXT NOT NULL REFERENCES characters (id) ON DELETE CASCADE,
+        ability TEXT NOT NULL CHECK (ability IN ('strength', 'dexterity', 'constitution', 'intelligence', 'wisdom', 'charisma')),
+        PRIMARY KEY (character_id, ability)
+    );
+    ",
+    "
+    CREATE TABLE character_skills (
+        character_id TEXT NOT NULL REFERENCES characters (id) ON DELETE CASCADE,
+        skill TEXT NOT NULL CHECK (skill IN ('acrobatics', 'animal_handling', 'arcana', 'athletics', 'deception', 'history', 'insight', 'intimidation', 'investigation', 'medicine', 'nature', 'perception', 'performance', 'persuasion', 'religion', 'sleight_of_hand', 'stealth', 'survival')),
+        expertise BOOLEAN NOT NULL DEFAULT FALSE,
+        PRIMARY KEY (character_id, skill)
+    );
+    ",
+    "
+    DROP TRIGGER characters_fts_insert;
+    DROP TRIGGER characters_fts_delete;
+    DROP TRIGGER characters_fts_update;
+    DROP TABLE characters_fts;
+
+    CREATE VIRTUAL TABLE characters_fts USING fts5 (
+        id UNINDEXED,
+        name,
+        notes
+    );
+    INSERT INTO characters_fts (id, name, notes) SELECT id, name, notes FROM characters;
+
+    CREATE TRIGGER characters_fts_insert AFTER INSERT ON characters BEGIN
+        INSERT INTO characters_fts (id, name, notes) VALUES (new.id, new.name, new.notes);
+    END;
+
+    CREATE TRIGGER characters_fts_delete AFTER DELETE ON characters BEGIN
+        DELETE FROM characters_fts WHERE id = old.id;
+    END;
+
+    CREATE TRIGGER characters_fts_update AFTER UPDATE OF id, name, notes ON characters BEGIN
+        UPDATE characters_fts SET id = new.id, name = new.name, notes = new.notes WHERE id = old.id;
+    END;
+    ",
+    "
+    DROP TRIGGER notes_fts_insert;
+    DROP TRIGGER notes_fts_delete;
+    DROP TRIGGER notes_fts_update;
+    DROP TABLE notes_fts;
+
+    CREATE VIRTUAL TABLE notes_fts USING fts5 (
+        id UNINDEXED,
+        title,
+        body
+    );
+    INSERT INTO notes_fts (id, title, body) SELECT id, title, body FROM notes;
+
+    CREATE TRIGGER notes_fts_insert AFTER INSERT ON notes BEGIN
+        INSERT INTO notes_fts (id, title, body) VALUES (new.id, new.title, new.body);
+    END;
+
+    CREATE TRIGGER notes_fts_delete AFTER DELETE ON notes BEGIN
+        DELETE FROM notes_fts WHERE id = old.id;
+    END;
+
+    CREATE TRIGGER notes_fts_update AFTER UPDATE OF id, title, body ON notes BEGIN
+        UPDATE notes_fts SET id = new.id, title = new.title, body = new.body WHERE id = old.id;
+    END;
+    ",
+];
+
+// Turns free text into FTS5 prefix terms, so "gob king" finds "Goblin King".
+pub(crate) fn match_expression(search: &str) -> Option<String> {
+    let terms: Vec<String> = search
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect();
+
+    if terms.is_empty() {
+        return None;
+    }
+
+    Some(terms.join(" "))
+}
+
+// SQLite allows a limited number of parameters, so parent ids are sent in chunks of this size.
+const ID_CHUNK_SIZE: usize = 500;
+
+// Loads the child rows of many parents at once and groups them by the parent id in key_column,
+// in order_column order. Parents without rows are left out of the map.
+pub(crate) fn load_grouped_by_id<T>(
+    connection: &Connection,
+    table: &str,
+    key_column: &str,
+    order_column: &str,
+    ids: &[Uuid],
+    from_row: impl Fn(&Row) -> rusqlite::Result<T>,
+) -> rusqlite::Result<HashMap<Uuid, Vec<T>>> {
+    let mut grouped: HashMap<Uuid, Vec<T>> = HashMap::new();
+
+    for chunk in ids.chunks(ID_CHUNK_SIZE) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let mut statement = connection.prepare(&format!(
+            "SELECT * FROM {table} WHERE {key_column} IN ({placeholders}) ORDER BY {key_column}, {order_column}"
+        ))?;
+
+        let mut rows = statement.query(params_from_iter(chunk.iter().map(Uuid::to_string)))?;
+        while let Some(row) = rows.next()? {
+            let key: String = row.get(key_column)?;
+            let key = Uuid::parse_str(&key).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(e))
+            })?;
+            grouped.entry(key).or_default().push(from_row(row)?);
+        }
+    }
+
+    Ok(grouped)
+}
+
 pub fn setup_structure(
     pool: &Pool<SqliteConnectionManager>,
     configuration: &super::configuration::Configuration,
-) -> rusqlite::Result<()> {
+) -> Result<(), String> {
     if configuration.development_mode {
         log::debug!("Run with --run-migrations to run migrations");
         // @TODO: Set up with --run-migrations flag and uncomment this return.
@@ -35,58 +625,120 @@ pub fn setup_structure(
 
     log::info!("Running Migrations");
 
-    pool.get()
-        .unwrap()
-        .execute(
-            "
-            CREATE TABLE IF NOT EXISTS characters (
-            id UUID PRIMARY KEY,
-            name TEXT NOT NULL,
-            class TEXT NOT NULL,
-            race TEXT NOT NULL,
-            background TEXT,
-            level INTEGER NOT NULL,
-            experience INTEGER NOT NULL,
-            hit_points INTEGER NOT NULL,
-            current_hit_points INTEGER NOT NULL,
-            armor_class INTEGER NOT NULL,
-            initiative INTEGER,
-            alive BOOLEAN NOT NULL,
-            notes TEXT NOT NULL,
-            created_at_utc TIMESTAMP WITH TIME ZONE NOT NULL,
-            updated_at_utc TIMESTAMP WITH TIME ZONE NOT NULL
-        );",
-            [],
-        )
-        .unwrap();
-
-    pool.get()
-        .unwrap()
-        .execute(
-            "
-            CREATE TABLE IF NOT EXISTS encounters (
-                id TEXT PRIMARY KEY,
-                encounter_title TEXT NOT NULL
-            );
-        ",
-            [],
-        )
-        .unwrap();
-
-    pool.get()
-        .unwrap()
-        .execute(
-            "
-            CREATE TABLE IF NOT EXISTS encounter_characters (
-                id TEXT PRIMARY KEY,
-                encounter_id TEXT NOT NULL,
-                character_id TEXT NOT NULL,
-                initiative INTEGER
-            );
-        ",
-            [],
-        )
-        .unwrap();
+    let mut conn = pool.get().map_err(|e| e.to_string())?;
+    let schema_version: usize = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(schema_version) {
+        let version = index + 1;
+        log::info!("Migrating database to version {}", version);
+
+        conn.execute_batch("PRAGMA foreign_keys = OFF;")
+            .map_err(|e| e.to_string())?;
+        let migrated = migrate(&mut conn, version, migration);
+        // The connection goes back to the pool, so this is restored even when the migration failed.
+        conn.pragma_update(None, "foreign_keys", configuration.database.foreign_keys)
+            .map_err(|e| e.to_string())?;
+        migrated?;
+    }
+
+    let orphaned_rows = find_orphaned_rows(&conn).map_err(|e| e.to_string())?;
+    if !orphaned_rows.is_empty() {
+        log::warn!(
+            "Database contains {} orphaned rows, run the integrity check for details",
+            orphaned_rows.len()
+        );
+    }
 
     Ok(())
 }
+
+// Rows whose parent is missing are copied as they are, so the integrity check can report them.
+fn migrate(conn: &mut Connection, version: usize, migration: &str) -> Result<(), String> {
+    let orphaned_before = find_orphaned_rows(conn).map_err(|e| e.to_string())?.len();
+
+    let transaction = conn.transaction().map_err(|e| e.to_string())?;
+    transaction
+        .execute_batch(migration)
+        .map_err(|e| format!("Migration to version {} failed: {}", version, e))?;
+
+    let orphaned_rows = find_orphaned_rows(&transaction).map_err(|e| e.to_string())?;
+    if orphaned_rows.len() > orphaned_before {
+        for row in &orphaned_rows {
+            log::warn!(
+                "Row {} in {} points to a missing row in {}",
+                row.row_id,
+                row.table,
+                row.parent_table
+            );
+        }
+        log::warn!(
+            "Migration to version {} found {} new rows with broken foreign keys",
+            version,
+            orphaned_rows.len() - orphaned_before
+        );
+    }
+
+    transaction
+        .pragma_update(None, "user_version", version)
+        .map_err(|e| e.to_string())?;
+    transaction.commit().map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrphanedRow {
+    pub table: String,
+    pub row_id: i64,
+    pub parent_table: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IntegrityReport {
+    pub schema_version: usize,
+    pub integrity_errors: Vec<String>,
+    pub orphaned_rows: Vec<OrphanedRow>,
+}
+
+fn find_orphaned_rows(conn: &Connection) -> rusqlite::Result<Vec<OrphanedRow>> {
+    let mut statement = conn.prepare("PRAGMA foreign_key_check")?;
+
+    let orphaned_rows = statement
+        .query_map([], |row| {
+            Ok(OrphanedRow {
+                table: row.get(0)?,
+                row_id: row.get(1)?,
+                parent_table: row.get(2)?,
+            })
+        })?
+        .collect();
+
+    orphaned_rows
+}
+
+impl IntegrityReport {
+    pub fn run(conn: &Connection) -> rusqlite::Result<Self> {
+        let mut statement = conn.prepare("PRAGMA integrity_check")?;
+        let integrity_errors = statement
+            .query_map([], |row| row.get::<_, String>(0))?
+            .filter(|message| !matches!(message.as_deref(), Ok("ok")))
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+
+        Ok(IntegrityReport {
+            schema_version: conn.query_row("PRAGMA user_version", [], |row| row.get(0))?,
+            integrity_errors,
+            orphaned_rows: find_orphaned_rows(conn)?,
+        })
+    }
+}
+
+#[tauri::command]
+pub fn check_database_integrity_command(database: State<Database>) -> Result<String, String> {
+    let db_pool = database.pool();
+    log::debug!("Running check_database_integrity_command");
+    let conn = db_pool.get().map_err(|e| e.to_string())?;
+
+    let report = IntegrityReport::run(&conn).map_err(|e| e.to_string())?;
+
+    Ok(serde_json::to_string(&report).unwrap())
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use common::TestApp;
+use serde_json::Value;
+
+use dm_companion_lib::calendar::{
+    advance_time_command, create_calendar_command, load_upcoming_events_command,
+    schedule_event_command, set_clock_command, Calendar, DateInput, Month, TimeAdvance,
+};
+use dm_companion_lib::character::{
+    add_character_history_command, load_character_history_command, Character,
+};
+use dm_companion_lib::encounter::{create_encounter_command, load_encounters_command};
+use dm_companion_lib::repository::{CharacterRepository, SqliteRepository};
+
+fn harptos() -> (Vec<Month>, Vec<String>) {
+    let months = ["Hammer", "Alturiak", "Ches"]
+        .iter()
+        .map(|name| Month {
+            name: String::from(*name),
+            days: 30,
+        })
+        .collect();
+    let weekdays = (1..=10).map(|day| format!("Day {}", day)).collect();
+
+    (months, weekdays)
+}
+
+fn date(year: i64, month: u32, day: u32, hour: u32) -> DateInput {
+    DateInput {
+        year,
+        month,
+        day,
+        hour,
+        minute: 0,
+    }
+}
+
+#[test]
+fn calendar_converts_between_dates_and_times() {
+    let (months, weekdays) = harptos();
+    let calendar = Calendar::new(String::from("Harptos"), months, weekdays).unwrap();
+
+    let time = calendar.time(&date(1491, 2, 15, 18)).unwrap();
+    let in_game_date = calendar.date(time);
+    assert_eq!(in_game_date.year, 1491);
+    assert_eq!(in_game_date.month_name, "Alturiak");
+    assert_eq!(in_game_date.day, 15);
+    assert_eq!(in_game_date.hour, 18);
+
+    // The year has 90 days, so the 45th day of any year falls on the fifth weekday.
+    assert_eq!(in_game_date.weekday, "Day 5");
+
+    assert!(calendar.time(&date(1491, 4, 1, 0)).is_err());
+    assert!(calendar.time(&date(1491, 1, 31, 0)).is_err());
+    assert_eq!(
+        calendar.time(&date(i64::MAX, 1, 1, 0)).err().as_deref(),
+        Some("Year 9223372036854775807 is out of range")
+    );
+    assert!(calendar.time(&date(i64::MIN, 1, 1, 0)).is_err());
+    assert!(Calendar::new(String::from("Empty"), Vec::new(), Vec::new()).is_err());
+}
+
+#[test]
+fn clock_advances_and_stamps_records() {
+    let test_app = TestApp::new();
+    let (months, weekdays) = harptos();
+
+    let calendar: Value = serde_json::from_str(
+        &create_calendar_command(
+            test_app.app.handle().clone(),
+            test_app.database(),
+            String::from("Harptos"),
+            months,
+            weekdays,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    set_clock_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        calendar["id"].as_str().unwrap().to_string(),
+        date(1491, 3, 30, 20),
+    )
+    .unwrap();
+
+    schedule_event_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        String::from("Festival"),
+        String::new(),
+        date(1492, 1, 1, 0),
+    )
+    .unwrap();
+    schedule_event_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        String::from("Council meeting"),
+        String::new(),
+        date(1492, 1, 2, 12),
+    )
+    .unwrap();
+
+    assert!(advance_time_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        TimeAdvance::Travel,
+        None
+    )
+    .is_err());
+    assert!(advance_time_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        TimeAdvance::Travel,
+        Some(i64::MAX)
+    )
+    .is_err());
+    let status: Value = serde_json::from_str(
+        &advance_time_command(
+            test_app.app.handle().clone(),
+            test_app.database(),
+            TimeAdvance::LongRest,
+            None,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(status["date"]["year"], 1492);
+    assert_eq!(status["date"]["month_name"], "Hammer");
+    assert_eq!(status["date"]["hour"], 4);
+    assert_eq!(status["due_events"][0]["title"], "Festival");
+
+    let upcoming: Vec<Value> =
+        serde_json::from_str(&load_upcoming_events_command(test_app.database()).unwrap()).unwrap();
+    assert_eq!(upcoming.len(), 1);
+    assert_eq!(upcoming[0]["title"], "Council meeting");
+
+    create_encounter_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        String::from("Ambush"),
+        None,
+    )
+    .unwrap();
+    let encounters: Vec<Value> =
+        serde_json::from_str(&load_encounters_command(test_app.database()).unwrap()).unwrap();
+    assert_eq!(
+        encounters[0]["in_game_time"],
+        status["clock"]["current_time"]
+    );
+
+    let character = Character::new(
+        String::from("Sildar"),
+        String::from("Fighter"),
+        String::from("Human"),
+        None,
+        5,
+        0,
+        40,
+        16,
+        String::new(),
+    );
+    SqliteRepository::new(&test_app.db_pool())
+        .save_character(&character)
+        .unwrap();
+    add_character_history_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        character.id.to_string(),
+        String::from("Rescued from the Cragmaw hideout"),
+    )
+    .unwrap();
+    let history: Vec<Value> = serde_json::from_str(
+        &load_character_history_command(test_app.database(), character.id.to_string()).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0]["in_game_time"], status["clock"]["current_time"]);
+}
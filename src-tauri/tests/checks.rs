@@ -0,0 +1,223 @@
+mod common;
+
+use common::TestApp;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::HashMap;
+
+use dm_companion_lib::abilities::{Ability, AbilityScores, Skill, SkillProficiency};
+use dm_companion_lib::character::Character;
+use dm_companion_lib::checks::{roll_group, Check, GroupRoll, GroupRollTargets};
+use dm_companion_lib::configuration::Preferences;
+use dm_companion_lib::dice::RollMode;
+use dm_companion_lib::encounter::{add_character_to_encounter, Encounter};
+use dm_companion_lib::encounter_template::{EncounterTemplate, TemplateCombatant};
+use dm_companion_lib::repository::{
+    CharacterRepository, EncounterRepository, InMemoryRepository, SqliteRepository,
+};
+
+fn sample_character(name: &str, campaign: &str) -> Character {
+    let mut character = Character::new(
+        String::from(name),
+        String::from("Rogue"),
+        String::from("Halfling"),
+        None,
+        5,
+        0,
+        30,
+        14,
+        String::new(),
+    );
+    character.campaign = Some(String::from(campaign));
+    character
+}
+
+#[test]
+fn skill_modifiers_include_proficiency_and_expertise() {
+    let mut lidda = sample_character("Lidda", "Greyhawk");
+    lidda.abilities = AbilityScores {
+        dexterity: 17,
+        wisdom: 8,
+        ..AbilityScores::default()
+    };
+    lidda.skill_proficiencies = vec![
+        SkillProficiency {
+            skill: Skill::Stealth,
+            expertise: true,
+        },
+        SkillProficiency {
+            skill: Skill::Perception,
+            expertise: false,
+        },
+    ];
+
+    assert_eq!(lidda.skill_modifier(Skill::Stealth), 9);
+    assert_eq!(lidda.skill_modifier(Skill::Perception), 2);
+    assert_eq!(lidda.skill_modifier(Skill::Acrobatics), 3);
+    assert_eq!(
+        Check::AbilityCheck {
+            ability: Ability::Wisdom
+        }
+        .modifier_for(&lidda),
+        -1
+    );
+}
+
+#[test]
+fn the_party_rolls_together_outside_an_encounter() {
+    let repository = InMemoryRepository::new();
+    for name in ["Tordek", "Mialee", "Jozan", "Lidda"] {
+        repository
+            .save_character(&sample_character(name, "Greyhawk"))
+            .unwrap();
+    }
+    let mut fallen = sample_character("Regdar", "Greyhawk");
+    fallen.alive = false;
+    repository.save_character(&fallen).unwrap();
+    repository
+        .save_character(&sample_character("Strahd", "Barovia"))
+        .unwrap();
+
+    let group_roll = GroupRoll {
+        check: Check::Skill {
+            skill: Skill::Stealth,
+        },
+        dc: 11,
+        targets: GroupRollTargets::Party {
+            campaign: String::from("Greyhawk"),
+        },
+        modes: HashMap::new(),
+    };
+    for seed in 0..20 {
+        let result =
+            roll_group(&repository, &group_roll, &mut StdRng::seed_from_u64(seed)).unwrap();
+        let names: Vec<&str> = result
+            .entries
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Jozan", "Lidda", "Mialee", "Tordek"]);
+        assert_eq!(result.passes + result.failures, 4);
+        assert_eq!(result.group_passed, result.passes >= 2);
+        for entry in &result.entries {
+            assert_eq!(entry.passed, entry.total >= 11);
+        }
+    }
+}
+
+#[test]
+fn a_party_larger_than_a_page_rolls_whole() {
+    let repository = InMemoryRepository::new();
+    for index in 0..501 {
+        repository
+            .save_character(&sample_character(
+                &format!("Soldier {:03}", index),
+                "Red Hand",
+            ))
+            .unwrap();
+    }
+
+    let result = roll_group(
+        &repository,
+        &GroupRoll {
+            check: Check::AbilityCheck {
+                ability: Ability::Wisdom,
+            },
+            dc: 10,
+            targets: GroupRollTargets::Party {
+                campaign: String::from("Red Hand"),
+            },
+            modes: HashMap::new(),
+        },
+        &mut StdRng::seed_from_u64(0),
+    )
+    .unwrap();
+
+    assert_eq!(result.entries.len(), 501);
+    assert_eq!(result.entries[500].name, "Soldier 500");
+}
+
+#[test]
+fn participants_roll_with_their_own_advantage() {
+    let repository = InMemoryRepository::new();
+    let encounter = Encounter::new(String::from("Trapped corridor"));
+    repository.save_encounter(&encounter).unwrap();
+    let mut participant_ids = Vec::new();
+    for name in ["Tordek", "Mialee"] {
+        let character = sample_character(name, "Greyhawk");
+        repository.save_character(&character).unwrap();
+        participant_ids.push(
+            add_character_to_encounter(&repository, encounter.id, character.id, None)
+                .unwrap()
+                .id,
+        );
+    }
+
+    let group_roll = GroupRoll {
+        check: Check::SavingThrow {
+            ability: Ability::Dexterity,
+        },
+        dc: 13,
+        targets: GroupRollTargets::Participants {
+            encounter_id: encounter.id,
+            participant_ids: participant_ids.clone(),
+        },
+        modes: HashMap::from([(participant_ids[0], RollMode::Advantage)]),
+    };
+    let result = roll_group(&repository, &group_roll, &mut StdRng::seed_from_u64(7)).unwrap();
+
+    assert_eq!(result.entries[0].id, participant_ids[0]);
+    assert_eq!(result.entries[0].roll.rolls.len(), 2);
+    assert_eq!(result.entries[1].roll.rolls.len(), 1);
+}
+
+#[test]
+fn template_monsters_do_not_roll_with_the_party() {
+    let test_app = TestApp::new();
+    let repository = SqliteRepository::new(&test_app.db_pool());
+    for name in ["Tordek", "Mialee"] {
+        repository
+            .save_character(&sample_character(name, "Greyhawk"))
+            .unwrap();
+    }
+    let mut template = EncounterTemplate::new(String::from("Goblin camp"));
+    template.combatants = vec![TemplateCombatant::new(
+        template.id,
+        String::from("Goblin"),
+        String::from("Monster"),
+        String::from("Goblinoid"),
+        1,
+        7,
+        None,
+        15,
+        3,
+        String::new(),
+    )
+    .unwrap()];
+    template
+        .instantiate(
+            String::from("Ambush at the ford"),
+            &test_app.db_pool(),
+            &Preferences::default(),
+            &mut StdRng::seed_from_u64(7),
+        )
+        .unwrap();
+
+    let group_roll = GroupRoll {
+        check: Check::Skill {
+            skill: Skill::Perception,
+        },
+        dc: 10,
+        targets: GroupRollTargets::Party {
+            campaign: String::from("Greyhawk"),
+        },
+        modes: HashMap::new(),
+    };
+    let result = roll_group(&repository, &group_roll, &mut StdRng::seed_from_u64(7)).unwrap();
+    let names: Vec<&str> = result
+        .entries
+        .iter()
+        .map(|entry| entry.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["Mialee", "Tordek"]);
+}
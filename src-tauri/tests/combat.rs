@@ -0,0 +1,287 @@
+mod common;
+
+use common::TestApp;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use uuid::Uuid;
+
+use dm_companion_lib::abilities::{Ability, AbilityScores};
+use dm_companion_lib::character::Character;
+use dm_companion_lib::combat::{resolve_area_effect, resolve_attack, AreaEffect, Attack};
+use dm_companion_lib::damage::{Damage, DamageModifier, DamageModifierKind, DamageType};
+use dm_companion_lib::dice::{DiceExpression, RollMode};
+use dm_companion_lib::encounter::{add_character_to_encounter, Encounter};
+use dm_companion_lib::repository::{
+    CharacterRepository, EncounterRepository, InMemoryRepository, SqliteRepository,
+};
+
+fn sample_character(name: &str, hit_points: i32, armor_class: i32) -> Character {
+    Character::new(
+        String::from(name),
+        String::from("Fighter"),
+        String::from("Human"),
+        None,
+        3,
+        0,
+        hit_points,
+        armor_class,
+        String::new(),
+    )
+}
+
+// Returns the repository with the encounter id and the attacker and target participant ids.
+fn duel(target_hit_points: i32) -> (InMemoryRepository, Uuid, Uuid, Uuid) {
+    let repository = InMemoryRepository::new();
+    let attacker = sample_character("Tordek", 28, 16);
+    let target = sample_character("Goblin", target_hit_points, 15);
+    repository.save_character(&attacker).unwrap();
+    repository.save_character(&target).unwrap();
+
+    let encounter = Encounter::new(String::from("Goblin ambush"));
+    repository.save_encounter(&encounter).unwrap();
+    let attacker =
+        add_character_to_encounter(&repository, encounter.id, attacker.id, None).unwrap();
+    let target = add_character_to_encounter(&repository, encounter.id, target.id, None).unwrap();
+
+    (repository, encounter.id, attacker.id, target.id)
+}
+
+fn longsword(mode: RollMode) -> Attack {
+    Attack {
+        attack_bonus: 5,
+        damage: DiceExpression::new(1, 8, 3),
+        damage_type: DamageType::Slashing,
+        magical: false,
+        mode,
+    }
+}
+
+#[test]
+fn attacks_are_deterministic_under_a_seeded_rng() {
+    let (first, encounter_id, attacker_id, target_id) = duel(10_000);
+    let (second, second_encounter_id, second_attacker_id, second_target_id) = duel(10_000);
+
+    for seed in 0..20 {
+        let a = resolve_attack(
+            &first,
+            encounter_id,
+            attacker_id,
+            target_id,
+            &longsword(RollMode::Advantage),
+            &mut StdRng::seed_from_u64(seed),
+        )
+        .unwrap();
+        let b = resolve_attack(
+            &second,
+            second_encounter_id,
+            second_attacker_id,
+            second_target_id,
+            &longsword(RollMode::Advantage),
+            &mut StdRng::seed_from_u64(seed),
+        )
+        .unwrap();
+
+        assert_eq!(a.attack_roll.rolls, b.attack_roll.rolls);
+        assert_eq!(a.damage_dealt, b.damage_dealt);
+        assert_eq!(a.hit_points_after, b.hit_points_after);
+    }
+}
+
+#[test]
+fn attacks_follow_the_rules_for_hits_and_criticals() {
+    let (repository, encounter_id, attacker_id, target_id) = duel(10_000);
+    let mut criticals = 0;
+    let mut fumbles = 0;
+
+    for seed in 0..300 {
+        let mode = match seed % 3 {
+            0 => RollMode::Normal,
+            1 => RollMode::Advantage,
+            _ => RollMode::Disadvantage,
+        };
+        let result = resolve_attack(
+            &repository,
+            encounter_id,
+            attacker_id,
+            target_id,
+            &longsword(mode),
+            &mut StdRng::seed_from_u64(seed),
+        )
+        .unwrap();
+
+        match mode {
+            RollMode::Normal => assert_eq!(result.attack_roll.rolls.len(), 1),
+            RollMode::Advantage => assert_eq!(
+                result.attack_roll.natural,
+                *result.attack_roll.rolls.iter().max().unwrap()
+            ),
+            RollMode::Disadvantage => assert_eq!(
+                result.attack_roll.natural,
+                *result.attack_roll.rolls.iter().min().unwrap()
+            ),
+        }
+
+        if result.critical {
+            criticals += 1;
+            assert!(result.hit);
+            assert_eq!(result.damage_roll.as_ref().unwrap().rolls.len(), 2);
+        } else if result.fumble {
+            fumbles += 1;
+            assert!(!result.hit);
+        } else {
+            assert_eq!(result.hit, result.attack_total >= 15);
+        }
+
+        if result.hit {
+            assert_eq!(result.damage_roll.as_ref().unwrap().modifier, 3);
+        } else {
+            assert!(result.damage_roll.is_none());
+        }
+        assert_eq!(
+            result.hit_points_after,
+            result.hit_points_before - result.damage_dealt
+        );
+        let target = repository
+            .load_encounter_detail(encounter_id)
+            .unwrap()
+            .unwrap()
+            .characters
+            .into_iter()
+            .find(|participant| participant.id == target_id)
+            .unwrap();
+        assert_eq!(target.character.current_hit_points, result.hit_points_after);
+    }
+
+    assert!(criticals > 0);
+    assert!(fumbles > 0);
+}
+
+#[test]
+fn damage_uses_temporary_hit_points_first_and_stops_at_zero() {
+    let mut character = sample_character("Goblin", 7, 15);
+    character.temporary_hit_points = 5;
+
+    character.take_damage(&[Damage::new(8, DamageType::Slashing)]);
+    assert_eq!(character.temporary_hit_points, 0);
+    assert_eq!(character.current_hit_points, 4);
+
+    character.take_damage(&[Damage::new(10, DamageType::Slashing)]);
+    assert_eq!(character.current_hit_points, 0);
+
+    let (repository, encounter_id, attacker_id, _) = duel(10);
+    assert!(resolve_attack(
+        &repository,
+        encounter_id,
+        attacker_id,
+        Uuid::new_v4(),
+        &longsword(RollMode::Normal),
+        &mut StdRng::seed_from_u64(1),
+    )
+    .is_err());
+}
+
+#[test]
+fn area_effects_roll_damage_once_and_save_per_target() {
+    let test_app = TestApp::new();
+    let db_pool = test_app.db_pool();
+    let repository = SqliteRepository::new(&db_pool);
+
+    let mut rogue = sample_character("Lidda", 10_000, 15);
+    rogue.abilities = AbilityScores {
+        dexterity: 18,
+        ..AbilityScores::default()
+    };
+    rogue.saving_throw_proficiencies = vec![Ability::Dexterity];
+    rogue.evasion = true;
+    let mut salamander = sample_character("Salamander", 10_000, 15);
+    salamander.damage_modifiers = vec![DamageModifier::new(
+        DamageModifierKind::Resistance,
+        DamageType::Fire,
+        None,
+    )];
+    let goblin = sample_character("Goblin", 10_000, 15);
+
+    let encounter = Encounter::new(String::from("Fireball"));
+    repository.save_encounter(&encounter).unwrap();
+    let mut target_ids = Vec::new();
+    for character in [&rogue, &salamander, &goblin] {
+        repository.save_character(character).unwrap();
+        target_ids.push(
+            add_character_to_encounter(&repository, encounter.id, character.id, None)
+                .unwrap()
+                .id,
+        );
+    }
+    assert_eq!(rogue.saving_throw_modifier(Ability::Dexterity), 6);
+
+    let fireball = AreaEffect {
+        save_ability: Ability::Dexterity,
+        dc: 15,
+        damage: DiceExpression::new(8, 6, 0),
+        damage_type: DamageType::Fire,
+        magical: true,
+        half_on_success: true,
+    };
+    for seed in 0..30 {
+        let result = resolve_area_effect(
+            &repository,
+            encounter.id,
+            &target_ids,
+            &fireball,
+            &mut StdRng::seed_from_u64(seed),
+        )
+        .unwrap();
+        let rolled = result.damage_roll.total;
+
+        let [lidda, salamander, goblin] = &result.targets[..] else {
+            panic!("expected three targets");
+        };
+        for target in &result.targets {
+            assert_eq!(target.saved, target.save_total >= 15);
+            assert_eq!(
+                target.hit_points_after,
+                target.hit_points_before - target.damage_dealt
+            );
+        }
+        assert_eq!(lidda.damage_dealt, if lidda.saved { 0 } else { rolled / 2 });
+        let halved = if salamander.saved { rolled / 2 } else { rolled };
+        assert_eq!(salamander.damage_dealt, halved / 2);
+        assert_eq!(
+            goblin.damage_dealt,
+            if goblin.saved { rolled / 2 } else { rolled }
+        );
+    }
+
+    let stored = repository.load_character(goblin.id).unwrap().unwrap();
+    let participants = repository
+        .load_encounter_detail(encounter.id)
+        .unwrap()
+        .unwrap()
+        .characters;
+    let participant = participants
+        .iter()
+        .find(|participant| participant.character.id == goblin.id)
+        .unwrap();
+    assert_eq!(
+        stored.current_hit_points,
+        participant.character.current_hit_points
+    );
+    assert!(stored.current_hit_points < 10_000);
+
+    let duplicate = resolve_area_effect(
+        &repository,
+        encounter.id,
+        &[target_ids[2], target_ids[2]],
+        &fireball,
+        &mut StdRng::seed_from_u64(0),
+    );
+    assert!(duplicate.is_err());
+    assert_eq!(
+        repository
+            .load_character(goblin.id)
+            .unwrap()
+            .unwrap()
+            .current_hit_points,
+        stored.current_hit_points
+    );
+}
@@ -0,0 +1,245 @@
+mod common;
+
+use common::TestApp;
+use serde_json::Value;
+
+use dm_companion_lib::character::{
+    create_character_command, load_characters_command, Character, ClassLevel,
+};
+use dm_companion_lib::configuration::{create_profile_command, switch_profile_command};
+use dm_companion_lib::encounter::{
+    add_character_to_encounter_command, create_encounter_command, load_encounter_detail_command,
+    load_encounters_command,
+};
+
+fn create_character(test_app: &TestApp, name: &str, level: i32) -> Result<Value, String> {
+    let character = create_character_command(
+        test_app.app.handle().clone(),
+        String::from(name),
+        vec![ClassLevel::new(String::from("Rogue"), None, level)],
+        String::from("Halfling"),
+        Some(String::from("Urchin")),
+        0,
+        18,
+        14,
+        String::new(),
+        None,
+        test_app.database(),
+    )?;
+
+    Ok(serde_json::from_str(&character).unwrap())
+}
+
+fn create_encounter(test_app: &TestApp, title: &str) -> Value {
+    create_encounter_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        String::from(title),
+        None,
+    )
+    .unwrap();
+
+    let encounters: Vec<Value> =
+        serde_json::from_str(&load_encounters_command(test_app.database()).unwrap()).unwrap();
+
+    encounters
+        .into_iter()
+        .find(|encounter| encounter["encounter_title"] == title)
+        .unwrap()
+}
+
+#[test]
+fn created_characters_are_loaded() {
+    let test_app = TestApp::new();
+
+    let character = create_character(&test_app, "Lidda", 2).unwrap();
+    let characters: Vec<Value> = serde_json::from_str(
+        &load_characters_command(test_app.database(), test_app.configuration()).unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(characters.len(), 1);
+    assert_eq!(characters[0]["id"], character["id"]);
+    assert_eq!(characters[0]["current_hit_points"], 18);
+}
+
+#[test]
+fn character_with_invalid_level_is_rejected() {
+    let test_app = TestApp::new();
+
+    assert!(create_character(&test_app, "Lidda", 0).is_err());
+    assert!(create_character(&test_app, "Lidda", 21).is_err());
+}
+
+#[test]
+fn invalid_class_levels_are_explained_before_saving() {
+    let class_levels = |levels: [(i32, u32); 2]| {
+        levels
+            .iter()
+            .map(|(levels, hit_die)| {
+                let mut class_level = ClassLevel::new(String::from("Rogue"), None, *levels);
+                class_level.hit_die = *hit_die;
+                class_level
+            })
+            .collect::<Vec<ClassLevel>>()
+    };
+
+    for (levels, error) in [
+        ([(3, 8), (0, 8)], "Rogue needs at least one level, got 0"),
+        (
+            [(3, 7), (1, 8)],
+            "Hit die d7 of Rogue is not a d6, d8, d10 or d12",
+        ),
+        (
+            [(15, 8), (10, 8)],
+            "Class levels add up to 25, the most is 20",
+        ),
+    ] {
+        let character = Character::with_class_levels(
+            String::from("Lidda"),
+            class_levels(levels),
+            String::from("Halfling"),
+            None,
+            0,
+            18,
+            14,
+            String::new(),
+        );
+        assert_eq!(character.err().as_deref(), Some(error));
+    }
+}
+
+#[test]
+fn multiclass_characters_keep_their_class_levels() {
+    let test_app = TestApp::new();
+
+    create_character_command(
+        test_app.app.handle().clone(),
+        String::from("Tordek"),
+        vec![
+            ClassLevel::new(String::from("Fighter"), Some(String::from("Champion")), 3),
+            ClassLevel::new(String::from("Wizard"), None, 2),
+        ],
+        String::from("Dwarf"),
+        None,
+        0,
+        40,
+        16,
+        String::new(),
+        None,
+        test_app.database(),
+    )
+    .unwrap();
+
+    let characters: Vec<Value> = serde_json::from_str(
+        &load_characters_command(test_app.database(), test_app.configuration()).unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(characters[0]["class"], "Fighter / Wizard");
+    assert_eq!(characters[0]["level"], 5);
+    assert_eq!(characters[0]["proficiency_bonus"], 3);
+    assert_eq!(characters[0]["class_levels"][0]["subclass"], "Champion");
+    assert_eq!(characters[0]["class_levels"][0]["hit_die"], 10);
+    assert_eq!(characters[0]["class_levels"][1]["hit_die"], 6);
+}
+
+#[test]
+fn characters_added_to_an_encounter_show_up_in_its_detail() {
+    let test_app = TestApp::new();
+
+    let character = create_character(&test_app, "Lidda", 2).unwrap();
+    let encounter = create_encounter(&test_app, "Goblin ambush");
+    let encounter_id = encounter["id"].as_str().unwrap().to_string();
+
+    add_character_to_encounter_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        encounter_id.clone(),
+        character["id"].as_str().unwrap().to_string(),
+        Some(17),
+    )
+    .unwrap();
+
+    let encounter_detail: Value = serde_json::from_str(
+        &load_encounter_detail_command(test_app.database(), encounter_id).unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(encounter_detail["encounter"]["id"], encounter["id"]);
+    assert_eq!(encounter_detail["characters"][0]["initiative"], 17);
+    assert_eq!(
+        encounter_detail["characters"][0]["character"]["id"],
+        character["id"]
+    );
+}
+
+#[test]
+fn unknown_ids_are_reported_as_errors() {
+    let test_app = TestApp::new();
+
+    let character = create_character(&test_app, "Lidda", 2).unwrap();
+    let character_id = character["id"].as_str().unwrap().to_string();
+    let encounter = create_encounter(&test_app, "Goblin ambush");
+    let encounter_id = encounter["id"].as_str().unwrap().to_string();
+    let unknown_id = uuid::Uuid::new_v4().to_string();
+
+    assert!(add_character_to_encounter_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        unknown_id.clone(),
+        character_id,
+        None
+    )
+    .is_err());
+    assert!(add_character_to_encounter_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        encounter_id,
+        unknown_id.clone(),
+        None
+    )
+    .is_err());
+    assert!(load_encounter_detail_command(test_app.database(), unknown_id).is_err());
+    assert!(
+        load_encounter_detail_command(test_app.database(), String::from("not-a-uuid")).is_err()
+    );
+}
+
+#[test]
+fn switching_profiles_uses_a_separate_database() {
+    let test_app = TestApp::new();
+
+    create_character(&test_app, "Lidda", 2).unwrap();
+
+    create_profile_command(
+        test_app.configuration(),
+        String::from("Convention one-shot"),
+    )
+    .unwrap();
+    switch_profile_command(
+        test_app.app.handle().clone(),
+        test_app.configuration(),
+        test_app.database(),
+        String::from("Convention one-shot"),
+    )
+    .unwrap();
+
+    let characters: Vec<Value> = serde_json::from_str(
+        &load_characters_command(test_app.database(), test_app.configuration()).unwrap(),
+    )
+    .unwrap();
+    assert!(characters.is_empty());
+
+    assert!(switch_profile_command(
+        test_app.app.handle().clone(),
+        test_app.configuration(),
+        test_app.database(),
+        String::from("Unknown"),
+    )
+    .is_err());
+    assert_eq!(
+        test_app.configuration().lock().unwrap().active_profile,
+        "Convention one-shot"
+    );
+}
@@ -0,0 +1,74 @@
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use tauri::test::{mock_builder, mock_context, noop_assets, MockRuntime};
+use tauri::{App, Manager, State};
+
+use dm_companion_lib::configuration::{Configuration, ConfigurationOverrides};
+use dm_companion_lib::player_server::PlayerServerState;
+use dm_companion_lib::player_view::PlayerScreen;
+use dm_companion_lib::storage::{self, Database};
+
+// Every test gets its own configuration and database in a throwaway directory.
+pub struct TestApp {
+    pub app: App<MockRuntime>,
+    directory: PathBuf,
+}
+
+impl TestApp {
+    pub fn new() -> Self {
+        let directory = std::env::temp_dir().join(format!("dm-companion-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let configuration = Configuration::load(ConfigurationOverrides {
+            data_directory: Some(directory.clone()),
+            profile: None,
+        })
+        .unwrap();
+        let db_pool = storage::setup_database(&configuration).unwrap();
+
+        let app = mock_builder()
+            .manage(Mutex::new(configuration))
+            .manage(Database::new(db_pool))
+            .manage(PlayerScreen::default())
+            .manage(PlayerServerState::default())
+            .build(mock_context(noop_assets()))
+            .unwrap();
+
+        TestApp { app, directory }
+    }
+
+    pub fn directory(&self) -> &PathBuf {
+        &self.directory
+    }
+
+    pub fn database(&self) -> State<'_, Database> {
+        self.app.state()
+    }
+
+    pub fn db_pool(&self) -> Pool<SqliteConnectionManager> {
+        self.database().pool()
+    }
+
+    pub fn configuration(&self) -> State<'_, Mutex<Configuration>> {
+        self.app.state()
+    }
+
+    pub fn player_screen(&self) -> State<'_, PlayerScreen> {
+        self.app.state()
+    }
+
+    pub fn player_server(&self) -> State<'_, PlayerServerState> {
+        self.app.state()
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.directory);
+    }
+}
@@ -0,0 +1,286 @@
+mod common;
+
+use common::TestApp;
+
+use dm_companion_lib::configuration::{
+    update_preferences_command, Configuration, ConfigurationOverrides, DiceMode, HitPointMethod,
+    Preferences, RuleSet, CONFIGURATION_VERSION,
+};
+
+#[test]
+fn missing_keys_fall_back_to_defaults() {
+    let directory = std::env::temp_dir().join(format!("dm-companion-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(
+        directory.join("config.toml"),
+        "[preferences]\nhitPointMethod = \"maximum\"\n",
+    )
+    .unwrap();
+
+    let configuration = Configuration::load(ConfigurationOverrides {
+        data_directory: Some(directory.clone()),
+        profile: None,
+    })
+    .unwrap();
+
+    assert_eq!(
+        configuration.preferences.hit_point_method,
+        HitPointMethod::Maximum
+    );
+    assert_eq!(
+        configuration.preferences.backup_retention,
+        Preferences::default().backup_retention
+    );
+    assert_eq!(configuration.active_profile, "default");
+    assert!(configuration.db_path.starts_with(&directory));
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn preferences_are_validated_and_saved() {
+    let test_app = TestApp::new();
+
+    let invalid_preferences = Preferences {
+        backup_retention: 1000,
+        ..Preferences::default()
+    };
+    assert!(update_preferences_command(test_app.configuration(), invalid_preferences).is_err());
+
+    let preferences = Preferences {
+        backup_retention: 10,
+        auto_roll_monster_initiative: true,
+        ..Preferences::default()
+    };
+    update_preferences_command(test_app.configuration(), preferences).unwrap();
+
+    let config_path = test_app.configuration().lock().unwrap().config_path.clone();
+    let saved: Configuration =
+        toml::from_str(&std::fs::read_to_string(config_path).unwrap()).unwrap();
+    assert_eq!(saved.preferences.backup_retention, 10);
+    assert!(saved.preferences.auto_roll_monster_initiative);
+}
+
+#[test]
+fn older_configuration_is_upgraded_and_backed_up() {
+    let directory = std::env::temp_dir().join(format!("dm-companion-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let legacy_db_path = directory.join("legacy.sqlite");
+    let original = format!(
+        "version = \"0.1.0\"\ndevelopmentMode = false\nconfigurationPath = \"config.toml\"\ndbPath = {:?}\ntheme = \"dark\"\n",
+        legacy_db_path.to_str().unwrap()
+    );
+    std::fs::write(directory.join("config.toml"), &original).unwrap();
+
+    let configuration = Configuration::load(ConfigurationOverrides {
+        data_directory: Some(directory.clone()),
+        profile: None,
+    })
+    .unwrap();
+
+    assert_eq!(configuration.schema_version, CONFIGURATION_VERSION);
+    assert_eq!(configuration.version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(configuration.db_path, legacy_db_path);
+    assert_eq!(configuration.profiles[0].db_path, legacy_db_path);
+    assert!(configuration
+        .load_warnings
+        .iter()
+        .any(|warning| warning.contains("theme")));
+    assert_eq!(
+        std::fs::read_to_string(directory.join("config.toml.bak")).unwrap(),
+        original
+    );
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn rule_set_is_kept_on_upgrade() {
+    let directory = std::env::temp_dir().join(format!("dm-companion-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(
+        directory.join("config.toml"),
+        "[preferences]\nruleSet = \"5e-2024\"\ndiceMode = \"physical\"\n",
+    )
+    .unwrap();
+
+    let configuration = Configuration::load(ConfigurationOverrides {
+        data_directory: Some(directory.clone()),
+        profile: None,
+    })
+    .unwrap();
+
+    assert_eq!(configuration.schema_version, CONFIGURATION_VERSION);
+    assert_eq!(
+        configuration.preferences.rule_set,
+        RuleSet::FifthEdition2024
+    );
+    assert_eq!(configuration.preferences.dice_mode, DiceMode::Physical);
+    assert!(configuration.load_warnings.is_empty());
+    assert!(std::fs::read_to_string(directory.join("config.toml"))
+        .unwrap()
+        .contains("ruleSet = \"5e-2024\""));
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn invalid_values_fall_back_on_their_own() {
+    let directory = std::env::temp_dir().join(format!("dm-companion-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(
+        directory.join("config.toml"),
+        "schemaVersion = 2\n[preferences]\ndiceMode = \"telepathic\"\nhitPointMethod = \"maximum\"\nbackupRetention = 7\n",
+    )
+    .unwrap();
+
+    let configuration = Configuration::load(ConfigurationOverrides {
+        data_directory: Some(directory.clone()),
+        profile: None,
+    })
+    .unwrap();
+
+    assert_eq!(configuration.preferences.dice_mode, DiceMode::Digital);
+    assert_eq!(
+        configuration.preferences.hit_point_method,
+        HitPointMethod::Maximum
+    );
+    assert_eq!(configuration.preferences.backup_retention, 7);
+    assert_eq!(configuration.load_warnings.len(), 1);
+    assert!(configuration.load_warnings[0].contains("preferences.diceMode"));
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn backups_are_rotated_up_to_the_retention() {
+    let directory = std::env::temp_dir().join(format!("dm-companion-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&directory).unwrap();
+
+    // Every load finds an unknown key, so every load backs the file up before rewriting it.
+    for generation in 0..4 {
+        std::fs::write(
+            directory.join("config.toml"),
+            format!(
+                "schemaVersion = 2\ngeneration = {}\n[preferences]\nbackupRetention = 3\n",
+                generation
+            ),
+        )
+        .unwrap();
+        Configuration::load(ConfigurationOverrides {
+            data_directory: Some(directory.clone()),
+            profile: None,
+        })
+        .unwrap();
+    }
+
+    for (backup, generation) in [
+        ("config.toml.bak", 3),
+        ("config.toml.bak.1", 2),
+        ("config.toml.bak.2", 1),
+    ] {
+        assert!(std::fs::read_to_string(directory.join(backup))
+            .unwrap()
+            .contains(&format!("generation = {}", generation)));
+    }
+    assert!(!directory.join("config.toml.bak.3").exists());
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn broken_configuration_is_reset() {
+    let directory = std::env::temp_dir().join(format!("dm-companion-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("config.toml"), "preferences = [not toml").unwrap();
+
+    let configuration = Configuration::load(ConfigurationOverrides {
+        data_directory: Some(directory.clone()),
+        profile: None,
+    })
+    .unwrap();
+
+    assert_eq!(configuration.active_profile, "default");
+    assert_eq!(configuration.load_warnings.len(), 1);
+    assert_eq!(
+        std::fs::read_to_string(directory.join("config.toml.bak")).unwrap(),
+        "preferences = [not toml"
+    );
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn unknown_profiles_are_not_created() {
+    let directory = std::env::temp_dir().join(format!("dm-companion-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&directory).unwrap();
+
+    let result = Configuration::load(ConfigurationOverrides {
+        data_directory: Some(directory.clone()),
+        profile: Some(String::from("defualt")),
+    });
+    let configuration = Configuration::load(ConfigurationOverrides {
+        data_directory: Some(directory.clone()),
+        profile: Some(String::from("default")),
+    })
+    .unwrap();
+
+    assert_eq!(result.err().as_deref(), Some("Unknown profile defualt"));
+    assert_eq!(configuration.profiles.len(), 1);
+    assert!(std::fs::read_dir(&directory).unwrap().all(|entry| !entry
+        .unwrap()
+        .file_name()
+        .to_string_lossy()
+        .contains("defualt")));
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn profiles_cannot_share_a_database() {
+    let directory = std::env::temp_dir().join(format!("dm-companion-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let mut configuration = Configuration::load(ConfigurationOverrides {
+        data_directory: Some(directory.clone()),
+        profile: None,
+    })
+    .unwrap();
+
+    configuration.create_profile("Lost Mine").unwrap();
+    for name in ["lost mine", "Lost-Mine", "LOST_MINE"] {
+        assert_eq!(
+            configuration.create_profile(name).err(),
+            Some(format!(
+                "Profile {} would share a database with profile Lost Mine",
+                name
+            ))
+        );
+    }
+    assert!(configuration.create_profile("Lost Mine 2").is_ok());
+    assert_eq!(configuration.profiles.len(), 3);
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+// The only test in this binary that installs the global logger.
+#[test]
+fn log_level_preference_reaches_the_logger() {
+    let directory = std::env::temp_dir().join(format!("dm-companion-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(
+        directory.join("config.toml"),
+        "[preferences]\nlogLevel = \"trace\"\n",
+    )
+    .unwrap();
+
+    Configuration::init_with(ConfigurationOverrides {
+        data_directory: Some(directory.clone()),
+        profile: None,
+    })
+    .unwrap();
+
+    assert_eq!(log::max_level(), log::LevelFilter::Trace);
+    assert!(log::log_enabled!(log::Level::Trace));
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use common::TestApp;
+use serde_json::Value;
+
+use dm_companion_lib::character::{set_damage_modifiers_command, Character};
+use dm_companion_lib::damage::{
+    adjust, Damage, DamageModifier, DamageModifierKind, DamageQualifier, DamageType,
+};
+use dm_companion_lib::repository::{CharacterRepository, SqliteRepository};
+
+fn werewolf_modifiers() -> Vec<DamageModifier> {
+    vec![
+        DamageModifier::new(
+            DamageModifierKind::Immunity,
+            DamageType::Slashing,
+            Some(DamageQualifier::Nonsilvered),
+        ),
+        DamageModifier::new(DamageModifierKind::Resistance, DamageType::Fire, None),
+        DamageModifier::new(DamageModifierKind::Vulnerability, DamageType::Fire, None),
+        DamageModifier::new(DamageModifierKind::Vulnerability, DamageType::Radiant, None),
+    ]
+}
+
+#[test]
+fn damage_is_adjusted_by_modifiers() {
+    let modifiers = werewolf_modifiers();
+
+    let mundane = adjust(&Damage::new(9, DamageType::Slashing), &modifiers);
+    assert_eq!(mundane.applied, 0);
+    assert_eq!(mundane.reasons, vec!["immunity to nonsilvered slashing"]);
+
+    let silvered = Damage {
+        silvered: true,
+        ..Damage::new(9, DamageType::Slashing)
+    };
+    assert_eq!(adjust(&silvered, &modifiers).applied, 9);
+    let magical = Damage {
+        magical: true,
+        ..Damage::new(9, DamageType::Slashing)
+    };
+    assert_eq!(adjust(&magical, &modifiers).applied, 9);
+
+    // Resistance halves first, rounding down, then vulnerability doubles.
+    let fire = adjust(&Damage::new(9, DamageType::Fire), &modifiers);
+    assert_eq!(fire.applied, 8);
+    assert_eq!(fire.reasons.len(), 2);
+
+    assert_eq!(
+        adjust(&Damage::new(5, DamageType::Radiant), &modifiers).applied,
+        10
+    );
+    assert_eq!(
+        adjust(&Damage::new(5, DamageType::Cold), &modifiers).reasons,
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn damage_modifiers_are_stored_with_the_character() {
+    let test_app = TestApp::new();
+    let character = Character::new(
+        String::from("Werewolf"),
+        String::from("Monster"),
+        String::from("Human"),
+        None,
+        3,
+        0,
+        58,
+        12,
+        String::new(),
+    );
+    SqliteRepository::new(&test_app.db_pool())
+        .save_character(&character)
+        .unwrap();
+
+    let stored: Value = serde_json::from_str(
+        &set_damage_modifiers_command(
+            test_app.app.handle().clone(),
+            test_app.database(),
+            character.id.to_string(),
+            werewolf_modifiers(),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(stored["damage_modifiers"][0]["qualifier"], "nonsilvered");
+
+    let repository = SqliteRepository::new(&test_app.db_pool());
+    let mut werewolf = repository.load_character(character.id).unwrap().unwrap();
+    assert_eq!(werewolf.damage_modifiers, werewolf_modifiers());
+
+    let applied = werewolf.take_damage(&[
+        Damage::new(12, DamageType::Slashing),
+        Damage::new(4, DamageType::Radiant),
+    ]);
+    assert_eq!(applied[0].applied, 0);
+    assert_eq!(applied[1].applied, 8);
+    assert_eq!(werewolf.current_hit_points, 50);
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use common::TestApp;
+use dm_companion_lib::character::Character;
+use dm_companion_lib::encounter::{Encounter, EncounterCharacter};
+use rusqlite::Connection;
+
+static STATEMENTS: AtomicUsize = AtomicUsize::new(0);
+
+fn count_statement(_sql: &str) {
+    STATEMENTS.fetch_add(1, Ordering::SeqCst);
+}
+
+fn encounter_with(conn: &mut Connection, participants: usize) -> Encounter {
+    let transaction = conn.transaction().unwrap();
+    let encounter = Encounter::new(format!("Siege with {} zombies", participants));
+    encounter.save(&transaction).unwrap();
+
+    for number in 0..participants {
+        let character = Character::new(
+            format!("Zombie {}", number),
+            String::from("Monster"),
+            String::from("Undead"),
+            None,
+            1,
+            0,
+            22,
+            8,
+            String::new(),
+        );
+        character.save(&transaction).unwrap();
+
+        EncounterCharacter::new(character, encounter.clone())
+            .save(&transaction)
+            .unwrap();
+    }
+    transaction.commit().unwrap();
+
+    encounter
+}
+
+// Returns the participants and the number of statements it took to load them.
+fn load_counting_statements(
+    conn: &mut Connection,
+    encounter: &Encounter,
+) -> (Vec<EncounterCharacter>, usize) {
+    STATEMENTS.store(0, Ordering::SeqCst);
+    conn.trace(Some(count_statement));
+    let participants = EncounterCharacter::load_for_encounter(encounter.clone(), conn).unwrap();
+    conn.trace(None);
+
+    (participants, STATEMENTS.load(Ordering::SeqCst))
+}
+
+#[test]
+fn loads_large_encounter_detail_in_one_pass() {
+    let test_app = TestApp::new();
+    let mut conn = test_app.db_pool().get().unwrap();
+
+    let skirmish = encounter_with(&mut conn, 3);
+    let siege = encounter_with(&mut conn, 500);
+
+    let (skirmish_participants, skirmish_statements) =
+        load_counting_statements(&mut conn, &skirmish);
+    let (siege_participants, siege_statements) = load_counting_statements(&mut conn, &siege);
+
+    assert_eq!(skirmish_participants.len(), 3);
+    assert_eq!(siege_participants.len(), 500);
+    assert!(siege_participants
+        .iter()
+        .all(|participant| participant.encounter.id == siege.id));
+    // One joined query plus one per child table, however many participants there are.
+    assert_eq!(siege_statements, skirmish_statements);
+}
@@ -0,0 +1,254 @@
+mod common;
+
+use std::time::Duration;
+
+use common::TestApp;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use dm_companion_lib::configuration::{DiceMode, HitPointMethod, Preferences};
+use dm_companion_lib::dice::DiceExpression;
+use dm_companion_lib::encounter::EncounterDetail;
+use dm_companion_lib::encounter_template::{EncounterTemplate, TemplateCombatant};
+use dm_companion_lib::repository::{EncounterRepository, SqliteRepository};
+
+fn goblin(template: &EncounterTemplate, level: i32, count: i32) -> TemplateCombatant {
+    TemplateCombatant::new(
+        template.id,
+        String::from("Goblin"),
+        String::from("Monster"),
+        String::from("Goblinoid"),
+        level,
+        7,
+        Some(String::from("2d6")),
+        15,
+        count,
+        String::new(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn parses_dice_expressions() {
+    assert_eq!(
+        DiceExpression::parse("2d6+3").unwrap(),
+        DiceExpression::new(2, 6, 3)
+    );
+    assert_eq!(
+        DiceExpression::parse("d20").unwrap(),
+        DiceExpression::new(1, 20, 0)
+    );
+    assert_eq!(
+        DiceExpression::parse(" 1D8 - 1 ").unwrap(),
+        DiceExpression::new(1, 8, -1)
+    );
+    assert_eq!(
+        DiceExpression::parse("4").unwrap(),
+        DiceExpression::new(0, 0, 4)
+    );
+
+    for invalid in [
+        "",
+        "d0",
+        "goblin",
+        "2d",
+        "101d6",
+        "1d1001",
+        "1d6+99999999999",
+    ] {
+        assert!(
+            DiceExpression::parse(invalid).is_err(),
+            "{} should not parse",
+            invalid
+        );
+    }
+}
+
+#[test]
+fn rolls_stay_within_the_expression() {
+    let expression = DiceExpression::parse("3d6+2").unwrap();
+    assert_eq!(expression.average(), 12);
+    assert_eq!(expression.maximum(), 20);
+
+    for seed in 0..50 {
+        let roll = expression.roll(&mut StdRng::seed_from_u64(seed));
+        assert_eq!(roll.rolls.len(), 3);
+        assert!(roll.rolls.iter().all(|roll| (1..=6).contains(roll)));
+        assert_eq!(
+            roll.total,
+            roll.rolls.iter().map(|roll| *roll as i32).sum::<i32>() + 2
+        );
+    }
+
+    let largest = DiceExpression::parse("100d1000+10000").unwrap();
+    assert_eq!(largest.maximum(), 110_000);
+}
+
+#[test]
+fn rejects_combatant_counts_out_of_range() {
+    let template = EncounterTemplate::new(String::from("Goblin camp"));
+
+    for (level, count) in [(1, 0), (1, 51), (0, 1), (21, 1)] {
+        assert!(TemplateCombatant::new(
+            template.id,
+            String::from("Goblin"),
+            String::from("Monster"),
+            String::from("Goblinoid"),
+            level,
+            7,
+            None,
+            15,
+            count,
+            String::new(),
+        )
+        .is_err());
+    }
+}
+
+#[test]
+fn instantiates_a_template_on_a_single_connection() {
+    let test_app = TestApp::new();
+    let template = EncounterTemplate::new(String::from("Goblin camp"));
+    template.save(&test_app.db_pool()).unwrap();
+    goblin(&template, 1, 3)
+        .save(&test_app.db_pool().get().unwrap())
+        .unwrap();
+    let template = EncounterTemplate::load_by_id(&test_app.db_pool(), template.id)
+        .unwrap()
+        .unwrap();
+
+    // With a single connection any second checkout would time out.
+    let db_path = test_app.configuration().lock().unwrap().db_path.clone();
+    let single_connection = Pool::builder()
+        .max_size(1)
+        .connection_timeout(Duration::from_secs(1))
+        .build(
+            SqliteConnectionManager::file(db_path)
+                .with_init(|conn| conn.execute_batch("PRAGMA foreign_keys = ON;")),
+        )
+        .unwrap();
+    let preferences = Preferences {
+        hit_point_method: HitPointMethod::Maximum,
+        auto_roll_monster_initiative: true,
+        ..Preferences::default()
+    };
+    let encounter = template
+        .instantiate(
+            String::from("Ambush at the ford"),
+            &single_connection,
+            &preferences,
+            &mut StdRng::seed_from_u64(7),
+        )
+        .unwrap();
+
+    let encounter_detail = EncounterDetail::load_by_id(&test_app.db_pool(), encounter.id).unwrap();
+    let mut names: Vec<&str> = encounter_detail
+        .characters
+        .iter()
+        .map(|participant| participant.character.name.as_str())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["Goblin 1", "Goblin 2", "Goblin 3"]);
+    assert!(encounter_detail.characters.iter().all(|participant| {
+        participant.character.hit_points == 12
+            && participant
+                .initiative
+                .is_some_and(|initiative| (1..=20).contains(&initiative))
+    }));
+}
+
+#[test]
+fn lists_templates_with_their_own_combatants() {
+    let test_app = TestApp::new();
+    for (title, count) in [("Goblin camp", 3), ("Wolf den", 2), ("Empty ruin", 0)] {
+        let template = EncounterTemplate::new(String::from(title));
+        template.save(&test_app.db_pool()).unwrap();
+        if count > 0 {
+            goblin(&template, 1, count)
+                .save(&test_app.db_pool().get().unwrap())
+                .unwrap();
+        }
+    }
+
+    let mut templates = EncounterTemplate::load_all_templates(&test_app.db_pool()).unwrap();
+    templates.sort_by(|a, b| a.template_title.cmp(&b.template_title));
+    let counts: Vec<(&str, Vec<i32>)> = templates
+        .iter()
+        .map(|template| {
+            (
+                template.template_title.as_str(),
+                template
+                    .combatants
+                    .iter()
+                    .map(|combatant| combatant.count)
+                    .collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        counts,
+        vec![
+            ("Empty ruin", vec![]),
+            ("Goblin camp", vec![3]),
+            ("Wolf den", vec![2])
+        ]
+    );
+}
+
+#[test]
+fn physical_dice_leave_the_rolling_to_the_table() {
+    let test_app = TestApp::new();
+    let mut template = EncounterTemplate::new(String::from("Goblin camp"));
+    template.combatants = vec![goblin(&template, 1, 2)];
+    let preferences = Preferences {
+        dice_mode: DiceMode::Physical,
+        hit_point_method: HitPointMethod::Roll,
+        auto_roll_monster_initiative: true,
+        ..Preferences::default()
+    };
+
+    let encounter = template
+        .instantiate(
+            String::from("Ambush at the ford"),
+            &test_app.db_pool(),
+            &preferences,
+            &mut StdRng::seed_from_u64(7),
+        )
+        .unwrap();
+
+    let encounter_detail = EncounterDetail::load_by_id(&test_app.db_pool(), encounter.id).unwrap();
+    assert_eq!(encounter_detail.characters.len(), 2);
+    assert!(encounter_detail.characters.iter().all(|participant| {
+        participant.character.hit_points == 7 && participant.initiative.is_none()
+    }));
+}
+
+#[test]
+fn failed_instantiation_leaves_nothing_behind() {
+    let test_app = TestApp::new();
+    let mut template = EncounterTemplate::new(String::from("Goblin camp"));
+    // Skips the constructor's checks so the level only fails at the database.
+    let mut champion = goblin(&template, 1, 1);
+    champion.level = 30;
+    template.combatants = vec![goblin(&template, 1, 2), champion];
+
+    let result = template.instantiate(
+        String::from("Ambush at the ford"),
+        &test_app.db_pool(),
+        &Preferences::default(),
+        &mut StdRng::seed_from_u64(7),
+    );
+    assert!(result.is_err());
+
+    let repository = SqliteRepository::new(&test_app.db_pool());
+    assert!(repository.load_encounters().unwrap().is_empty());
+    let characters: i64 = test_app
+        .db_pool()
+        .get()
+        .unwrap()
+        .query_row("SELECT COUNT(*) FROM characters", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(characters, 0);
+}
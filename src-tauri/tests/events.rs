@@ -0,0 +1,232 @@
+mod common;
+
+use common::TestApp;
+use serde_json::Value;
+use std::sync::mpsc::{self, Receiver};
+use tauri::Listener;
+
+use dm_companion_lib::abilities::Ability;
+use dm_companion_lib::character::{create_character_command, ClassLevel};
+use dm_companion_lib::combat::{area_effect_command, attack_command};
+use dm_companion_lib::damage::DamageType;
+use dm_companion_lib::encounter::{
+    add_character_to_encounter_command, advance_turn_command, create_encounter_command,
+    load_encounter_detail_command, load_encounters_command,
+};
+
+// Collects the named events as (name, payload) in the order they were emitted.
+fn record(test_app: &TestApp, names: &[&'static str]) -> Receiver<(&'static str, Value)> {
+    let (sender, receiver) = mpsc::channel();
+    for &name in names {
+        let sender = sender.clone();
+        test_app.app.handle().listen(name, move |event| {
+            sender
+                .send((name, serde_json::from_str(event.payload()).unwrap()))
+                .unwrap();
+        });
+    }
+
+    receiver
+}
+
+fn create_character(test_app: &TestApp, class_levels: Vec<ClassLevel>) -> Result<Value, String> {
+    let character = create_character_command(
+        test_app.app.handle().clone(),
+        String::from("Lidda"),
+        class_levels,
+        String::from("Halfling"),
+        None,
+        0,
+        18,
+        14,
+        String::new(),
+        None,
+        test_app.database(),
+    )?;
+
+    Ok(serde_json::from_str(&character).unwrap())
+}
+
+#[test]
+fn committed_changes_are_announced_in_order() {
+    let test_app = TestApp::new();
+    let receiver = record(
+        &test_app,
+        &[
+            "character_created",
+            "encounter_created",
+            "encounter_participant_added",
+            "turn_advanced",
+        ],
+    );
+
+    let character = create_character(
+        &test_app,
+        vec![ClassLevel::new(String::from("Rogue"), None, 2)],
+    )
+    .unwrap();
+    create_encounter_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        String::from("Goblin ambush"),
+        None,
+    )
+    .unwrap();
+    let encounters: Vec<Value> =
+        serde_json::from_str(&load_encounters_command(test_app.database()).unwrap()).unwrap();
+    let encounter_id = encounters[0]["id"].as_str().unwrap().to_string();
+    add_character_to_encounter_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        encounter_id.clone(),
+        character["id"].as_str().unwrap().to_string(),
+        Some(12),
+    )
+    .unwrap();
+    advance_turn_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        encounter_id.clone(),
+    )
+    .unwrap();
+
+    let events: Vec<(&str, Value)> = receiver.try_iter().collect();
+    let names: Vec<&str> = events.iter().map(|(name, _)| *name).collect();
+    assert_eq!(
+        names,
+        vec![
+            "character_created",
+            "encounter_created",
+            "encounter_participant_added",
+            "turn_advanced"
+        ]
+    );
+    assert_eq!(events[0].1["character_id"], character["id"]);
+    assert_eq!(events[1].1["encounter_id"], encounter_id.as_str());
+    assert_eq!(events[2].1["encounter_id"], encounter_id.as_str());
+    assert_eq!(events[2].1["character_id"], character["id"]);
+    assert!(events[2].1["participant_id"].is_string());
+    assert_eq!(events[3].1["round"], 1);
+}
+
+#[test]
+fn rejected_changes_stay_quiet() {
+    let test_app = TestApp::new();
+    let receiver = record(&test_app, &["character_created", "encounter_created"]);
+
+    assert!(create_character(&test_app, Vec::new()).is_err());
+    assert!(create_encounter_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        String::from("Goblin ambush"),
+        Some(uuid::Uuid::new_v4().to_string()),
+    )
+    .is_err());
+
+    assert!(receiver.try_recv().is_err());
+}
+
+#[test]
+fn damage_in_combat_updates_the_characters() {
+    let test_app = TestApp::new();
+    create_encounter_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        String::from("Goblin ambush"),
+        None,
+    )
+    .unwrap();
+    let encounters: Vec<Value> =
+        serde_json::from_str(&load_encounters_command(test_app.database()).unwrap()).unwrap();
+    let encounter_id = encounters[0]["id"].as_str().unwrap().to_string();
+    for _ in 0..2 {
+        let character = create_character(
+            &test_app,
+            vec![ClassLevel::new(String::from("Rogue"), None, 2)],
+        )
+        .unwrap();
+        add_character_to_encounter_command(
+            test_app.app.handle().clone(),
+            test_app.database(),
+            encounter_id.clone(),
+            character["id"].as_str().unwrap().to_string(),
+            None,
+        )
+        .unwrap();
+    }
+    let detail: Value = serde_json::from_str(
+        &load_encounter_detail_command(test_app.database(), encounter_id.clone()).unwrap(),
+    )
+    .unwrap();
+    let participant_ids: Vec<String> = detail["characters"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|participant| participant["id"].as_str().unwrap().to_string())
+        .collect();
+    let character_ids: Vec<&Value> = detail["characters"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|participant| &participant["character"]["id"])
+        .collect();
+    let receiver = record(
+        &test_app,
+        &["encounter_participant_updated", "character_updated"],
+    );
+
+    // Nobody makes a DC 30 save, so both take the flat damage.
+    area_effect_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        encounter_id.clone(),
+        participant_ids.clone(),
+        Ability::Dexterity,
+        30,
+        String::from("3"),
+        DamageType::Fire,
+        None,
+        None,
+    )
+    .unwrap();
+    let events: Vec<(&str, Value)> = receiver.try_iter().collect();
+    let names: Vec<&str> = events.iter().map(|(name, _)| *name).collect();
+    assert_eq!(
+        names,
+        vec![
+            "encounter_participant_updated",
+            "character_updated",
+            "encounter_participant_updated",
+            "character_updated"
+        ]
+    );
+    assert_eq!(&events[1].1["character_id"], character_ids[0]);
+    assert_eq!(&events[3].1["character_id"], character_ids[1]);
+
+    // Only a natural 1 misses, keep swinging until something lands.
+    let hit = (0..20).any(|_| {
+        let result: Value = serde_json::from_str(
+            &attack_command(
+                test_app.app.handle().clone(),
+                test_app.database(),
+                encounter_id.clone(),
+                participant_ids[0].clone(),
+                participant_ids[1].clone(),
+                100,
+                String::from("1"),
+                DamageType::Slashing,
+                None,
+                None,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        result["hit"] == true
+    });
+    assert!(hit);
+    let events: Vec<(&str, Value)> = receiver.try_iter().collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].0, "encounter_participant_updated");
+    assert_eq!(events[1].0, "character_updated");
+    assert_eq!(&events[1].1["character_id"], character_ids[1]);
+}
@@ -0,0 +1,229 @@
+mod common;
+
+use common::TestApp;
+
+use serde_json::json;
+
+use dm_companion_lib::character::{load_characters_command, Character};
+use dm_companion_lib::import::{import_characters_command, ImportFormat, ImportReport};
+
+const DND_BEYOND_EXPORT: &str = r#"{
+    "success": true,
+    "data": {
+        "name": "Thorin",
+        "race": { "fullName": "Mountain Dwarf", "baseName": "Dwarf" },
+        "background": { "definition": { "name": "Soldier" } },
+        "classes": [
+            { "level": 2, "isStartingClass": false, "definition": { "name": "Wizard" } },
+            {
+                "level": 3,
+                "isStartingClass": true,
+                "definition": { "name": "Fighter" },
+                "subclassDefinition": { "name": "Champion" }
+            }
+        ],
+        "stats": [{ "id": 2, "value": 14 }, { "id": 3, "value": 16 }],
+        "bonusStats": [{ "id": 3, "value": null }],
+        "overrideStats": [],
+        "baseHitPoints": 30,
+        "bonusHitPoints": null,
+        "overrideHitPoints": null,
+        "removedHitPoints": 5,
+        "temporaryHitPoints": 0,
+        "currentXp": 900,
+        "notes": { "backstory": "Exiled from the mountain.", "allies": null },
+        "traits": { "ideals": "Honour" },
+        "inventory": [{ "definition": { "name": "Longsword" } }],
+        "spells": { "class": [] }
+    }
+}"#;
+
+const FOUNDRY_VTT_EXPORT: &str = r#"{
+    "name": "Lia",
+    "type": "character",
+    "system": {
+        "attributes": {
+            "hp": { "value": 12, "max": 18, "temp": 3 },
+            "ac": { "flat": 15, "calc": "flat" }
+        },
+        "details": {
+            "xp": { "value": 300 },
+            "biography": { "value": "<p>Raised by wolves.</p>" }
+        },
+        "currency": { "gp": 10 }
+    },
+    "items": [
+        { "name": "Ranger", "type": "class", "system": { "identifier": "ranger", "levels": 3 } },
+        { "name": "Wood Elf", "type": "race", "system": {} },
+        { "name": "Shortbow", "type": "weapon", "system": {} }
+    ]
+}"#;
+
+#[test]
+fn dnd_beyond_export_is_mapped_with_unmapped_fields_reported() {
+    let report = ImportReport::parse(DND_BEYOND_EXPORT).unwrap();
+
+    assert_eq!(report.format, ImportFormat::DndBeyond);
+    assert!(report.dry_run);
+
+    let imported = &report.characters[0];
+    assert_eq!(imported.character.name, "Thorin");
+    assert_eq!(imported.character.race, "Mountain Dwarf");
+    assert_eq!(imported.character.class, "Fighter / Wizard");
+    assert_eq!(imported.character.level, 5);
+    assert_eq!(imported.character.background.as_deref(), Some("Soldier"));
+    // 30 base hit points plus a +3 constitution modifier for five levels.
+    assert_eq!(imported.character.hit_points, 45);
+    assert_eq!(imported.character.current_hit_points, 40);
+    assert_eq!(imported.character.armor_class, 12);
+    assert_eq!(imported.character.experience, 900);
+    assert!(imported
+        .character
+        .notes
+        .contains("Exiled from the mountain."));
+    assert!(imported.character.notes.contains("Honour"));
+
+    assert_eq!(imported.unmapped_fields, vec!["inventory", "spells"]);
+}
+
+#[test]
+fn foundry_vtt_export_is_mapped() {
+    let report = ImportReport::parse(FOUNDRY_VTT_EXPORT).unwrap();
+
+    assert_eq!(report.format, ImportFormat::FoundryVtt);
+
+    let imported = &report.characters[0];
+    assert_eq!(imported.character.class, "Ranger");
+    assert_eq!(imported.character.race, "Wood Elf");
+    assert_eq!(imported.character.level, 3);
+    assert_eq!(imported.character.hit_points, 18);
+    assert_eq!(imported.character.current_hit_points, 12);
+    assert_eq!(imported.character.temporary_hit_points, 3);
+    assert_eq!(imported.character.armor_class, 15);
+    assert!(imported
+        .warnings
+        .iter()
+        .any(|warning| warning.contains("Shortbow")));
+    assert_eq!(
+        imported.unmapped_fields,
+        vec!["system.attributes.ac.calc", "system.currency"]
+    );
+}
+
+#[test]
+fn dry_run_does_not_save_characters() {
+    let test_app = TestApp::new();
+    let path = test_app.directory().join("thorin.json");
+    std::fs::write(&path, DND_BEYOND_EXPORT).unwrap();
+    let path = path.to_str().unwrap().to_string();
+
+    import_characters_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        path.clone(),
+        true,
+    )
+    .unwrap();
+    let characters =
+        load_characters_command(test_app.database(), test_app.configuration()).unwrap();
+    assert_eq!(characters, "[]");
+
+    let report = import_characters_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        path,
+        false,
+    )
+    .unwrap();
+    assert!(report.contains("\"dry_run\":false"));
+    let characters =
+        load_characters_command(test_app.database(), test_app.configuration()).unwrap();
+    assert!(characters.contains("Thorin"));
+}
+
+#[test]
+fn dm_companion_export_is_checked_per_character() {
+    let test_app = TestApp::new();
+    let mut elminster = serde_json::to_value(Character::new(
+        String::from("Elminster"),
+        String::from("Wizard"),
+        String::from("Human"),
+        None,
+        5,
+        0,
+        30,
+        12,
+        String::new(),
+    ))
+    .unwrap();
+    // Levels that would overflow when added up and a hit die no class has.
+    elminster["class_levels"] = json!([
+        { "class": "Wizard", "subclass": null, "levels": i32::MAX, "hit_die": 7 },
+        { "class": "Cleric", "subclass": null, "levels": i32::MAX, "hit_die": 8 }
+    ]);
+    let export = json!({
+        "characters": [elminster, { "name": "Halfling with no sheet" }],
+        "encounters": [{ "encounter": { "encounter_title": "Goblin ambush" }, "characters": [] }]
+    });
+
+    let mut report = ImportReport::parse(&export.to_string()).unwrap();
+
+    assert_eq!(report.format, ImportFormat::DmCompanion);
+    assert_eq!(report.characters.len(), 1);
+    let imported = &report.characters[0];
+    assert_eq!(imported.character.level, 20);
+    assert_eq!(imported.character.class, "Wizard");
+    assert_eq!(imported.character.class_levels[0].hit_die, 6);
+    assert_eq!(imported.warnings.len(), 2);
+    assert_eq!(report.skipped.len(), 2);
+    assert_eq!(
+        report.skipped[0],
+        "Encounter Goblin ambush was not imported, only characters are"
+    );
+    assert!(report.skipped[1].contains("Halfling with no sheet"));
+
+    report.save(&mut test_app.db_pool().get().unwrap()).unwrap();
+    let characters =
+        load_characters_command(test_app.database(), test_app.configuration()).unwrap();
+    assert!(characters.contains("Elminster"));
+}
+
+#[test]
+fn dm_companion_export_is_clamped_like_other_formats() {
+    let test_app = TestApp::new();
+    let mut mordenkainen = serde_json::to_value(Character::new(
+        String::from("Mordenkainen"),
+        String::from("Wizard"),
+        String::from("Human"),
+        None,
+        20,
+        0,
+        90,
+        12,
+        String::new(),
+    ))
+    .unwrap();
+    // Edited by hand past what the database accepts.
+    mordenkainen["experience"] = json!(-100);
+    mordenkainen["hit_points"] = json!(-5);
+    mordenkainen["current_hit_points"] = json!(250);
+    mordenkainen["temporary_hit_points"] = json!(-3);
+    mordenkainen["abilities"]["intelligence"] = json!(40);
+    mordenkainen["abilities"]["strength"] = json!(0);
+
+    let mut report = ImportReport::parse(&json!([mordenkainen]).to_string()).unwrap();
+
+    let imported = &report.characters[0].character;
+    assert_eq!(imported.experience, 0);
+    assert_eq!(imported.hit_points, 0);
+    assert_eq!(imported.current_hit_points, 0);
+    assert_eq!(imported.temporary_hit_points, 0);
+    assert_eq!(imported.abilities.intelligence, 30);
+    assert_eq!(imported.abilities.strength, 1);
+    assert_eq!(report.characters[0].warnings.len(), 2);
+
+    report.save(&mut test_app.db_pool().get().unwrap()).unwrap();
+    let characters =
+        load_characters_command(test_app.database(), test_app.configuration()).unwrap();
+    assert!(characters.contains("Mordenkainen"));
+}
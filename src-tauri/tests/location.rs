@@ -0,0 +1,168 @@
+mod common;
+
+use common::TestApp;
+use serde_json::Value;
+
+use dm_companion_lib::encounter::{
+    create_encounter_command, load_encounters_command, set_encounter_location_command,
+};
+use dm_companion_lib::location::{
+    create_location_command, load_location_detail_command, update_location_command, LocationKind,
+};
+use dm_companion_lib::npc::{create_npc_command, update_npc_command, Attitude};
+
+fn create_location(
+    test_app: &TestApp,
+    name: &str,
+    kind: LocationKind,
+    parent: Option<&Value>,
+) -> Result<Value, String> {
+    let location = create_location_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        String::from(name),
+        kind,
+        parent.map(|parent| parent["id"].as_str().unwrap().to_string()),
+        String::new(),
+        String::new(),
+    )?;
+
+    Ok(serde_json::from_str(&location).unwrap())
+}
+
+#[test]
+fn locations_form_a_tree() {
+    let test_app = TestApp::new();
+
+    let sword_coast =
+        create_location(&test_app, "Sword Coast", LocationKind::Region, None).unwrap();
+    let phandalin = create_location(
+        &test_app,
+        "Phandalin",
+        LocationKind::City,
+        Some(&sword_coast),
+    )
+    .unwrap();
+    let inn = create_location(
+        &test_app,
+        "Stonehill Inn",
+        LocationKind::Building,
+        Some(&phandalin),
+    )
+    .unwrap();
+
+    assert!(create_location(&test_app, "Nowhere", LocationKind::Region, Some(&inn)).is_err());
+
+    let detail: Value = serde_json::from_str(
+        &load_location_detail_command(test_app.database(), inn["id"].as_str().unwrap().to_string())
+            .unwrap(),
+    )
+    .unwrap();
+    let path: Vec<&str> = detail["path"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|location| location["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(path, vec!["Sword Coast", "Phandalin"]);
+
+    // Turning the city into a room would leave the inn above its parent.
+    let mut phandalin = phandalin;
+    phandalin["kind"] = Value::from("room");
+    assert!(update_location_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        serde_json::from_value(phandalin).unwrap()
+    )
+    .is_err());
+}
+
+#[test]
+fn location_lists_everything_that_happened_there() {
+    let test_app = TestApp::new();
+
+    let phandalin = create_location(&test_app, "Phandalin", LocationKind::City, None).unwrap();
+    let inn = create_location(
+        &test_app,
+        "Stonehill Inn",
+        LocationKind::Building,
+        Some(&phandalin),
+    )
+    .unwrap();
+    let phandalin_id = phandalin["id"].as_str().unwrap().to_string();
+    let inn_id = inn["id"].as_str().unwrap().to_string();
+
+    create_encounter_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        String::from("Redbrand ruffians"),
+        Some(phandalin_id.clone()),
+    )
+    .unwrap();
+    create_encounter_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        String::from("Goblin ambush"),
+        None,
+    )
+    .unwrap();
+    let encounters: Vec<Value> =
+        serde_json::from_str(&load_encounters_command(test_app.database()).unwrap()).unwrap();
+    let ambush = encounters
+        .iter()
+        .find(|encounter| encounter["encounter_title"] == "Goblin ambush")
+        .unwrap();
+    set_encounter_location_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        ambush["id"].as_str().unwrap().to_string(),
+        Some(inn_id.clone()),
+    )
+    .unwrap();
+
+    let npc: Value = serde_json::from_str(
+        &create_npc_command(
+            test_app.app.handle().clone(),
+            test_app.database(),
+            String::from("Toblen"),
+            String::from("Innkeeper"),
+            None,
+            None,
+            Attitude::Friendly,
+            String::new(),
+            String::new(),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let mut npc = npc;
+    npc["location_id"] = Value::from(inn_id.clone());
+    update_npc_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        serde_json::from_value(npc).unwrap(),
+    )
+    .unwrap();
+
+    let detail: Value = serde_json::from_str(
+        &load_location_detail_command(test_app.database(), phandalin_id).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(detail["children"][0]["name"], "Stonehill Inn");
+    assert_eq!(detail["encounters"].as_array().unwrap().len(), 2);
+    assert_eq!(detail["npcs"][0]["name"], "Toblen");
+
+    let detail: Value =
+        serde_json::from_str(&load_location_detail_command(test_app.database(), inn_id).unwrap())
+            .unwrap();
+    assert_eq!(detail["encounters"].as_array().unwrap().len(), 1);
+    assert_eq!(detail["encounters"][0]["encounter_title"], "Goblin ambush");
+
+    assert!(create_encounter_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        String::from("Lost"),
+        Some(uuid::Uuid::new_v4().to_string()),
+    )
+    .is_err());
+}
@@ -0,0 +1,218 @@
+mod common;
+
+use common::TestApp;
+use serde_json::Value;
+
+use dm_companion_lib::character::Character;
+use dm_companion_lib::notes::{
+    create_note_command, load_note_detail_command, load_notes_command, parse_wikilinks,
+    search_notes_command, update_note_command, AttachmentKind,
+};
+use dm_companion_lib::repository::{CharacterRepository, SqliteRepository};
+
+fn create_note(test_app: &TestApp, title: &str, body: &str, tags: &[&str]) -> Value {
+    let note = create_note_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        String::from(title),
+        String::from(body),
+        tags.iter().map(|tag| String::from(*tag)).collect(),
+        None,
+        None,
+    )
+    .unwrap();
+
+    serde_json::from_str(&note).unwrap()
+}
+
+#[test]
+fn wikilinks_are_parsed_from_markdown() {
+    let links = parse_wikilinks(
+        "Met [[Sildar]] at the [[Stonehill Inn|inn]], see [[sildar#Background]] and [[ ]] or [[unclosed",
+    );
+
+    assert_eq!(links, vec!["Sildar", "Stonehill Inn"]);
+}
+
+#[test]
+fn notes_keep_backlinks_and_are_searchable() {
+    let test_app = TestApp::new();
+
+    let inn = create_note(
+        &test_app,
+        "Stonehill Inn",
+        "The party rests here.",
+        &["Location", "#phandalin"],
+    );
+    let session = create_note(
+        &test_app,
+        "Session 3",
+        "Ambushed on the way to [[stonehill inn]], heard rumours about [[Cragmaw Castle]].",
+        &["session"],
+    );
+    assert_eq!(inn["tags"], serde_json::json!(["location", "phandalin"]));
+
+    let inn_id = inn["id"].as_str().unwrap().to_string();
+    let detail: Value = serde_json::from_str(
+        &load_note_detail_command(test_app.database(), inn_id.clone()).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(detail["backlinks"].as_array().unwrap().len(), 1);
+    assert_eq!(detail["backlinks"][0]["title"], "Session 3");
+
+    let session_id = session["id"].as_str().unwrap().to_string();
+    let detail: Value = serde_json::from_str(
+        &load_note_detail_command(test_app.database(), session_id.clone()).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(detail["links"][0]["note_id"], inn_id.as_str());
+    assert_eq!(detail["links"][1]["title"], "Cragmaw Castle");
+    assert!(detail["links"][1]["note_id"].is_null());
+
+    let found: Vec<Value> = serde_json::from_str(
+        &search_notes_command(test_app.database(), String::from("ambush")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0]["title"], "Session 3");
+
+    update_note_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        session_id,
+        String::from("Session 3"),
+        String::from("Reached the castle."),
+        vec![String::from("session")],
+    )
+    .unwrap();
+    let detail: Value =
+        serde_json::from_str(&load_note_detail_command(test_app.database(), inn_id).unwrap())
+            .unwrap();
+    assert!(detail["backlinks"].as_array().unwrap().is_empty());
+    let found: Vec<Value> = serde_json::from_str(
+        &search_notes_command(test_app.database(), String::from("ambush")).unwrap(),
+    )
+    .unwrap();
+    assert!(found.is_empty());
+
+    assert!(create_note_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        String::from("stonehill inn"),
+        String::new(),
+        Vec::new(),
+        None,
+        None,
+    )
+    .is_err());
+}
+
+#[test]
+fn notes_are_attached_and_filtered() {
+    let test_app = TestApp::new();
+
+    let character = Character::new(
+        String::from("Sildar"),
+        String::from("Fighter"),
+        String::from("Human"),
+        None,
+        5,
+        0,
+        40,
+        16,
+        String::new(),
+    );
+    SqliteRepository::new(&test_app.db_pool())
+        .save_character(&character)
+        .unwrap();
+
+    create_note_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        String::from("Sildar's debts"),
+        String::from("Owes the Lords' Alliance."),
+        vec![String::from("secret")],
+        Some(AttachmentKind::Character),
+        Some(character.id.to_string()),
+    )
+    .unwrap();
+    create_note(&test_app, "Phandalin", "A frontier town.", &["secret"]);
+
+    let notes: Vec<Value> = serde_json::from_str(
+        &load_notes_command(
+            test_app.database(),
+            None,
+            Some(AttachmentKind::Character),
+            Some(character.id.to_string()),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0]["attachment"]["kind"], "character");
+
+    let notes: Vec<Value> = serde_json::from_str(
+        &load_notes_command(
+            test_app.database(),
+            Some(String::from("Secret")),
+            None,
+            None,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(notes.len(), 2);
+
+    assert!(create_note_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        String::from("Orphan"),
+        String::new(),
+        Vec::new(),
+        Some(AttachmentKind::Encounter),
+        Some(uuid::Uuid::new_v4().to_string()),
+    )
+    .is_err());
+}
+
+#[test]
+fn search_follows_note_ids_through_a_table_rebuild() {
+    let test_app = TestApp::new();
+    let first = create_note(&test_app, "Session 1", "Met Gundren.", &["session"]);
+    create_note(
+        &test_app,
+        "Session 2",
+        "Ambushed by goblins.",
+        &["session", "combat"],
+    );
+    create_note(
+        &test_app,
+        "Tresendar Manor",
+        "Redbrand hideout.",
+        &["location"],
+    );
+
+    // Copying rows into a new table, as migrations do, hands out fresh rowids.
+    let conn = test_app.db_pool().get().unwrap();
+    conn.execute(
+        "DELETE FROM notes WHERE id = ?1",
+        [first["id"].as_str().unwrap()],
+    )
+    .unwrap();
+    conn.execute_batch(
+        "PRAGMA foreign_keys = OFF;
+        PRAGMA legacy_alter_table = ON;
+        CREATE TABLE notes_rebuilt AS SELECT * FROM notes;
+        DROP TABLE notes;
+        ALTER TABLE notes_rebuilt RENAME TO notes;",
+    )
+    .unwrap();
+
+    let found: Vec<Value> = serde_json::from_str(
+        &search_notes_command(test_app.database(), String::from("ambush")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0]["title"], "Session 2");
+    assert_eq!(found[0]["tags"], serde_json::json!(["combat", "session"]));
+}
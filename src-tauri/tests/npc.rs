@@ -0,0 +1,114 @@
+mod common;
+
+use common::TestApp;
+use serde_json::Value;
+
+use dm_companion_lib::character::Character;
+use dm_companion_lib::npc::{
+    create_npc_command, create_relationship_command, load_npcs_command, load_relationships_command,
+    Attitude, EntityKind, RelationshipKind,
+};
+use dm_companion_lib::repository::{CharacterRepository, SqliteRepository};
+
+fn create_npc(test_app: &TestApp, name: &str, faction: Option<&str>) -> Value {
+    let npc = create_npc_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        String::from(name),
+        String::from("Innkeeper"),
+        Some(String::from("Phandalin")),
+        faction.map(String::from),
+        Attitude::Friendly,
+        String::from("Works for the Black Spider"),
+        String::new(),
+    )
+    .unwrap();
+
+    serde_json::from_str(&npc).unwrap()
+}
+
+#[test]
+fn npcs_are_filtered_by_faction() {
+    let test_app = TestApp::new();
+
+    create_npc(&test_app, "Toblen", None);
+    create_npc(&test_app, "Iarno", Some("Redbrands"));
+
+    let npcs: Vec<Value> = serde_json::from_str(
+        &load_npcs_command(test_app.database(), Some(String::from("Redbrands"))).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(npcs.len(), 1);
+    assert_eq!(npcs[0]["name"], "Iarno");
+    assert_eq!(npcs[0]["attitude"], "friendly");
+
+    let npcs: Vec<Value> =
+        serde_json::from_str(&load_npcs_command(test_app.database(), None).unwrap()).unwrap();
+    assert_eq!(npcs.len(), 2);
+}
+
+#[test]
+fn relationships_link_characters_and_npcs() {
+    let test_app = TestApp::new();
+
+    let character = Character::new(
+        String::from("Sildar"),
+        String::from("Fighter"),
+        String::from("Human"),
+        None,
+        5,
+        0,
+        40,
+        16,
+        String::new(),
+    );
+    SqliteRepository::new(&test_app.db_pool())
+        .save_character(&character)
+        .unwrap();
+    let npc = create_npc(&test_app, "Iarno", Some("Redbrands"));
+    let npc_id = npc["id"].as_str().unwrap().to_string();
+
+    create_relationship_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        EntityKind::Character,
+        character.id.to_string(),
+        EntityKind::Npc,
+        npc_id.clone(),
+        RelationshipKind::Rival,
+        String::from("Iarno betrayed him"),
+    )
+    .unwrap();
+
+    let relationships: Vec<Value> = serde_json::from_str(
+        &load_relationships_command(test_app.database(), EntityKind::Npc, npc_id.clone()).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(relationships.len(), 1);
+    assert_eq!(relationships[0]["kind"], "rival");
+    assert_eq!(relationships[0]["source"]["name"], "Sildar");
+    assert_eq!(relationships[0]["target"]["name"], "Iarno");
+
+    assert!(create_relationship_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        EntityKind::Npc,
+        npc_id.clone(),
+        EntityKind::Npc,
+        npc_id.clone(),
+        RelationshipKind::Ally,
+        String::new(),
+    )
+    .is_err());
+    assert!(create_relationship_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        EntityKind::Npc,
+        npc_id,
+        EntityKind::Character,
+        uuid::Uuid::new_v4().to_string(),
+        RelationshipKind::Family,
+        String::new(),
+    )
+    .is_err());
+}
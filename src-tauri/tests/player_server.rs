@@ -0,0 +1,223 @@
+mod common;
+
+use common::TestApp;
+use serde_json::Value;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::Message;
+
+use dm_companion_lib::configuration::{Configuration, PlayerServerSettings};
+use dm_companion_lib::player_server::{update_player_server_command, PlayerServer};
+use dm_companion_lib::player_view::{HealthDescriptor, PlayerParticipant, PlayerView};
+
+fn local_settings() -> PlayerServerSettings {
+    PlayerServerSettings {
+        enabled: true,
+        bind_address: String::from("127.0.0.1"),
+        port: 0,
+    }
+}
+
+fn sample_view(round: i32) -> Option<PlayerView> {
+    Some(PlayerView {
+        encounter_id: uuid::Uuid::new_v4(),
+        encounter_title: String::from("Goblin ambush"),
+        round,
+        participants: vec![PlayerParticipant {
+            id: uuid::Uuid::new_v4(),
+            name: String::from("Goblin boss"),
+            initiative: Some(15),
+            conditions: vec![String::from("prone")],
+            health: HealthDescriptor::Bloodied,
+            active: true,
+        }],
+    })
+}
+
+fn get(server: &PlayerServer, path: &str) -> String {
+    let mut stream = TcpStream::connect(server.address()).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn the_player_view_is_served_and_streamed() {
+    let server = PlayerServer::start(&local_settings(), &sample_view(1)).unwrap();
+
+    assert!(get(&server, "/").contains("<html"));
+    assert!(get(&server, "/secrets").starts_with("HTTP/1.1 404"));
+    let state = get(&server, "/state");
+    let body: Value = serde_json::from_str(state.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+    assert_eq!(body["participants"][0]["health"], "bloodied");
+
+    let (mut socket, _) = tungstenite::connect(format!("ws://{}/ws", server.address())).unwrap();
+    let initial: Value = match socket.read().unwrap() {
+        Message::Text(text) => serde_json::from_str(&text).unwrap(),
+        message => panic!("unexpected message {:?}", message),
+    };
+    assert_eq!(initial["round"], 1);
+
+    server.broadcast(&sample_view(2));
+    let updated: Value = match socket.read().unwrap() {
+        Message::Text(text) => serde_json::from_str(&text).unwrap(),
+        message => panic!("unexpected message {:?}", message),
+    };
+    assert_eq!(updated["round"], 2);
+
+    server.stop();
+    assert!(TcpStream::connect(server.address()).is_err());
+}
+
+#[test]
+fn the_player_server_is_off_until_enabled() {
+    assert!(!Configuration::default().player_server.enabled);
+
+    let test_app = TestApp::new();
+    assert!(test_app.player_server().address().is_none());
+
+    let status: Value = serde_json::from_str(
+        &update_player_server_command(
+            test_app.configuration(),
+            test_app.database(),
+            test_app.player_screen(),
+            test_app.player_server(),
+            local_settings(),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let address = test_app.player_server().address().unwrap();
+    assert_eq!(status["address"], address.to_string());
+    assert!(
+        test_app
+            .configuration()
+            .lock()
+            .unwrap()
+            .player_server
+            .enabled
+    );
+    let state = {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET /state HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+    assert!(state.ends_with("null"));
+
+    update_player_server_command(
+        test_app.configuration(),
+        test_app.database(),
+        test_app.player_screen(),
+        test_app.player_server(),
+        PlayerServerSettings {
+            enabled: false,
+            ..local_settings()
+        },
+    )
+    .unwrap();
+    assert!(test_app.player_server().address().is_none());
+    assert!(TcpStream::connect(address).is_err());
+}
+
+#[test]
+fn a_port_that_cannot_be_bound_keeps_the_previous_server() {
+    let test_app = TestApp::new();
+    let update = |settings: PlayerServerSettings| {
+        update_player_server_command(
+            test_app.configuration(),
+            test_app.database(),
+            test_app.player_screen(),
+            test_app.player_server(),
+            settings,
+        )
+    };
+    update(local_settings()).unwrap();
+    let address = test_app.player_server().address().unwrap();
+    let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+
+    let result = update(PlayerServerSettings {
+        port: taken.local_addr().unwrap().port(),
+        ..local_settings()
+    });
+
+    assert!(result.is_err());
+    let restarted = test_app.player_server().address().unwrap();
+    assert_eq!(restarted.ip(), address.ip());
+    assert!(TcpStream::connect(restarted).is_ok());
+    let saved = test_app
+        .configuration()
+        .lock()
+        .unwrap()
+        .player_server
+        .clone();
+    assert!(saved.enabled);
+    assert_eq!(saved.port, 0);
+}
+
+fn read_text(socket: &mut tungstenite::WebSocket<MaybeTlsStream<TcpStream>>) -> Value {
+    match socket.read().unwrap() {
+        Message::Text(text) => serde_json::from_str(&text).unwrap(),
+        message => panic!("unexpected message {:?}", message),
+    }
+}
+
+#[test]
+fn players_are_answered_and_can_leave() {
+    let server = PlayerServer::start(&local_settings(), &sample_view(1)).unwrap();
+    let (mut socket, _) = tungstenite::connect(format!("ws://{}/ws", server.address())).unwrap();
+    assert_eq!(read_text(&mut socket)["round"], 1);
+
+    socket.send(Message::Ping(b"still there".to_vec())).unwrap();
+    assert_eq!(
+        socket.read().unwrap(),
+        Message::Pong(b"still there".to_vec())
+    );
+
+    socket.close(None).unwrap();
+    loop {
+        match socket.read() {
+            Ok(_) => {}
+            Err(tungstenite::Error::ConnectionClosed) => break,
+            Err(e) => panic!("unexpected error {:?}", e),
+        }
+    }
+
+    // The closed client is dropped on the next broadcast while the others keep receiving.
+    let (mut other, _) = tungstenite::connect(format!("ws://{}/ws", server.address())).unwrap();
+    assert_eq!(read_text(&mut other)["round"], 1);
+    server.broadcast(&sample_view(2));
+    assert_eq!(read_text(&mut other)["round"], 2);
+}
+
+#[test]
+fn connections_are_capped() {
+    let server = PlayerServer::start(&local_settings(), &sample_view(1)).unwrap();
+    let url = format!("ws://{}/ws", server.address());
+    let mut sockets: Vec<_> = (0..32)
+        .map(|_| {
+            let (mut socket, _) = tungstenite::connect(&url).unwrap();
+            read_text(&mut socket);
+            socket
+        })
+        .collect();
+
+    assert!(tungstenite::connect(&url).is_err());
+
+    sockets.pop().unwrap().close(None).unwrap();
+    // The freed place is given back once the client's thread notices the close.
+    let started = std::time::Instant::now();
+    let (mut socket, _) = loop {
+        match tungstenite::connect(&url) {
+            Ok(connected) => break connected,
+            Err(_) if started.elapsed() < std::time::Duration::from_secs(5) => {
+                std::thread::sleep(std::time::Duration::from_millis(50))
+            }
+            Err(e) => panic!("no place was freed: {:?}", e),
+        }
+    };
+    assert_eq!(read_text(&mut socket)["round"], 1);
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use common::TestApp;
+use serde_json::Value;
+use std::sync::mpsc;
+use tauri::Listener;
+
+use dm_companion_lib::character::Character;
+use dm_companion_lib::encounter::{
+    add_character_to_encounter, advance_turn_command, Encounter, EncounterDetail,
+};
+use dm_companion_lib::player_view::{
+    show_encounter_to_players_command, HealthDescriptor, PlayerView, PLAYER_VIEW_UPDATED,
+};
+use dm_companion_lib::repository::{CharacterRepository, EncounterRepository, SqliteRepository};
+
+fn sample_character(name: &str, hit_points: i32, current_hit_points: i32) -> Character {
+    let mut character = Character::new(
+        String::from(name),
+        String::from("Warrior"),
+        String::from("Goblin"),
+        None,
+        1,
+        0,
+        hit_points,
+        15,
+        String::from("Carries the key to the cellar"),
+    );
+    character.current_hit_points = current_hit_points;
+    character
+}
+
+// Returns the encounter with a bloodied goblin acting first and an untouched one second.
+fn goblin_ambush(test_app: &TestApp) -> Encounter {
+    let db_pool = test_app.db_pool();
+    let repository = SqliteRepository::new(&db_pool);
+    let encounter = Encounter::new(String::from("Goblin ambush"));
+    repository.save_encounter(&encounter).unwrap();
+
+    for (character, initiative) in [
+        (sample_character("Goblin boss", 21, 10), 15),
+        (sample_character("Goblin", 7, 7), 12),
+    ] {
+        repository.save_character(&character).unwrap();
+        add_character_to_encounter(&repository, encounter.id, character.id, Some(initiative))
+            .unwrap();
+    }
+
+    encounter
+}
+
+#[test]
+fn health_is_described_instead_of_shown() {
+    assert_eq!(
+        HealthDescriptor::of(&sample_character("Goblin", 7, 7)),
+        HealthDescriptor::Healthy
+    );
+    assert_eq!(
+        HealthDescriptor::of(&sample_character("Goblin", 7, 4)),
+        HealthDescriptor::Wounded
+    );
+    assert_eq!(
+        HealthDescriptor::of(&sample_character("Goblin", 8, 4)),
+        HealthDescriptor::Bloodied
+    );
+    assert_eq!(
+        HealthDescriptor::of(&sample_character("Goblin", 7, 0)),
+        HealthDescriptor::Down
+    );
+
+    let test_app = TestApp::new();
+    let encounter = goblin_ambush(&test_app);
+    let encounter_detail = EncounterDetail::load_by_id(&test_app.db_pool(), encounter.id).unwrap();
+    let player_view = serde_json::to_value(PlayerView::from_detail(&encounter_detail)).unwrap();
+
+    assert_eq!(player_view["participants"][0]["name"], "Goblin boss");
+    assert_eq!(player_view["participants"][0]["health"], "bloodied");
+    let serialized = player_view.to_string();
+    assert!(!serialized.contains("hit_points"));
+    assert!(!serialized.contains("armor_class"));
+    assert!(!serialized.contains("cellar"));
+}
+
+#[test]
+fn the_shown_encounter_is_sent_out_after_every_change() {
+    let test_app = TestApp::new();
+    let encounter = goblin_ambush(&test_app);
+    let (sender, receiver) = mpsc::channel();
+    test_app
+        .app
+        .handle()
+        .listen(PLAYER_VIEW_UPDATED, move |event| {
+            sender.send(event.payload().to_string()).unwrap();
+        });
+
+    // Changes to an encounter nobody is looking at stay quiet.
+    advance_turn_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        encounter.id.to_string(),
+    )
+    .unwrap();
+    assert!(receiver.try_recv().is_err());
+
+    show_encounter_to_players_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        test_app.player_screen(),
+        Some(encounter.id.to_string()),
+    )
+    .unwrap();
+    let shown: Value = serde_json::from_str(&receiver.try_recv().unwrap()).unwrap();
+    assert_eq!(shown["participants"][0]["active"], true);
+
+    advance_turn_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        encounter.id.to_string(),
+    )
+    .unwrap();
+    let advanced: Value = serde_json::from_str(&receiver.try_recv().unwrap()).unwrap();
+    assert_eq!(advanced["round"], 1);
+    assert_eq!(advanced["participants"][0]["active"], false);
+    assert_eq!(advanced["participants"][1]["active"], true);
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use common::TestApp;
+use serde_json::Value;
+
+use dm_companion_lib::character::Character;
+use dm_companion_lib::encounter::{create_encounter_command, load_encounters_command};
+use dm_companion_lib::quest::{
+    add_objective_command, create_quest_command, link_encounter_to_quest_command,
+    load_quests_command, set_objective_completed_command, set_quest_status_command, QuestStatus,
+};
+use dm_companion_lib::repository::{CharacterRepository, SqliteRepository};
+
+fn load_quests(test_app: &TestApp, status: Option<QuestStatus>) -> Vec<Value> {
+    serde_json::from_str(&load_quests_command(test_app.database(), status).unwrap()).unwrap()
+}
+
+#[test]
+fn quests_track_objectives_and_status() {
+    let test_app = TestApp::new();
+
+    let giver = Character::new(
+        String::from("Gundren"),
+        String::from("Fighter"),
+        String::from("Dwarf"),
+        None,
+        3,
+        0,
+        25,
+        14,
+        String::new(),
+    );
+    SqliteRepository::new(&test_app.db_pool())
+        .save_character(&giver)
+        .unwrap();
+
+    let quest: Value = serde_json::from_str(
+        &create_quest_command(
+            test_app.app.handle().clone(),
+            test_app.database(),
+            String::from("Find Cragmaw Castle"),
+            String::new(),
+            String::from("500 gp"),
+            Some(giver.id.to_string()),
+            vec![String::from("Question the goblins")],
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    create_quest_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        String::from("Clear out the Redbrands"),
+        String::new(),
+        String::new(),
+        None,
+        Vec::new(),
+    )
+    .unwrap();
+    assert_eq!(quest["status"], "rumored");
+    assert_eq!(quest["giver_name"], "Gundren");
+    let quest_id = quest["id"].as_str().unwrap().to_string();
+
+    let quest: Value = serde_json::from_str(
+        &add_objective_command(
+            test_app.app.handle().clone(),
+            test_app.database(),
+            quest_id.clone(),
+            String::from("Rescue Gundren"),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    set_objective_completed_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        quest_id.clone(),
+        quest["objectives"][0]["id"].as_str().unwrap().to_string(),
+        true,
+    )
+    .unwrap();
+    set_quest_status_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        quest_id.clone(),
+        QuestStatus::Active,
+    )
+    .unwrap();
+
+    create_encounter_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        String::from("Cragmaw hideout"),
+        None,
+    )
+    .unwrap();
+    let encounters: Vec<Value> =
+        serde_json::from_str(&load_encounters_command(test_app.database()).unwrap()).unwrap();
+    link_encounter_to_quest_command(
+        test_app.app.handle().clone(),
+        test_app.database(),
+        quest_id,
+        encounters[0]["id"].as_str().unwrap().to_string(),
+    )
+    .unwrap();
+
+    let active = load_quests(&test_app, Some(QuestStatus::Active));
+    assert_eq!(active.len(), 1);
+    assert_eq!(active[0]["title"], "Find Cragmaw Castle");
+    assert_eq!(active[0]["objectives"][0]["completed"], true);
+    assert_eq!(active[0]["objectives"][1]["description"], "Rescue Gundren");
+    assert_eq!(active[0]["objectives"][1]["completed"], false);
+    assert_eq!(active[0]["encounter_ids"][0], encounters[0]["id"]);
+
+    assert_eq!(load_quests(&test_app, Some(QuestStatus::Rumored)).len(), 1);
+    assert_eq!(load_quests(&test_app, None).len(), 2);
+}
@@ -0,0 +1,315 @@
+mod common;
+
+use common::TestApp;
+use dm_companion_lib::character::{
+    Character, CharacterQuery, CharacterSort, ClassLevel, SortDirection,
+};
+use dm_companion_lib::encounter::{
+    add_character_to_encounter, advance_turn, Encounter, EncounterDetail,
+};
+use dm_companion_lib::repository::{
+    CharacterRepository, InMemoryRepository, SqliteRepository, UnitOfWork,
+};
+
+fn sample_character(name: &str) -> Character {
+    Character::new(
+        String::from(name),
+        String::from("Fighter"),
+        String::from("Human"),
+        None,
+        3,
+        900,
+        28,
+        16,
+        String::new(),
+    )
+}
+
+fn class_level_with_hit_die(hit_die: u32) -> ClassLevel {
+    let mut class_level = ClassLevel::new(String::from("Fighter"), None, 3);
+    class_level.hit_die = hit_die;
+
+    class_level
+}
+
+fn exercise_repository<R: UnitOfWork>(repository: &R) {
+    let character = sample_character("Tordek");
+    repository.save_character(&character).unwrap();
+
+    let encounter = Encounter::new(String::from("Goblin ambush"));
+    repository.save_encounter(&encounter).unwrap();
+
+    add_character_to_encounter(repository, encounter.id, character.id, Some(14)).unwrap();
+    assert!(
+        add_character_to_encounter(repository, encounter.id, uuid::Uuid::new_v4(), None).is_err()
+    );
+
+    let mut wounded = repository.load_character(character.id).unwrap().unwrap();
+    wounded.current_hit_points = 11;
+    repository.update_character(&wounded).unwrap();
+
+    let encounter_detail = repository
+        .load_encounter_detail(encounter.id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(encounter_detail.characters.len(), 1);
+    assert_eq!(
+        encounter_detail.characters[0].character.current_hit_points,
+        11
+    );
+
+    assert!(repository
+        .load_encounter_detail(uuid::Uuid::new_v4())
+        .unwrap()
+        .is_none());
+}
+
+fn exercise_turns<R: UnitOfWork>(repository: &R) {
+    let encounter = Encounter::new(String::from("Goblin ambush"));
+    repository.save_encounter(&encounter).unwrap();
+    let join = |name: &str, initiative: Option<i32>| {
+        let character = sample_character(name);
+        repository.save_character(&character).unwrap();
+        add_character_to_encounter(repository, encounter.id, character.id, initiative).unwrap()
+    };
+    let active = |encounter_detail: &EncounterDetail| {
+        encounter_detail
+            .active_participant()
+            .map(|participant| participant.character.name.clone())
+    };
+
+    join("Tordek", Some(18));
+    join("Sildar", Some(12));
+    join("Goblin", Some(5));
+    advance_turn(repository, encounter.id).unwrap();
+    let encounter_detail = advance_turn(repository, encounter.id).unwrap();
+    assert_eq!(active(&encounter_detail).as_deref(), Some("Sildar"));
+
+    // Both newcomers sort before Sildar, who keeps the turn.
+    join("Goblin Boss", Some(20));
+    join("Goblin Archer", Some(12));
+    let encounter_detail = repository
+        .load_encounter_detail(encounter.id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(encounter_detail.encounter.turn_index, 3);
+    assert_eq!(active(&encounter_detail).as_deref(), Some("Sildar"));
+
+    let encounter_detail = advance_turn(repository, encounter.id).unwrap();
+    assert_eq!(active(&encounter_detail).as_deref(), Some("Goblin"));
+}
+
+fn exercise_character_queries<R: CharacterRepository>(repository: &R) {
+    for (name, race, level, notes) in [
+        (
+            "Goblin Boss",
+            "Goblin",
+            4,
+            "Leads the ambush at the old bridge",
+        ),
+        ("Goblin Archer", "Goblin", 1, "Hides in the trees"),
+        ("Sildar", "Human", 5, "Captured near the bridge"),
+    ] {
+        let mut character = sample_character(name);
+        character.race = String::from(race);
+        character.set_class_levels(vec![ClassLevel::new(String::from("Fighter"), None, level)]);
+        character.notes = String::from(notes);
+        character.campaign = Some(String::from("Lost Mine"));
+        repository.save_character(&character).unwrap();
+    }
+    let mut outsider = sample_character("Strahd");
+    outsider.campaign = Some(String::from("Curse of Strahd"));
+    repository.save_character(&outsider).unwrap();
+
+    let page = repository
+        .query_characters(&CharacterQuery {
+            search: Some(String::from("bridge")),
+            ..CharacterQuery::default()
+        })
+        .unwrap();
+    assert_eq!(page.total, 2);
+    assert_eq!(page.characters[0].name, "Goblin Boss");
+
+    let page = repository
+        .query_characters(&CharacterQuery {
+            search: Some(String::from("gob")),
+            race: Some(String::from("goblin")),
+            min_level: Some(2),
+            ..CharacterQuery::default()
+        })
+        .unwrap();
+    assert_eq!(page.total, 1);
+    assert_eq!(page.characters[0].name, "Goblin Boss");
+
+    let page = repository
+        .query_characters(&CharacterQuery {
+            campaign: Some(String::from("Lost Mine")),
+            class: Some(String::from("fighter")),
+            sort_by: CharacterSort::Level,
+            sort_direction: SortDirection::Descending,
+            page: 2,
+            page_size: 2,
+            ..CharacterQuery::default()
+        })
+        .unwrap();
+    assert_eq!(page.total, 3);
+    assert_eq!(page.characters.len(), 1);
+    assert_eq!(page.characters[0].name, "Goblin Archer");
+
+    let page = repository
+        .query_characters(&CharacterQuery {
+            page: u32::MAX,
+            page_size: 500,
+            ..CharacterQuery::default()
+        })
+        .unwrap();
+    assert_eq!(page.total, 4);
+    assert!(page.characters.is_empty());
+}
+
+fn exercise_unit_of_work<R: UnitOfWork>(repository: &R) {
+    let kept = sample_character("Tordek");
+    let discarded = sample_character("Sildar");
+
+    repository
+        .in_transaction(|repository| repository.save_character(&kept))
+        .unwrap();
+    let failed: Result<(), String> = repository.in_transaction(|repository| {
+        repository.save_character(&discarded)?;
+        Err(String::from("Encounter not found"))
+    });
+
+    assert!(failed.is_err());
+    assert!(repository.load_character(kept.id).unwrap().is_some());
+    assert!(repository.load_character(discarded.id).unwrap().is_none());
+}
+
+#[test]
+fn in_memory_repository_rolls_back_failed_work() {
+    exercise_unit_of_work(&InMemoryRepository::new());
+}
+
+#[test]
+fn sqlite_repository_rolls_back_failed_work() {
+    let test_app = TestApp::new();
+
+    exercise_unit_of_work(&SqliteRepository::new(&test_app.db_pool()));
+}
+
+#[test]
+fn in_memory_repository_round_trips_encounters() {
+    exercise_repository(&InMemoryRepository::new());
+}
+
+#[test]
+fn sqlite_repository_round_trips_encounters() {
+    let test_app = TestApp::new();
+
+    exercise_repository(&SqliteRepository::new(&test_app.db_pool()));
+}
+
+#[test]
+fn in_memory_repository_queries_characters() {
+    exercise_character_queries(&InMemoryRepository::new());
+}
+
+#[test]
+fn sqlite_repository_queries_characters() {
+    let test_app = TestApp::new();
+
+    exercise_character_queries(&SqliteRepository::new(&test_app.db_pool()));
+}
+
+#[test]
+fn in_memory_repository_keeps_the_turn_when_participants_join() {
+    exercise_turns(&InMemoryRepository::new());
+}
+
+#[test]
+fn sqlite_repository_keeps_the_turn_when_participants_join() {
+    let test_app = TestApp::new();
+
+    exercise_turns(&SqliteRepository::new(&test_app.db_pool()));
+}
+
+#[test]
+fn sqlite_repository_loads_child_rows_past_a_chunk() {
+    let test_app = TestApp::new();
+    let repository = SqliteRepository::new(&test_app.db_pool());
+    repository
+        .in_transaction(|repository| {
+            for index in 0..501 {
+                let mut character = sample_character(&format!("Goblin {}", index));
+                character.set_class_levels(vec![
+                    ClassLevel::new(String::from("Fighter"), None, 2),
+                    ClassLevel::new(String::from("Rogue"), None, 1),
+                ]);
+                repository.save_character(&character)?;
+            }
+
+            Ok(())
+        })
+        .unwrap();
+
+    let characters = repository.load_characters().unwrap();
+    assert_eq!(characters.len(), 501);
+    assert!(characters
+        .iter()
+        .all(|character| character.class == "Fighter / Rogue" && character.level == 3));
+}
+
+#[test]
+fn sqlite_repository_saves_characters_whole_or_not_at_all() {
+    let test_app = TestApp::new();
+    let repository = SqliteRepository::new(&test_app.db_pool());
+    let mut character = sample_character("Tordek");
+    repository.save_character(&character).unwrap();
+
+    // The class row is written after the character row and fails its hit die check.
+    character.set_class_levels(vec![class_level_with_hit_die(7)]);
+    character.current_hit_points = 11;
+    assert!(repository.update_character(&character).is_err());
+    let stored = repository.load_character(character.id).unwrap().unwrap();
+    assert_eq!(stored.current_hit_points, 28);
+    assert_eq!(stored.class_levels[0].hit_die, 10);
+
+    let mut newcomer = sample_character("Mialee");
+    newcomer.set_class_levels(vec![class_level_with_hit_die(7)]);
+    assert!(repository.save_character(&newcomer).is_err());
+    assert!(repository.load_character(newcomer.id).unwrap().is_none());
+}
+
+#[test]
+fn sqlite_search_survives_a_table_rebuild() {
+    let test_app = TestApp::new();
+    let repository = SqliteRepository::new(&test_app.db_pool());
+    let first = sample_character("Gundren");
+    repository.save_character(&first).unwrap();
+    let mut sildar = sample_character("Sildar");
+    sildar.notes = String::from("Captured near the bridge");
+    repository.save_character(&sildar).unwrap();
+
+    // Copying rows into a new table, as migrations do, hands out fresh rowids.
+    let conn = test_app.db_pool().get().unwrap();
+    conn.execute(
+        "DELETE FROM characters WHERE id = ?1",
+        [first.id.to_string()],
+    )
+    .unwrap();
+    conn.execute_batch(
+        "PRAGMA foreign_keys = OFF;
+        CREATE TABLE characters_rebuilt AS SELECT * FROM characters;
+        DROP TABLE characters;
+        ALTER TABLE characters_rebuilt RENAME TO characters;",
+    )
+    .unwrap();
+
+    let page = repository
+        .query_characters(&CharacterQuery {
+            search: Some(String::from("bridge")),
+            ..CharacterQuery::default()
+        })
+        .unwrap();
+    assert_eq!(page.total, 1);
+    assert_eq!(page.characters[0].name, "Sildar");
+}
@@ -0,0 +1,240 @@
+mod common;
+
+use common::TestApp;
+use rusqlite::{params, Connection};
+use serde_json::Value;
+
+use dm_companion_lib::character::Character;
+use dm_companion_lib::configuration::{
+    Configuration, ConfigurationOverrides, JournalMode, Synchronous,
+};
+use dm_companion_lib::encounter::{add_character_to_encounter, Encounter};
+use dm_companion_lib::repository::{CharacterRepository, EncounterRepository, SqliteRepository};
+use dm_companion_lib::storage::{self, check_database_integrity_command};
+
+fn count(conn: &Connection, table: &str) -> i64 {
+    conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+        row.get(0)
+    })
+    .unwrap()
+}
+
+// Returns the character and encounter ids of a single participant.
+fn participant(test_app: &TestApp) -> (String, String) {
+    let repository = SqliteRepository::new(&test_app.db_pool());
+    let character = Character::new(
+        String::from("Tordek"),
+        String::from("Fighter"),
+        String::from("Dwarf"),
+        None,
+        3,
+        0,
+        28,
+        16,
+        String::new(),
+    );
+    repository.save_character(&character).unwrap();
+    let encounter = Encounter::new(String::from("Goblin ambush"));
+    repository.save_encounter(&encounter).unwrap();
+    add_character_to_encounter(&repository, encounter.id, character.id, None).unwrap();
+
+    (character.id.to_string(), encounter.id.to_string())
+}
+
+#[test]
+fn deleting_a_character_removes_its_participants() {
+    let test_app = TestApp::new();
+    let (character_id, _) = participant(&test_app);
+    let conn = test_app.db_pool().get().unwrap();
+    assert_eq!(count(&conn, "encounter_characters"), 1);
+
+    conn.execute(
+        "DELETE FROM characters WHERE id = ?1",
+        params![character_id],
+    )
+    .unwrap();
+
+    assert_eq!(count(&conn, "encounter_characters"), 0);
+}
+
+#[test]
+fn rejects_participants_without_a_character() {
+    let test_app = TestApp::new();
+    let (_, encounter_id) = participant(&test_app);
+    let conn = test_app.db_pool().get().unwrap();
+
+    let result = conn.execute(
+        "INSERT INTO encounter_characters (id, encounter_id, character_id) VALUES (?1, ?2, ?3)",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            encounter_id,
+            uuid::Uuid::new_v4().to_string()
+        ],
+    );
+
+    assert!(result.is_err());
+    assert_eq!(count(&conn, "encounter_characters"), 1);
+}
+
+#[test]
+fn integrity_report_lists_orphaned_rows() {
+    let test_app = TestApp::new();
+    let (_, encounter_id) = participant(&test_app);
+
+    // A plain connection has foreign keys off, like databases written before they were enforced.
+    let db_path = test_app.configuration().lock().unwrap().db_path.clone();
+    let unchecked = Connection::open(db_path).unwrap();
+    unchecked
+        .execute(
+            "INSERT INTO encounter_characters (id, encounter_id, character_id) VALUES (?1, ?2, ?3)",
+            params![
+                uuid::Uuid::new_v4().to_string(),
+                encounter_id,
+                uuid::Uuid::new_v4().to_string()
+            ],
+        )
+        .unwrap();
+
+    let report: Value =
+        serde_json::from_str(&check_database_integrity_command(test_app.database()).unwrap())
+            .unwrap();
+    let orphaned_rows = report["orphaned_rows"].as_array().unwrap();
+
+    assert_eq!(orphaned_rows.len(), 1);
+    assert_eq!(orphaned_rows[0]["table"], "encounter_characters");
+    assert_eq!(orphaned_rows[0]["parent_table"], "characters");
+}
+
+#[test]
+fn failed_migrations_are_reported_instead_of_panicking() {
+    let directory = std::env::temp_dir().join(format!("dm-companion-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let configuration = Configuration::load(ConfigurationOverrides {
+        data_directory: Some(directory.clone()),
+        profile: None,
+    })
+    .unwrap();
+
+    // Claims the first version without any of its tables, so the next migration cannot run.
+    Connection::open(&configuration.db_path)
+        .unwrap()
+        .pragma_update(None, "user_version", 1)
+        .unwrap();
+    let result = storage::setup_database(&configuration);
+    let _ = std::fs::remove_dir_all(&directory);
+
+    assert!(result
+        .err()
+        .is_some_and(|e| e.contains("Migration to version 2 failed")));
+}
+
+#[test]
+fn migrating_keeps_orphaned_rows_for_the_integrity_check() {
+    let directory = std::env::temp_dir().join(format!("dm-companion-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let configuration = Configuration::load(ConfigurationOverrides {
+        data_directory: Some(directory.clone()),
+        profile: None,
+    })
+    .unwrap();
+
+    // The first version had no foreign keys, so nothing stopped these rows from losing their parents.
+    let conn = Connection::open(&configuration.db_path).unwrap();
+    conn.execute_batch(
+        "
+        CREATE TABLE characters (
+            id UUID PRIMARY KEY, name TEXT NOT NULL, class TEXT NOT NULL, race TEXT NOT NULL,
+            background TEXT, level INTEGER NOT NULL, experience INTEGER NOT NULL,
+            hit_points INTEGER NOT NULL, current_hit_points INTEGER NOT NULL,
+            armor_class INTEGER NOT NULL, initiative INTEGER, alive BOOLEAN NOT NULL,
+            notes TEXT NOT NULL, created_at_utc TIMESTAMP WITH TIME ZONE NOT NULL,
+            updated_at_utc TIMESTAMP WITH TIME ZONE NOT NULL
+        );
+        CREATE TABLE encounters (id TEXT PRIMARY KEY, encounter_title TEXT NOT NULL);
+        CREATE TABLE encounter_characters (
+            id TEXT PRIMARY KEY, encounter_id TEXT NOT NULL, character_id TEXT NOT NULL,
+            initiative INTEGER
+        );
+        CREATE TABLE encounter_templates (id TEXT PRIMARY KEY, template_title TEXT NOT NULL);
+        CREATE TABLE encounter_template_combatants (
+            id TEXT PRIMARY KEY, template_id TEXT NOT NULL, name TEXT NOT NULL,
+            class TEXT NOT NULL, race TEXT NOT NULL, level INTEGER NOT NULL,
+            hit_points INTEGER NOT NULL, hit_dice TEXT, armor_class INTEGER NOT NULL,
+            count INTEGER NOT NULL, notes TEXT NOT NULL
+        );
+        INSERT INTO encounter_characters VALUES ('participant', 'missing', 'missing', NULL);
+        INSERT INTO encounter_template_combatants
+        VALUES ('combatant', 'missing', 'Goblin', 'Monster', 'Goblinoid', 1, 7, NULL, 15, 1, '');
+        PRAGMA user_version = 1;
+        ",
+    )
+    .unwrap();
+    drop(conn);
+
+    let pool = storage::setup_database(&configuration).unwrap();
+    let conn = pool.get().unwrap();
+    let orphaned_tables: Vec<String> = conn
+        .prepare("SELECT DISTINCT \"table\" FROM pragma_foreign_key_check ORDER BY 1")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<rusqlite::Result<_>>()
+        .unwrap();
+    let counts = (
+        count(&conn, "encounter_characters"),
+        count(&conn, "encounter_template_combatants"),
+    );
+    drop(conn);
+    drop(pool);
+    let _ = std::fs::remove_dir_all(&directory);
+
+    assert_eq!(counts, (1, 1));
+    assert_eq!(
+        orphaned_tables,
+        vec!["encounter_characters", "encounter_template_combatants"]
+    );
+}
+
+fn pragma<T: rusqlite::types::FromSql>(conn: &Connection, name: &str) -> T {
+    conn.query_row(&format!("PRAGMA {}", name), [], |row| row.get(0))
+        .unwrap()
+}
+
+// Returns journal_mode, busy_timeout, foreign_keys and synchronous as read from the connection.
+fn pragmas(conn: &Connection) -> (String, i64, i64, i64) {
+    (
+        pragma(conn, "journal_mode"),
+        pragma(conn, "busy_timeout"),
+        pragma(conn, "foreign_keys"),
+        pragma(conn, "synchronous"),
+    )
+}
+
+#[test]
+fn pooled_connections_use_the_configured_pragmas() {
+    let test_app = TestApp::new();
+    let db_pool = test_app.db_pool();
+    // Hold two connections so both come from the pool's initialiser.
+    let first = db_pool.get().unwrap();
+    let second = db_pool.get().unwrap();
+
+    for conn in [&first, &second] {
+        assert_eq!(pragmas(conn), (String::from("wal"), 5000, 1, 1));
+    }
+
+    let mut configuration = Configuration::load(ConfigurationOverrides {
+        data_directory: Some(test_app.directory().join("tuned")),
+        profile: None,
+    })
+    .unwrap();
+    configuration.database.journal_mode = JournalMode::Truncate;
+    configuration.database.busy_timeout_ms = 1234;
+    configuration.database.foreign_keys = false;
+    configuration.database.synchronous = Synchronous::Full;
+    let tuned = storage::setup_database(&configuration).unwrap();
+
+    assert_eq!(
+        pragmas(&tuned.get().unwrap()),
+        (String::from("truncate"), 1234, 0, 2)
+    );
+}
@@ -93,10 +93,11 @@ const NewCharacterForm: React.FC = () => {
                 'create_character_command',
                 {
                     name: values.name,
-                    class: values.class,
+                    classLevels: [
+                        { class: values.class, subclass: null, levels: values.level }
+                    ],
                     race: values.race,
                     background: values.background,
-                    level: values.level,
                     experience: values.experience,
                     hitPoints: values.hit_points,
                     armorClass: values.armor_class,
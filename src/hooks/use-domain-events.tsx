@@ -0,0 +1,33 @@
+import * as React from "react"
+import { useQueryClient } from "@tanstack/react-query"
+import { listen } from "@tauri-apps/api/event"
+
+// Queries to reload when the backend reports a change, so every window stays current.
+const INVALIDATIONS: Record<string, string[][]> = {
+  character_created: [["characters"], ["availableCharactersForEncounter"]],
+  character_updated: [["characters"], ["encounterDetail"]],
+  encounter_created: [["encounters"]],
+  encounter_updated: [["encounters"], ["encounterDetail"]],
+  encounter_participant_added: [["encounterDetail"], ["availableCharactersForEncounter"]],
+  encounter_participant_updated: [["encounterDetail"], ["characters"]],
+  turn_advanced: [["encounterDetail"]],
+}
+
+export function useDomainEvents() {
+  const queryClient = useQueryClient()
+
+  React.useEffect(() => {
+    const unlisteners = Object.entries(INVALIDATIONS).map(([event, queryKeys]) =>
+      listen(event, () => {
+        queryKeys.forEach((queryKey) => queryClient.invalidateQueries({ queryKey }))
+      })
+    )
+
+    // A switched profile means different data everywhere.
+    unlisteners.push(listen("profile_switched", () => queryClient.invalidateQueries()))
+
+    return () => {
+      unlisteners.forEach((unlisten) => unlisten.then((f) => f()))
+    }
+  }, [queryClient])
+}
@@ -11,6 +11,7 @@
 // Import Routes
 
 import { Route as rootRoute } from './routes/__root'
+import { Route as PlayerImport } from './routes/player'
 import { Route as CharactersImport } from './routes/characters'
 import { Route as IndexImport } from './routes/index'
 import { Route as EncountersIndexImport } from './routes/encounters/index'
@@ -18,6 +19,12 @@ import { Route as EncountersEncounterIdImport } from './routes/encounters/$encou
 
 // Create/Update Routes
 
+const PlayerRoute = PlayerImport.update({
+  id: '/player',
+  path: '/player',
+  getParentRoute: () => rootRoute,
+} as any)
+
 const CharactersRoute = CharactersImport.update({
   id: '/characters',
   path: '/characters',
@@ -60,6 +67,13 @@ declare module '@tanstack/react-router' {
       preLoaderRoute: typeof CharactersImport
       parentRoute: typeof rootRoute
     }
+    '/player': {
+      id: '/player'
+      path: '/player'
+      fullPath: '/player'
+      preLoaderRoute: typeof PlayerImport
+      parentRoute: typeof rootRoute
+    }
     '/encounters/$encounterId': {
       id: '/encounters/$encounterId'
       path: '/encounters/$encounterId'
@@ -82,6 +96,7 @@ declare module '@tanstack/react-router' {
 export interface FileRoutesByFullPath {
   '/': typeof IndexRoute
   '/characters': typeof CharactersRoute
+  '/player': typeof PlayerRoute
   '/encounters/$encounterId': typeof EncountersEncounterIdRoute
   '/encounters': typeof EncountersIndexRoute
 }
@@ -89,6 +104,7 @@ export interface FileRoutesByFullPath {
 export interface FileRoutesByTo {
   '/': typeof IndexRoute
   '/characters': typeof CharactersRoute
+  '/player': typeof PlayerRoute
   '/encounters/$encounterId': typeof EncountersEncounterIdRoute
   '/encounters': typeof EncountersIndexRoute
 }
@@ -97,19 +113,31 @@ export interface FileRoutesById {
   __root__: typeof rootRoute
   '/': typeof IndexRoute
   '/characters': typeof CharactersRoute
+  '/player': typeof PlayerRoute
   '/encounters/$encounterId': typeof EncountersEncounterIdRoute
   '/encounters/': typeof EncountersIndexRoute
 }
 
 export interface FileRouteTypes {
   fileRoutesByFullPath: FileRoutesByFullPath
-  fullPaths: '/' | '/characters' | '/encounters/$encounterId' | '/encounters'
+  fullPaths:
+    | '/'
+    | '/characters'
+    | '/player'
+    | '/encounters/$encounterId'
+    | '/encounters'
   fileRoutesByTo: FileRoutesByTo
-  to: '/' | '/characters' | '/encounters/$encounterId' | '/encounters'
+  to:
+    | '/'
+    | '/characters'
+    | '/player'
+    | '/encounters/$encounterId'
+    | '/encounters'
   id:
     | '__root__'
     | '/'
     | '/characters'
+    | '/player'
     | '/encounters/$encounterId'
     | '/encounters/'
   fileRoutesById: FileRoutesById
@@ -118,6 +146,7 @@ export interface FileRouteTypes {
 export interface RootRouteChildren {
   IndexRoute: typeof IndexRoute
   CharactersRoute: typeof CharactersRoute
+  PlayerRoute: typeof PlayerRoute
   EncountersEncounterIdRoute: typeof EncountersEncounterIdRoute
   EncountersIndexRoute: typeof EncountersIndexRoute
 }
@@ -125,6 +154,7 @@ export interface RootRouteChildren {
 const rootRouteChildren: RootRouteChildren = {
   IndexRoute: IndexRoute,
   CharactersRoute: CharactersRoute,
+  PlayerRoute: PlayerRoute,
   EncountersEncounterIdRoute: EncountersEncounterIdRoute,
   EncountersIndexRoute: EncountersIndexRoute,
 }
@@ -141,6 +171,7 @@ export const routeTree = rootRoute
       "children": [
         "/",
         "/characters",
+        "/player",
         "/encounters/$encounterId",
         "/encounters/"
       ]
@@ -151,6 +182,9 @@ export const routeTree = rootRoute
     "/characters": {
       "filePath": "characters.tsx"
     },
+    "/player": {
+      "filePath": "player.tsx"
+    },
     "/encounters/$encounterId": {
       "filePath": "encounters/$encounterId.tsx"
     },
@@ -1,10 +1,23 @@
-import { createRootRoute, Outlet, useRouter, Link } from '@tanstack/react-router'
+import { createRootRoute, Outlet, useRouter, useRouterState, Link } from '@tanstack/react-router'
 
 import { SidebarProvider, SidebarTrigger } from "@/components/ui/sidebar"
 import { AppSidebar } from "@/components/app-sidebar"
+import { useDomainEvents } from "@/hooks/use-domain-events"
 
 export const Route = createRootRoute({
-    component: () => (
+    component: RootComponent,
+})
+
+function RootComponent() {
+    const pathname = useRouterState({ select: (state) => state.location.pathname })
+    useDomainEvents()
+
+    // The player window shows nothing but the encounter.
+    if (pathname === '/player') {
+        return <Outlet />
+    }
+
+    return (
         <>
             <SidebarProvider>
                 <AppSidebar />
@@ -13,5 +26,5 @@ export const Route = createRootRoute({
                 </main>
             </SidebarProvider>
         </>
-    ),
-})
+    )
+}
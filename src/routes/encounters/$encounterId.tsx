@@ -61,6 +61,7 @@ const EncounterDetailScreen: React.FC<EncounterDetailScreenProps> = ({ encounter
             <div className='flex container items-center'>
                 <h1>{encounterDetailQuery.data.encounter.encounter_title}</h1>
                 <div className='flex-grow' />
+                <ShowToPlayersButton encounterId={encounterDetailQuery.data.encounter.id} />
                 <AddEncounterCharacterDialog encounterId={encounterDetailQuery.data.encounter.id} />
             </div>
             <div className='flex'>
@@ -84,6 +85,26 @@ const EncounterDetailScreen: React.FC<EncounterDetailScreenProps> = ({ encounter
     )
 }
 
+interface ShowToPlayersButtonProps {
+    encounterId: string
+}
+
+const ShowToPlayersButton: React.FC<ShowToPlayersButtonProps> = ({ encounterId }) => {
+
+    const showToPlayersMutation = useMutation({
+        mutationFn: async () => {
+            await invoke('show_encounter_to_players_command', { encounterId: encounterId })
+            await invoke('open_player_window_command', {})
+        }
+    })
+
+    return (
+        <div className='mr-2'>
+            <Button variant='outline' onClick={() => showToPlayersMutation.mutate()}>Show to players</Button>
+        </div>
+    )
+}
+
 interface TrunOrderProps {
     characters: any[]
 }
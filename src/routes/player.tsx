@@ -0,0 +1,63 @@
+import * as React from 'react'
+import { createFileRoute } from '@tanstack/react-router'
+import { useQuery, useQueryClient } from '@tanstack/react-query'
+import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
+
+export const Route = createFileRoute('/player')({
+    component: RouteComponent,
+})
+
+function RouteComponent() {
+    const queryClient = useQueryClient()
+
+    const playerViewQuery = useQuery({
+        queryKey: ['playerView'],
+        queryFn: async () => {
+            let res = await invoke('load_player_view_command', {})
+            console.debug("Rust Return load_player_view_command", res)
+            return JSON.parse(res as string)
+        }
+    })
+
+    React.useEffect(() => {
+        const unlisten = listen('player_view_updated', (event) => {
+            queryClient.setQueryData(['playerView'], event.payload)
+        })
+
+        return () => {
+            unlisten.then((f) => f())
+        }
+    }, [queryClient])
+
+    if (playerViewQuery.isLoading) {
+        return <></>
+    }
+
+    if (playerViewQuery.isError) {
+        return <p>Error loading the encounter</p>
+    }
+
+    if (!playerViewQuery.data) {
+        return <p className='p-4'>Waiting for the DM...</p>
+    }
+
+    return (
+        <div className='p-4'>
+            <h1>{playerViewQuery.data.encounter_title}</h1>
+            {playerViewQuery.data.round > 0 && <p>Round {playerViewQuery.data.round}</p>}
+            {
+                playerViewQuery.data.participants.map((participant: any) => {
+                    return (
+                        <div key={participant.id} className={participant.active ? 'font-bold' : ''}>
+                            <p>
+                                {participant.name} - {participant.health}
+                                {participant.conditions.length > 0 && ' (' + participant.conditions.join(', ') + ')'}
+                            </p>
+                        </div>
+                    )
+                })
+            }
+        </div>
+    )
+}